@@ -0,0 +1,39 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Failed to read config file {path}: {reason}")]
+    File { path: String, reason: String },
+    #[error("Failed to load config: {0}")]
+    Load(String),
+    #[error("Invalid value for `{key}`: {reason}")]
+    Invalid { key: String, reason: String },
+}
+
+impl ConfigError {
+    pub fn invalid(key: &str, reason: impl Into<String>) -> Self {
+        ConfigError::Invalid {
+            key: key.to_string(),
+            reason: reason.into(),
+        }
+    }
+}
+
+impl From<figment::Error> for ConfigError {
+    fn from(err: figment::Error) -> Self {
+        let source = err
+            .metadata
+            .as_ref()
+            .map(|metadata| format!(" (from {})", metadata.name))
+            .unwrap_or_default();
+        let reason = format!("{}{source}", err.kind);
+        if err.path.is_empty() {
+            ConfigError::Load(reason)
+        } else {
+            ConfigError::Invalid {
+                key: err.path.join("."),
+                reason,
+            }
+        }
+    }
+}
@@ -0,0 +1,287 @@
+use std::{env, path::PathBuf};
+
+use clap::Args;
+use figment::{
+    Figment, Metadata, Profile, Provider,
+    providers::{Format, Serialized, Toml},
+    value::{Dict, Map, Value},
+};
+
+use super::{Config, errors::ConfigError};
+
+/// Environment variables read into the config, with the key each one sets.
+pub const ENV_VARS: &[(&str, &str)] = &[
+    ("SERVER_HOST", "server.host"),
+    ("PORT", "server.port"),
+    ("RUST_LOG", "server.log_level"),
+    ("CONFIG_WATCH_SECS", "server.config_watch_secs"),
+    ("SHUTDOWN_DELAY_SECS", "server.shutdown_delay_secs"),
+    ("SHUTDOWN_TIMEOUT_SECS", "server.shutdown_timeout_secs"),
+    ("CLICKHOUSE_URL", "clickhouse.url"),
+    ("CLICKHOUSE_USER", "clickhouse.user"),
+    ("CLICKHOUSE_PASSWORD", "clickhouse.password"),
+    ("CLICKHOUSE_DB", "clickhouse.database"),
+    ("CLICKHOUSE_TABLE_PREFIX", "clickhouse.table_prefix"),
+    ("CLICKHOUSE_CLUSTER", "clickhouse.cluster"),
+    ("CLICKHOUSE_CA_CERT", "clickhouse.ca_cert"),
+    ("CLICKHOUSE_CLIENT_CERT", "clickhouse.client_cert"),
+    ("CLICKHOUSE_CLIENT_KEY", "clickhouse.client_key"),
+    ("CLICKHOUSE_COMPRESSION", "clickhouse.compression"),
+    (
+        "CLICKHOUSE_CONNECT_TIMEOUT_MS",
+        "clickhouse.connect_timeout_ms",
+    ),
+    (
+        "CLICKHOUSE_REQUEST_TIMEOUT_MS",
+        "clickhouse.request_timeout_ms",
+    ),
+    ("CLICKHOUSE_POOL_MAX_IDLE", "clickhouse.pool_max_idle"),
+    (
+        "CLICKHOUSE_POOL_IDLE_TIMEOUT_MS",
+        "clickhouse.pool_idle_timeout_ms",
+    ),
+    ("DB_RETRY_MAX_ATTEMPTS", "clickhouse.retry.max_attempts"),
+    ("DB_RETRY_BASE_DELAY_MS", "clickhouse.retry.base_delay_ms"),
+    ("DB_RETRY_MAX_DELAY_MS", "clickhouse.retry.max_delay_ms"),
+    ("DB_RETRY_INSERTS", "clickhouse.retry.inserts"),
+    (
+        "DB_STARTUP_MAX_ATTEMPTS",
+        "clickhouse.retry.startup_max_attempts",
+    ),
+    (
+        "DB_BREAKER_FAILURE_THRESHOLD",
+        "clickhouse.breaker.failure_threshold",
+    ),
+    ("DB_BREAKER_OPEN_SECS", "clickhouse.breaker.open_secs"),
+    ("INSERT_MAX_ROWS", "clickhouse.insert.max_rows"),
+    ("INSERT_MAX_BYTES", "clickhouse.insert.max_bytes"),
+    ("INSERT_PARALLELISM", "clickhouse.insert.parallelism"),
+    ("INSERT_ASYNC", "clickhouse.insert.async"),
+    (
+        "STATS_MAX_EXECUTION_TIME_SECS",
+        "clickhouse.stats.max_execution_time",
+    ),
+    (
+        "STATS_MAX_MEMORY_USAGE",
+        "clickhouse.stats.max_memory_usage",
+    ),
+    ("GENERATOR_MIN_AMOUNT", "generator.min_amount"),
+    ("GENERATOR_MAX_AMOUNT", "generator.max_amount"),
+    ("GENERATOR_MIN_PRICE", "generator.min_price"),
+    ("GENERATOR_MAX_PRICE", "generator.max_price"),
+    ("GENERATOR_MAX_AGE_SECS", "generator.max_age_secs"),
+    ("GENERATOR_ADDRESS_POOL_SIZE", "generator.address_pool_size"),
+    ("DATA_GENERATION_MODE", "jobs.data_generation_mode"),
+    ("DATA_GENERATION_COUNT", "jobs.data_generation_count"),
+    ("JOBS_INTEGRITY_CHECK", "jobs.integrity_check"),
+    ("CLUSTER_OWNERSHIP_FILE", "analysis.ownership_file"),
+    (
+        "CLUSTER_MIN_MUTUAL_TRANSFERS",
+        "analysis.min_mutual_transfers",
+    ),
+    ("GRAPH_MAX_EDGES", "analysis.graph_max_edges"),
+    ("TRANSFER_ADDRESS_FORMAT", "validation.address_format"),
+    ("BUFFER_DIR", "buffer.dir"),
+    ("BUFFER_MAX_BYTES", "buffer.max_bytes"),
+    ("BUFFER_SEGMENT_BYTES", "buffer.segment_bytes"),
+    ("BUFFER_FSYNC", "buffer.fsync"),
+    ("KAFKA_BROKERS", "kafka.brokers"),
+    ("KAFKA_TOPIC", "kafka.topic"),
+    ("KAFKA_GROUP_ID", "kafka.group_id"),
+    ("KAFKA_FORMAT", "kafka.format"),
+    ("KAFKA_DEAD_LETTER_TOPIC", "kafka.dead_letter_topic"),
+    ("KAFKA_BATCH_SIZE", "kafka.batch_size"),
+    ("KAFKA_BATCH_WAIT_MS", "kafka.batch_wait_ms"),
+    ("EVM_RPC_URL", "evm.rpc_url"),
+    ("EVM_TOKEN_ADDRESS", "evm.token_address"),
+    ("EVM_TOKEN_DECIMALS", "evm.token_decimals"),
+    ("EVM_USD_PRICE", "evm.usd_price"),
+    ("EVM_START_BLOCK", "evm.start_block"),
+    ("EVM_CONFIRMATIONS", "evm.confirmations"),
+    ("EVM_REORG_DEPTH", "evm.reorg_depth"),
+    ("EVM_MAX_BLOCK_RANGE", "evm.max_block_range"),
+    ("EVM_POLL_INTERVAL_SECS", "evm.poll_interval_secs"),
+    ("STREAM_HEARTBEAT_SECS", "stream.heartbeat_secs"),
+    ("STREAM_CAPACITY", "stream.capacity"),
+    ("STREAM_MAX_ADDRESSES", "stream.max_addresses"),
+];
+
+/// Command line flags shared by every mode that needs a config.
+#[derive(Debug, Clone, Default, Args)]
+pub struct ConfigArgs {
+    /// TOML config file; `CONFIG_FILE` when not given.
+    #[arg(long, value_name = "PATH", global = true)]
+    pub config: Option<PathBuf>,
+    /// Shorthand for `--set server.port=<PORT>`.
+    #[arg(long, global = true)]
+    pub port: Option<u16>,
+    /// Overrides any key, e.g. `--set clickhouse.retry.max_attempts=5`.
+    #[arg(
+        long = "set",
+        value_name = "KEY=VALUE",
+        value_parser = parse_override,
+        global = true
+    )]
+    pub overrides: Vec<(String, String)>,
+}
+
+impl ConfigArgs {
+    pub fn config_file(&self) -> Option<PathBuf> {
+        self.config
+            .clone()
+            .or_else(|| env::var_os("CONFIG_FILE").map(PathBuf::from))
+    }
+}
+
+fn parse_override(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected KEY=VALUE, got `{arg}`")),
+    }
+}
+
+/// Stacks defaults, the config file, the environment and `args`, each
+/// overriding the previous ones.
+pub fn layers(args: &ConfigArgs) -> Result<Figment, ConfigError> {
+    dotenv::dotenv().ok();
+
+    let mut figment = Figment::from(Serialized::defaults(Config::default()));
+    if let Some(path) = args.config_file() {
+        if !path.is_file() {
+            return Err(ConfigError::File {
+                path: path.display().to_string(),
+                reason: "no such file".to_string(),
+            });
+        }
+        figment = figment.merge(Toml::file(path));
+    }
+
+    let env = ENV_VARS
+        .iter()
+        .filter_map(|(name, key)| {
+            env::var(name)
+                .ok()
+                .filter(|value| !value.is_empty())
+                .map(|value| (key.to_string(), value))
+        })
+        .collect();
+
+    let mut flags = args.overrides.clone();
+    if let Some(port) = args.port {
+        flags.push(("server.port".to_string(), port.to_string()));
+    }
+
+    Ok(figment
+        .merge(TextValues::new("environment variables", env))
+        .merge(TextValues::new("command line", flags)))
+}
+
+/// Values that arrive as text and only get their type when the config is
+/// extracted, so a numeric password stays a string.
+struct TextValues {
+    source: &'static str,
+    values: Vec<(String, String)>,
+}
+
+impl TextValues {
+    fn new(source: &'static str, values: Vec<(String, String)>) -> Self {
+        Self { source, values }
+    }
+}
+
+impl Provider for TextValues {
+    fn metadata(&self) -> Metadata {
+        Metadata::named(self.source)
+    }
+
+    fn data(&self) -> Result<Map<Profile, Dict>, figment::Error> {
+        let mut dict = Dict::new();
+        for (key, value) in &self.values {
+            insert(&mut dict, key, key, value).map_err(figment::Error::from)?;
+        }
+        Ok(Profile::Default.collect(dict))
+    }
+}
+
+fn insert(dict: &mut Dict, path: &str, key: &str, value: &str) -> Result<(), String> {
+    let Some((section, rest)) = key.split_once('.') else {
+        dict.insert(key.to_string(), Value::from(value.to_string()));
+        return Ok(());
+    };
+
+    match dict
+        .entry(section.to_string())
+        .or_insert_with(|| Value::from(Dict::new()))
+    {
+        Value::Dict(_, inner) => insert(inner, path, rest, value),
+        _ => Err(format!("`{path}` is set both as a value and as a section")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::validation::rules::AddressFormat;
+
+    fn extract(values: &[(&str, &str)]) -> Result<Config, ConfigError> {
+        let values = values
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        Ok(Figment::from(Serialized::defaults(Config::default()))
+            .merge(TextValues::new("command line", values))
+            .extract_lossy()?)
+    }
+
+    #[test]
+    fn test_text_values_take_the_field_type() {
+        let config = extract(&[
+            ("clickhouse.password", "123"),
+            ("server.port", "9000"),
+            ("clickhouse.insert.async", "true"),
+            ("clickhouse.stats.max_execution_time", "30"),
+            ("validation.address_format", "evm"),
+        ])
+        .unwrap();
+
+        assert_eq!(config.clickhouse.password, "123");
+        assert_eq!(config.server.port, 9000);
+        assert!(config.clickhouse.insert.async_insert);
+        assert_eq!(config.clickhouse.stats.max_execution_time, Some(30));
+        assert_eq!(config.validation.address_format, AddressFormat::Evm);
+    }
+
+    #[test]
+    fn test_text_value_errors_name_the_key() {
+        match extract(&[("clickhouse.retry.max_attempts", "many")]) {
+            Err(ConfigError::Invalid { key, reason }) => {
+                assert_eq!(key, "clickhouse.retry.max_attempts");
+                assert!(reason.contains("command line"));
+            }
+            other => panic!("expected an invalid key, got {other:?}"),
+        }
+        assert!(extract(&[("server", "1"), ("server.port", "1")]).is_err());
+    }
+
+    #[test]
+    fn test_parse_override() {
+        assert_eq!(
+            parse_override("server.port=1=2"),
+            Ok(("server.port".to_string(), "1=2".to_string()))
+        );
+        assert!(parse_override("server.port").is_err());
+        assert!(parse_override("=1").is_err());
+    }
+
+    #[test]
+    fn test_env_vars_name_existing_keys() {
+        for (name, key) in ENV_VARS {
+            if let Err(ConfigError::Invalid { reason, .. }) = extract(&[(key, "1")]) {
+                assert!(
+                    !reason.contains("unknown field"),
+                    "{name} sets unknown {key}"
+                );
+            }
+        }
+    }
+}
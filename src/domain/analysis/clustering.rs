@@ -0,0 +1,207 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::domain::entities::{
+    cluster::{Cluster, OwnershipLabel},
+    graph::GraphEdge,
+};
+
+#[derive(Debug, Clone)]
+pub struct ClusteringConfig {
+    /// Minimum number of transfers in *each* direction for two addresses to
+    /// be considered funding each other. `0` disables the heuristic.
+    pub min_mutual_transfers: u64,
+    pub ownership: Vec<OwnershipLabel>,
+}
+
+impl Default for ClusteringConfig {
+    fn default() -> Self {
+        Self {
+            min_mutual_transfers: 3,
+            ownership: Vec::new(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct UnionFind {
+    index: HashMap<String, usize>,
+    names: Vec<String>,
+    parent: Vec<usize>,
+    rank: Vec<u8>,
+}
+
+impl UnionFind {
+    fn insert(&mut self, address: &str) -> usize {
+        if let Some(&idx) = self.index.get(address) {
+            return idx;
+        }
+        let idx = self.names.len();
+        self.index.insert(address.to_string(), idx);
+        self.names.push(address.to_string());
+        self.parent.push(idx);
+        self.rank.push(0);
+        idx
+    }
+
+    fn find(&mut self, mut idx: usize) -> usize {
+        while self.parent[idx] != idx {
+            self.parent[idx] = self.parent[self.parent[idx]];
+            idx = self.parent[idx];
+        }
+        idx
+    }
+
+    fn union(&mut self, a: &str, b: &str) {
+        let a = self.insert(a);
+        let b = self.insert(b);
+        let (root_a, root_b) = (self.find(a), self.find(b));
+        if root_a == root_b {
+            return;
+        }
+        match self.rank[root_a].cmp(&self.rank[root_b]) {
+            std::cmp::Ordering::Less => self.parent[root_a] = root_b,
+            std::cmp::Ordering::Greater => self.parent[root_b] = root_a,
+            std::cmp::Ordering::Equal => {
+                self.parent[root_b] = root_a;
+                self.rank[root_a] += 1;
+            }
+        }
+    }
+}
+
+/// Groups addresses believed to share an owner. Only clusters with more than
+/// one address are returned; the cluster id is its smallest member address so
+/// that it stays stable between runs.
+pub fn cluster_addresses(edges: &[GraphEdge], config: &ClusteringConfig) -> Vec<Cluster> {
+    let mut union_find = UnionFind::default();
+
+    if config.min_mutual_transfers > 0 {
+        let counts: HashMap<(&str, &str), u64> = edges
+            .iter()
+            .map(|edge| ((edge.from.as_str(), edge.to.as_str()), edge.transfer_count))
+            .collect();
+
+        for (&(from, to), &count) in &counts {
+            if from >= to || count < config.min_mutual_transfers {
+                continue;
+            }
+            let reverse = counts.get(&(to, from)).copied().unwrap_or(0);
+            if reverse >= config.min_mutual_transfers {
+                union_find.union(from, to);
+            }
+        }
+    }
+
+    let mut owners: HashMap<&str, &str> = HashMap::new();
+    for label in &config.ownership {
+        match owners.get(label.owner.as_str()) {
+            Some(first) => union_find.union(first, &label.address),
+            None => {
+                union_find.insert(&label.address);
+                owners.insert(&label.owner, &label.address);
+            }
+        }
+    }
+
+    let mut groups: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+    for idx in 0..union_find.names.len() {
+        let root = union_find.find(idx);
+        groups
+            .entry(root)
+            .or_default()
+            .push(union_find.names[idx].clone());
+    }
+
+    let mut clusters: Vec<Cluster> = groups
+        .into_values()
+        .filter(|addresses| addresses.len() > 1)
+        .map(|mut addresses| {
+            addresses.sort();
+            Cluster {
+                cluster_id: addresses[0].clone(),
+                addresses,
+            }
+        })
+        .collect();
+
+    clusters.sort_by(|a, b| a.cluster_id.cmp(&b.cluster_id));
+    clusters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edge(from: &str, to: &str, transfer_count: u64) -> GraphEdge {
+        GraphEdge {
+            from: from.to_string(),
+            to: to.to_string(),
+            total_volume: 10.0 * transfer_count as f64,
+            transfer_count,
+        }
+    }
+
+    fn label(address: &str, owner: &str) -> OwnershipLabel {
+        OwnershipLabel {
+            address: address.to_string(),
+            owner: owner.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_mutual_funding_merges_addresses() {
+        let edges = vec![
+            edge("0xA", "0xB", 3),
+            edge("0xB", "0xA", 4),
+            edge("0xB", "0xC", 3),
+            edge("0xC", "0xB", 3),
+            edge("0xD", "0xE", 10),
+        ];
+
+        let clusters = cluster_addresses(&edges, &ClusteringConfig::default());
+
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].cluster_id, "0xA");
+        assert_eq!(clusters[0].addresses, vec!["0xA", "0xB", "0xC"]);
+    }
+
+    #[test]
+    fn test_one_sided_funding_is_ignored() {
+        let edges = vec![edge("0xA", "0xB", 3), edge("0xB", "0xA", 2)];
+
+        let clusters = cluster_addresses(&edges, &ClusteringConfig::default());
+
+        assert!(clusters.is_empty());
+    }
+
+    #[test]
+    fn test_ownership_labels_merge_addresses() {
+        let config = ClusteringConfig {
+            min_mutual_transfers: 0,
+            ownership: vec![
+                label("0xB", "exchange"),
+                label("0xD", "exchange"),
+                label("0xA", "alice"),
+            ],
+        };
+
+        let clusters = cluster_addresses(&[edge("0xA", "0xB", 5)], &config);
+
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].addresses, vec!["0xB", "0xD"]);
+    }
+
+    #[test]
+    fn test_heuristics_combine_transitively() {
+        let edges = vec![edge("0xA", "0xB", 3), edge("0xB", "0xA", 3)];
+        let config = ClusteringConfig {
+            min_mutual_transfers: 3,
+            ownership: vec![label("0xB", "team"), label("0xZ", "team")],
+        };
+
+        let clusters = cluster_addresses(&edges, &config);
+
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].addresses, vec!["0xA", "0xB", "0xZ"]);
+    }
+}
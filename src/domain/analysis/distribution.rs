@@ -0,0 +1,181 @@
+use crate::domain::entities::holder::{AddressBalance, BalanceBucket, HolderDistribution};
+
+/// Summarises how balances are spread across holders. Only addresses with a
+/// positive balance count as holders.
+pub fn holder_distribution(
+    balances: Vec<AddressBalance>,
+    top_n: usize,
+    as_of: Option<u64>,
+) -> HolderDistribution {
+    let mut holders: Vec<AddressBalance> = balances
+        .into_iter()
+        .filter(|holder| holder.balance.is_finite() && holder.balance > 0.0)
+        .collect();
+    holders.sort_by(|a, b| {
+        b.balance
+            .total_cmp(&a.balance)
+            .then_with(|| a.address.cmp(&b.address))
+    });
+
+    let total_supply: f64 = holders.iter().map(|holder| holder.balance).sum();
+    let values: Vec<f64> = holders.iter().map(|holder| holder.balance).collect();
+
+    HolderDistribution {
+        as_of,
+        holder_count: holders.len() as u64,
+        total_supply,
+        gini_coefficient: gini(&values),
+        nakamoto_coefficient: nakamoto(&values),
+        histogram: histogram(&values),
+        top_holders: holders.into_iter().take(top_n).collect(),
+    }
+}
+
+/// Gini coefficient of `values` sorted in descending order.
+fn gini(values: &[f64]) -> f64 {
+    let n = values.len() as f64;
+    let total: f64 = values.iter().sum();
+    if values.is_empty() || total <= 0.0 {
+        return 0.0;
+    }
+
+    // Ascending rank i (1-based) of the element at descending position idx
+    // is n - idx.
+    let weighted: f64 = values
+        .iter()
+        .enumerate()
+        .map(|(idx, value)| (n - idx as f64) * value)
+        .sum();
+
+    (2.0 * weighted / (n * total) - (n + 1.0) / n).max(0.0)
+}
+
+/// Smallest number of holders that together hold more than half of the
+/// supply, for `values` sorted in descending order.
+fn nakamoto(values: &[f64]) -> u64 {
+    let total: f64 = values.iter().sum();
+    let mut cumulative = 0.0;
+    for (idx, value) in values.iter().enumerate() {
+        cumulative += value;
+        if cumulative > total / 2.0 {
+            return idx as u64 + 1;
+        }
+    }
+    0
+}
+
+/// Power-of-ten buckets `[10^k, 10^(k+1))` covering every holder.
+fn histogram(values: &[f64]) -> Vec<BalanceBucket> {
+    let mut buckets: Vec<(i32, BalanceBucket)> = Vec::new();
+
+    for &value in values {
+        let exponent = value.log10().floor() as i32;
+        let idx = match buckets.iter().position(|(exp, _)| *exp == exponent) {
+            Some(idx) => idx,
+            None => {
+                buckets.push((
+                    exponent,
+                    BalanceBucket {
+                        min_balance: 10f64.powi(exponent),
+                        max_balance: 10f64.powi(exponent + 1),
+                        holders: 0,
+                        total_balance: 0.0,
+                    },
+                ));
+                buckets.len() - 1
+            }
+        };
+        buckets[idx].1.holders += 1;
+        buckets[idx].1.total_balance += value;
+    }
+
+    buckets.sort_by_key(|(exponent, _)| *exponent);
+    buckets.into_iter().map(|(_, bucket)| bucket).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn balance(address: &str, balance: f64) -> AddressBalance {
+        AddressBalance {
+            address: address.to_string(),
+            balance,
+        }
+    }
+
+    #[test]
+    fn test_equal_balances() {
+        let balances = vec![
+            balance("0xA", 10.0),
+            balance("0xB", 10.0),
+            balance("0xC", 10.0),
+        ];
+
+        let distribution = holder_distribution(balances, 10, None);
+
+        assert_eq!(distribution.holder_count, 3);
+        assert!(distribution.gini_coefficient.abs() < 1e-9);
+        assert_eq!(distribution.nakamoto_coefficient, 2);
+    }
+
+    #[test]
+    fn test_concentrated_balances() {
+        let balances = vec![
+            balance("0xA", 970.0),
+            balance("0xB", 10.0),
+            balance("0xC", 10.0),
+            balance("0xD", 10.0),
+        ];
+
+        let distribution = holder_distribution(balances, 2, Some(1_000));
+
+        assert_eq!(distribution.nakamoto_coefficient, 1);
+        assert!((distribution.gini_coefficient - 0.72).abs() < 1e-9);
+        assert_eq!(distribution.top_holders.len(), 2);
+        assert_eq!(distribution.top_holders[0].address, "0xA");
+        assert_eq!(distribution.as_of, Some(1_000));
+    }
+
+    #[test]
+    fn test_ignores_non_positive_balances() {
+        let balances = vec![
+            balance("0xA", 5.0),
+            balance("0xB", 0.0),
+            balance("0xC", -3.0),
+        ];
+
+        let distribution = holder_distribution(balances, 10, None);
+
+        assert_eq!(distribution.holder_count, 1);
+        assert_eq!(distribution.total_supply, 5.0);
+    }
+
+    #[test]
+    fn test_histogram_buckets() {
+        let balances = vec![
+            balance("0xA", 0.5),
+            balance("0xB", 5.0),
+            balance("0xC", 7.0),
+            balance("0xD", 250.0),
+        ];
+
+        let histogram = holder_distribution(balances, 10, None).histogram;
+
+        assert_eq!(histogram.len(), 3);
+        assert!((histogram[0].min_balance - 0.1).abs() < 1e-12);
+        assert_eq!(histogram[1].holders, 2);
+        assert_eq!(histogram[1].total_balance, 12.0);
+        assert_eq!(histogram[2].min_balance, 100.0);
+    }
+
+    #[test]
+    fn test_empty_distribution() {
+        let distribution = holder_distribution(Vec::new(), 10, None);
+
+        assert_eq!(distribution.holder_count, 0);
+        assert_eq!(distribution.gini_coefficient, 0.0);
+        assert_eq!(distribution.nakamoto_coefficient, 0);
+        assert!(distribution.histogram.is_empty());
+    }
+}
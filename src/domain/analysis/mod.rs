@@ -0,0 +1,3 @@
+pub mod clustering;
+pub mod distribution;
+pub mod wash_trading;
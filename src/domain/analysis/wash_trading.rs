@@ -0,0 +1,279 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use serde::Deserialize;
+
+use crate::domain::entities::{transfer::Transfer, wash_trading::WashTradingScore};
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct WashTradingConfig {
+    /// Maximum time between the first and the closing leg of a cycle.
+    pub window_secs: u64,
+    /// Maximum relative difference between leg amounts, e.g. `0.05` for 5%.
+    pub amount_tolerance: f64,
+}
+
+impl Default for WashTradingConfig {
+    fn default() -> Self {
+        Self {
+            window_secs: 3_600,
+            amount_tolerance: 0.05,
+        }
+    }
+}
+
+#[derive(Default)]
+struct AddressCycles {
+    cycles: u64,
+    flagged: HashSet<usize>,
+}
+
+/// Scores addresses by the cycles they take part in. Cycles are taken in
+/// time order and a transfer closes at most one of them, so a run of
+/// ping-pong transfers counts each cycle once rather than every overlapping
+/// pair.
+pub fn detect_wash_trading(
+    transfers: &[Transfer],
+    config: &WashTradingConfig,
+) -> Vec<WashTradingScore> {
+    let mut order: Vec<usize> = (0..transfers.len()).collect();
+    order.sort_by_key(|&idx| transfers[idx].ts);
+
+    let mut outgoing: HashMap<&str, Vec<usize>> = HashMap::new();
+    for &idx in &order {
+        outgoing
+            .entry(transfers[idx].from.as_str())
+            .or_default()
+            .push(idx);
+    }
+
+    let mut cycles: HashMap<&str, AddressCycles> = HashMap::new();
+    let mut used = vec![false; transfers.len()];
+    let mut record = |used: &mut [bool], legs: &[usize]| {
+        for &leg in legs {
+            used[leg] = true;
+        }
+        let members: BTreeSet<&str> = legs
+            .iter()
+            .map(|&leg| transfers[leg].from.as_str())
+            .collect();
+        for member in members {
+            let entry = cycles.entry(member).or_default();
+            entry.cycles += 1;
+            for &leg in legs {
+                let transfer = &transfers[leg];
+                if transfer.from == member || transfer.to == member {
+                    entry.flagged.insert(leg);
+                }
+            }
+        }
+    };
+
+    for &first in &order {
+        let a = &transfers[first];
+        if a.from == a.to || used[first] {
+            continue;
+        }
+        let deadline = a.ts.saturating_add(config.window_secs);
+
+        'second: for second in legs_within(&outgoing, transfers, &a.to, a.ts, deadline) {
+            let b = &transfers[second];
+            if second == first
+                || used[second]
+                || !similar_amounts(a.amount, b.amount, config.amount_tolerance)
+            {
+                continue;
+            }
+
+            if b.to == a.from {
+                record(&mut used, &[first, second]);
+                break;
+            }
+
+            for third in legs_within(&outgoing, transfers, &b.to, b.ts, deadline) {
+                let c = &transfers[third];
+                if third == second
+                    || used[third]
+                    || c.to != a.from
+                    || !similar_amounts(a.amount, c.amount, config.amount_tolerance)
+                {
+                    continue;
+                }
+                record(&mut used, &[first, second, third]);
+                break 'second;
+            }
+        }
+    }
+
+    let mut total_volume: HashMap<&str, f64> = HashMap::new();
+    for transfer in transfers {
+        *total_volume.entry(transfer.from.as_str()).or_default() += transfer.amount.abs();
+        *total_volume.entry(transfer.to.as_str()).or_default() += transfer.amount.abs();
+    }
+
+    let mut scores: Vec<WashTradingScore> = cycles
+        .into_iter()
+        .map(|(address, found)| {
+            let flagged_volume: f64 = found
+                .flagged
+                .iter()
+                .map(|&idx| transfers[idx].amount.abs())
+                .sum();
+            let total_volume = total_volume.get(address).copied().unwrap_or(0.0);
+            let suspicion_score = if total_volume > 0.0 {
+                (flagged_volume / total_volume).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+
+            WashTradingScore {
+                address: address.to_string(),
+                cycle_count: found.cycles,
+                flagged_volume,
+                total_volume,
+                suspicion_score,
+            }
+        })
+        .collect();
+
+    scores.sort_by(|a, b| {
+        b.suspicion_score
+            .total_cmp(&a.suspicion_score)
+            .then_with(|| a.address.cmp(&b.address))
+    });
+    scores
+}
+
+/// Outgoing transfers of `address` with `start <= ts <= end`.
+fn legs_within<'a>(
+    outgoing: &'a HashMap<&str, Vec<usize>>,
+    transfers: &'a [Transfer],
+    address: &str,
+    start: u64,
+    end: u64,
+) -> impl Iterator<Item = usize> + 'a {
+    let legs = outgoing.get(address).map(Vec::as_slice).unwrap_or(&[]);
+    let first = legs.partition_point(|&idx| transfers[idx].ts < start);
+    legs[first..]
+        .iter()
+        .copied()
+        .take_while(move |&idx| transfers[idx].ts <= end)
+}
+
+fn similar_amounts(a: f64, b: f64, tolerance: f64) -> bool {
+    let largest = a.abs().max(b.abs());
+    if largest == 0.0 {
+        return true;
+    }
+    (a - b).abs() / largest <= tolerance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transfer(ts: u64, from: &str, to: &str, amount: f64) -> Transfer {
+        Transfer {
+            ts,
+            from: from.to_string(),
+            to: to.to_string(),
+            amount,
+            usd_price: 1.0,
+        }
+    }
+
+    fn score_of<'a>(scores: &'a [WashTradingScore], address: &str) -> Option<&'a WashTradingScore> {
+        scores.iter().find(|score| score.address == address)
+    }
+
+    #[test]
+    fn test_detects_two_leg_cycle() {
+        let transfers = vec![
+            transfer(100, "0xA", "0xB", 100.0),
+            transfer(200, "0xB", "0xA", 99.0),
+        ];
+
+        let scores = detect_wash_trading(&transfers, &WashTradingConfig::default());
+
+        assert_eq!(scores.len(), 2);
+        let a = score_of(&scores, "0xA").unwrap();
+        assert_eq!(a.cycle_count, 1);
+        assert!((a.suspicion_score - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_detects_three_leg_cycle() {
+        let transfers = vec![
+            transfer(100, "0xA", "0xB", 50.0),
+            transfer(150, "0xB", "0xC", 50.0),
+            transfer(190, "0xC", "0xA", 49.5),
+        ];
+
+        let scores = detect_wash_trading(&transfers, &WashTradingConfig::default());
+
+        assert_eq!(scores.len(), 3);
+        for address in ["0xA", "0xB", "0xC"] {
+            assert_eq!(score_of(&scores, address).unwrap().cycle_count, 1);
+        }
+    }
+
+    #[test]
+    fn test_overlapping_cycles_count_once() {
+        let transfers = vec![
+            transfer(100, "0xA", "0xB", 100.0),
+            transfer(200, "0xB", "0xA", 100.0),
+            transfer(300, "0xA", "0xB", 100.0),
+            transfer(400, "0xB", "0xA", 100.0),
+            transfer(500, "0xA", "0xB", 100.0),
+        ];
+
+        let scores = detect_wash_trading(&transfers, &WashTradingConfig::default());
+
+        for address in ["0xA", "0xB"] {
+            let score = score_of(&scores, address).unwrap();
+            assert_eq!(score.cycle_count, 2);
+            assert!((score.flagged_volume - 400.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_ignores_cycle_outside_window() {
+        let transfers = vec![
+            transfer(100, "0xA", "0xB", 100.0),
+            transfer(100 + 7_200, "0xB", "0xA", 100.0),
+        ];
+
+        let scores = detect_wash_trading(&transfers, &WashTradingConfig::default());
+
+        assert!(scores.is_empty());
+    }
+
+    #[test]
+    fn test_ignores_dissimilar_amounts() {
+        let transfers = vec![
+            transfer(100, "0xA", "0xB", 100.0),
+            transfer(200, "0xB", "0xA", 10.0),
+        ];
+
+        let scores = detect_wash_trading(&transfers, &WashTradingConfig::default());
+
+        assert!(scores.is_empty());
+    }
+
+    #[test]
+    fn test_score_reflects_share_of_volume() {
+        let transfers = vec![
+            transfer(100, "0xA", "0xB", 100.0),
+            transfer(200, "0xB", "0xA", 100.0),
+            transfer(300, "0xA", "0xD", 200.0),
+        ];
+
+        let scores = detect_wash_trading(&transfers, &WashTradingConfig::default());
+
+        let a = score_of(&scores, "0xA").unwrap();
+        assert!((a.flagged_volume - 200.0).abs() < 1e-9);
+        assert!((a.total_volume - 400.0).abs() < 1e-9);
+        assert!((a.suspicion_score - 0.5).abs() < 1e-9);
+        assert!(score_of(&scores, "0xD").is_none());
+    }
+}
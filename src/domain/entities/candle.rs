@@ -0,0 +1,33 @@
+use clickhouse::Row;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Row)]
+pub struct Candle {
+    pub bucket_start: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+    pub trade_count: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum CandleInterval {
+    #[serde(rename = "1m")]
+    Minute,
+    #[serde(rename = "1h")]
+    Hour,
+    #[serde(rename = "1d")]
+    Day,
+}
+
+impl CandleInterval {
+    pub fn secs(&self) -> u64 {
+        match self {
+            CandleInterval::Minute => 60,
+            CandleInterval::Hour => 3_600,
+            CandleInterval::Day => 86_400,
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use clickhouse::Row;
+use serde::{Deserialize, Serialize};
+
+use super::transfer::Transfer;
+
+/// Last block whose transfers are stored, with its hash so a reorg that
+/// replaced it can be noticed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Row)]
+pub struct Checkpoint {
+    /// Unix milliseconds; the latest checkpoint wins.
+    pub updated_at: u64,
+    pub block_number: u64,
+    pub block_hash: String,
+}
+
+/// A transfer decoded from a `Transfer` event log, with the block it came
+/// from so a reorg can take it back out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Row)]
+pub struct ChainTransfer {
+    pub block_number: u64,
+    pub log_index: u64,
+    pub block_hash: String,
+    pub tx_hash: String,
+    pub ts: u64,
+    pub from: String,
+    pub to: String,
+    pub amount: f64,
+    pub usd_price: f64,
+}
+
+impl ChainTransfer {
+    pub fn to_transfer(&self) -> Transfer {
+        Transfer {
+            ts: self.ts,
+            from: self.from.clone(),
+            to: self.to.clone(),
+            amount: self.amount,
+            usd_price: self.usd_price,
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnershipLabel {
+    pub address: String,
+    pub owner: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AddressCluster {
+    pub address: String,
+    pub cluster_id: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Cluster {
+    pub cluster_id: String,
+    pub addresses: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClusterStats {
+    pub cluster_id: String,
+    pub addresses: Vec<String>,
+    pub total_volume: f64,
+    pub avg_buy_price: f64,
+    pub avg_sell_price: f64,
+    pub max_balance: f64,
+}
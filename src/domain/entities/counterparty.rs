@@ -0,0 +1,19 @@
+use clickhouse::Row;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, Row)]
+pub struct Counterparty {
+    pub address: String,
+    pub sent_volume: f64,
+    pub received_volume: f64,
+    pub total_volume: f64,
+    pub transfer_count: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CounterpartySort {
+    #[default]
+    Volume,
+    Count,
+}
@@ -0,0 +1,12 @@
+use clickhouse::Row;
+use serde::{Deserialize, Serialize};
+
+/// One insert of generated transfers, recorded so later runs know what
+/// the database already holds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Row)]
+pub struct GenerationRun {
+    pub ts: u64,
+    /// `startup` or `cli`.
+    pub source: String,
+    pub rows: u64,
+}
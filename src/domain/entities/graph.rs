@@ -0,0 +1,26 @@
+use clickhouse::Row;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, Row)]
+pub struct GraphEdge {
+    pub from: String,
+    pub to: String,
+    pub total_volume: f64,
+    pub transfer_count: u64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GraphFilter {
+    pub from_ts: Option<u64>,
+    pub to_ts: Option<u64>,
+    pub min_volume: Option<f64>,
+    /// Heaviest edges returned; all of them when unset.
+    pub limit: Option<usize>,
+}
+
+/// The heaviest edges of the graph; `truncated` when there were more.
+#[derive(Debug, Clone)]
+pub struct TransferGraph {
+    pub edges: Vec<GraphEdge>,
+    pub truncated: bool,
+}
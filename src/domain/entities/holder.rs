@@ -0,0 +1,27 @@
+use clickhouse::Row;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Row)]
+pub struct AddressBalance {
+    pub address: String,
+    pub balance: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BalanceBucket {
+    pub min_balance: f64,
+    pub max_balance: f64,
+    pub holders: u64,
+    pub total_balance: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HolderDistribution {
+    pub as_of: Option<u64>,
+    pub holder_count: u64,
+    pub total_supply: f64,
+    pub top_holders: Vec<AddressBalance>,
+    pub histogram: Vec<BalanceBucket>,
+    pub gini_coefficient: f64,
+    pub nakamoto_coefficient: u64,
+}
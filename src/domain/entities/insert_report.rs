@@ -0,0 +1,23 @@
+use serde::Serialize;
+
+/// One committed insert of a `save_all` call.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChunkReport {
+    /// Position of the chunk within the input slice.
+    pub index: usize,
+    pub rows: usize,
+    /// Estimated uncompressed RowBinary size.
+    pub bytes: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct InsertReport {
+    /// Committed chunks ordered by `index`.
+    pub chunks: Vec<ChunkReport>,
+}
+
+impl InsertReport {
+    pub fn rows(&self) -> usize {
+        self.chunks.iter().map(|chunk| chunk.rows).sum()
+    }
+}
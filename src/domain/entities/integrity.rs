@@ -0,0 +1,49 @@
+use clickhouse::Row;
+use serde::{Deserialize, Serialize};
+
+use super::transfer::Transfer;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferCheck {
+    /// `from == to`.
+    SelfTransfer,
+    /// Zero, negative or non-finite `amount`.
+    InvalidAmount,
+    /// Zero, negative or non-finite `usd_price`.
+    InvalidPrice,
+    /// `ts` later than the given cut-off.
+    FutureTimestamp { after: u64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Row)]
+pub struct NegativeBalance {
+    pub address: String,
+    pub min_balance: f64,
+    pub first_negative_ts: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IntegrityIssue<T> {
+    pub count: u64,
+    pub samples: Vec<T>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IntegrityReport {
+    pub checked_at: u64,
+    pub negative_balances: IntegrityIssue<NegativeBalance>,
+    pub self_transfers: IntegrityIssue<Transfer>,
+    pub invalid_amounts: IntegrityIssue<Transfer>,
+    pub invalid_prices: IntegrityIssue<Transfer>,
+    pub future_timestamps: IntegrityIssue<Transfer>,
+}
+
+impl IntegrityReport {
+    pub fn total_issues(&self) -> u64 {
+        self.negative_balances.count
+            + self.self_transfers.count
+            + self.invalid_amounts.count
+            + self.invalid_prices.count
+            + self.future_timestamps.count
+    }
+}
@@ -0,0 +1,52 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LabelCategory {
+    Exchange,
+    Contract,
+    Team,
+    User,
+}
+
+impl LabelCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LabelCategory::Exchange => "exchange",
+            LabelCategory::Contract => "contract",
+            LabelCategory::Team => "team",
+            LabelCategory::User => "user",
+        }
+    }
+}
+
+impl fmt::Display for LabelCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for LabelCategory {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "exchange" => Ok(LabelCategory::Exchange),
+            "contract" => Ok(LabelCategory::Contract),
+            "team" => Ok(LabelCategory::Team),
+            "user" => Ok(LabelCategory::User),
+            other => Err(format!("Unknown label category: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AddressLabel {
+    pub address: String,
+    pub name: String,
+    pub category: LabelCategory,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
@@ -1,2 +1,16 @@
+pub mod candle;
+pub mod chain;
+pub mod cluster;
+pub mod counterparty;
+pub mod generation;
+pub mod graph;
+pub mod holder;
+pub mod insert_report;
+pub mod integrity;
+pub mod label;
+pub mod price;
+pub mod summary;
+pub mod time_range;
 pub mod transfer;
 pub mod user_stats;
+pub mod wash_trading;
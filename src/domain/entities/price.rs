@@ -0,0 +1,19 @@
+use clickhouse::Row;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Row)]
+pub struct PricePoint {
+    pub token: String,
+    pub ts: u64,
+    pub price: f64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PriceSource {
+    /// The `usd_price` stored on each transfer.
+    #[default]
+    Transfer,
+    /// The latest oracle price at or before each transfer.
+    Oracle,
+}
@@ -0,0 +1,30 @@
+use clickhouse::Row;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Row)]
+pub struct TransferTotals {
+    pub total_volume: f64,
+    pub total_usd_volume: f64,
+    pub transfer_count: u64,
+    pub unique_senders: u64,
+    pub unique_receivers: u64,
+    pub vwap: f64,
+    pub median_transfer_size: f64,
+    pub p25_transfer_size: f64,
+    pub p75_transfer_size: f64,
+    pub p90_transfer_size: f64,
+    pub p99_transfer_size: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Row)]
+pub struct DailyActivity {
+    pub day: u64,
+    pub active_addresses: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MarketSummary {
+    #[serde(flatten)]
+    pub totals: TransferTotals,
+    pub daily_active_addresses: Vec<DailyActivity>,
+}
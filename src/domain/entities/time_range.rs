@@ -0,0 +1,21 @@
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub struct TimeRange {
+    pub from_ts: Option<u64>,
+    pub to_ts: Option<u64>,
+}
+
+impl TimeRange {
+    pub fn new(from_ts: Option<u64>, to_ts: Option<u64>) -> Self {
+        Self { from_ts, to_ts }
+    }
+
+    pub fn start(&self) -> u64 {
+        self.from_ts.unwrap_or(0)
+    }
+
+    pub fn end(&self) -> u64 {
+        self.to_ts.unwrap_or(u64::MAX)
+    }
+}
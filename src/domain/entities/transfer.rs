@@ -1,7 +1,7 @@
 use clickhouse::Row;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize, Row)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Row)]
 pub struct Transfer {
     pub ts: u64,
     pub from: String,
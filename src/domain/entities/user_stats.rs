@@ -1,7 +1,9 @@
 use clickhouse::Row;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize, Row)]
+use super::label::AddressLabel;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Row)]
 pub struct UserStats {
     pub address: String,
     pub total_volume: f64,
@@ -27,3 +29,25 @@ impl UserStats {
         }
     }
 }
+
+/// `UserStats` with optional per-address extras. Extras that were not
+/// requested are left out of the JSON, so the plain shape is unchanged.
+#[derive(Debug, Clone, Serialize)]
+pub struct EnrichedUserStats {
+    #[serde(flatten)]
+    pub stats: UserStats,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suspicion_score: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<AddressLabel>,
+}
+
+impl From<UserStats> for EnrichedUserStats {
+    fn from(stats: UserStats) -> Self {
+        Self {
+            stats,
+            suspicion_score: None,
+            label: None,
+        }
+    }
+}
@@ -0,0 +1,10 @@
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WashTradingScore {
+    pub address: String,
+    pub cycle_count: u64,
+    pub flagged_volume: f64,
+    pub total_volume: f64,
+    pub suspicion_score: f64,
+}
@@ -1,3 +1,5 @@
+pub mod analysis;
 pub mod entities;
 pub mod repositories;
 pub mod services;
+pub mod validation;
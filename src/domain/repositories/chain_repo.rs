@@ -0,0 +1,24 @@
+use async_trait::async_trait;
+use mockall::automock;
+
+use crate::domain::entities::chain::{ChainTransfer, Checkpoint};
+
+use super::errors::TransferRepoError;
+
+pub type ChainRepoResult<T> = Result<T, TransferRepoError>;
+
+/// Progress of chain ingestion and the provenance of the transfers it
+/// inserted.
+#[automock]
+#[async_trait]
+pub trait ChainRepoAbstract {
+    async fn checkpoint(&self) -> ChainRepoResult<Option<Checkpoint>>;
+    async fn save_checkpoint(&self, checkpoint: &Checkpoint) -> ChainRepoResult<()>;
+    /// Records transfers about to be inserted.
+    async fn save_logs(&self, logs: &[ChainTransfer]) -> ChainRepoResult<()>;
+    /// Recorded transfers of `from_block` and later, in block and log order.
+    async fn logs_from(&self, from_block: u64) -> ChainRepoResult<Vec<ChainTransfer>>;
+    /// Deletes the recorded transfers of `from_block` and later, and the
+    /// rows inserted for them into the transfers table.
+    async fn rollback(&self, from_block: u64) -> ChainRepoResult<()>;
+}
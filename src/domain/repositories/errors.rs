@@ -1,13 +1,73 @@
 use thiserror::Error;
 
+use crate::domain::{entities::insert_report::InsertReport, validation::errors::ValidationError};
+
 #[derive(Debug, Error)]
 pub enum TransferRepoError {
     #[error("Database connection failed: {0}")]
     DatabaseConnectionError(String),
+    #[error("Database request timed out: {0}")]
+    TimedOut(String),
+    #[error("Bad request: {0}")]
+    BadRequest(String),
     #[error("Transfer not found with id: {id}")]
     TransferNotFound { id: String },
     #[error("Database query failed: {0}")]
     QueryError(String),
+    #[error("Write buffer is full: {0}")]
+    BufferFull(String),
+    #[error("Write buffer failed: {0}")]
+    BufferFailed(String),
+    #[error("Validation failed: {0}")]
+    ValidationError(#[from] ValidationError),
+    /// Some chunks of a batched insert were committed before `source` stopped
+    /// the rest.
+    #[error("Inserted {} rows before failing: {source}", report.rows())]
+    PartialInsert {
+        report: InsertReport,
+        source: Box<TransferRepoError>,
+    },
+}
+
+impl TransferRepoError {
+    /// Stable machine-readable identifier of the error kind.
+    pub fn code(&self) -> &'static str {
+        match self {
+            TransferRepoError::DatabaseConnectionError(_) => "database_unavailable",
+            TransferRepoError::TimedOut(_) => "database_timeout",
+            TransferRepoError::BadRequest(_) => "bad_request",
+            TransferRepoError::TransferNotFound { .. } => "not_found",
+            TransferRepoError::QueryError(_) => "query_failed",
+            TransferRepoError::BufferFull(_) => "buffer_full",
+            TransferRepoError::BufferFailed(_) => "buffer_failed",
+            TransferRepoError::ValidationError(_) => "validation_failed",
+            TransferRepoError::PartialInsert { source, .. } => source.code(),
+        }
+    }
+
+    /// Whether repeating the same request may succeed.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            TransferRepoError::DatabaseConnectionError(_)
+            | TransferRepoError::TimedOut(_)
+            | TransferRepoError::BufferFull(_) => true,
+            TransferRepoError::PartialInsert { source, .. } => source.is_retryable(),
+            _ => false,
+        }
+    }
+
+    /// Whether the error came back from the database, as opposed to being
+    /// raised before a request was sent.
+    pub fn reached_database(&self) -> bool {
+        match self {
+            TransferRepoError::BadRequest(_)
+            | TransferRepoError::BufferFull(_)
+            | TransferRepoError::BufferFailed(_)
+            | TransferRepoError::ValidationError(_) => false,
+            TransferRepoError::PartialInsert { source, .. } => source.reached_database(),
+            _ => true,
+        }
+    }
 }
 
 #[derive(Debug, Error)]
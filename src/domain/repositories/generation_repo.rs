@@ -0,0 +1,23 @@
+use async_trait::async_trait;
+use mockall::automock;
+
+use crate::domain::entities::generation::GenerationRun;
+
+use super::errors::TransferRepoError;
+
+pub type GenerationRepoResult<T> = Result<T, TransferRepoError>;
+
+#[automock]
+#[async_trait]
+pub trait GenerationRepoAbstract {
+    async fn record(&self, run: &GenerationRun) -> GenerationRepoResult<()>;
+    /// Generated rows recorded so far.
+    async fn generated_rows(&self) -> GenerationRepoResult<u64>;
+    /// Claims the run named `key`. Of all processes claiming the same key,
+    /// only the first gets `true`, now and on every later call, until the
+    /// claim is released.
+    async fn claim(&self, key: &str) -> GenerationRepoResult<bool>;
+    /// Gives up the current claim of `key`, for an owner that failed before
+    /// doing the run; the next claim starts a new round.
+    async fn release(&self, key: &str) -> GenerationRepoResult<()>;
+}
@@ -0,0 +1,18 @@
+use async_trait::async_trait;
+use mockall::automock;
+
+use crate::domain::entities::label::{AddressLabel, LabelCategory};
+
+use super::errors::TransferRepoError;
+
+pub type LabelRepoResult<T> = Result<T, TransferRepoError>;
+
+#[automock]
+#[async_trait]
+pub trait LabelRepoAbstract {
+    async fn save_all(&self, labels: &[AddressLabel]) -> LabelRepoResult<()>;
+    async fn find_by_address(&self, address: &str) -> LabelRepoResult<Option<AddressLabel>>;
+    async fn find_all(&self, category: Option<LabelCategory>)
+    -> LabelRepoResult<Vec<AddressLabel>>;
+    async fn delete(&self, address: &str) -> LabelRepoResult<()>;
+}
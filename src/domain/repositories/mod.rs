@@ -1,2 +1,7 @@
+pub mod chain_repo;
 pub mod errors;
+pub mod generation_repo;
+pub mod label_repo;
+pub mod price_repo;
+pub mod transfer_buffer;
 pub mod transfer_repo;
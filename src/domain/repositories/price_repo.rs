@@ -0,0 +1,17 @@
+use async_trait::async_trait;
+use mockall::automock;
+
+use crate::domain::entities::{price::PricePoint, time_range::TimeRange};
+
+use super::errors::TransferRepoError;
+
+pub type PriceRepoResult<T> = Result<T, TransferRepoError>;
+
+#[automock]
+#[async_trait]
+pub trait PriceRepoAbstract {
+    async fn save_all(&self, prices: &[PricePoint]) -> PriceRepoResult<()>;
+    /// Latest price of `token` with `ts <= at`.
+    async fn price_at(&self, token: &str, at: u64) -> PriceRepoResult<Option<PricePoint>>;
+    async fn prices(&self, token: &str, range: &TimeRange) -> PriceRepoResult<Vec<PricePoint>>;
+}
@@ -0,0 +1,17 @@
+use async_trait::async_trait;
+use mockall::automock;
+
+use crate::domain::entities::transfer::Transfer;
+
+use super::errors::TransferRepoError;
+
+pub type TransferBufferResult<T> = Result<T, TransferRepoError>;
+
+/// Durable staging in front of the transfer repository.
+#[automock]
+#[async_trait]
+pub trait TransferBufferAbstract {
+    /// Validates and stores `transfers`; once this returns they survive a
+    /// restart and are inserted in the order they were appended.
+    async fn append(&self, transfers: Vec<Transfer>) -> TransferBufferResult<()>;
+}
@@ -1,15 +1,89 @@
 use async_trait::async_trait;
+use futures::stream::BoxStream;
 use mockall::automock;
 
-use crate::domain::entities::{transfer::Transfer, user_stats::UserStats};
+use crate::domain::entities::{
+    candle::Candle,
+    chain::ChainTransfer,
+    cluster::AddressCluster,
+    counterparty::{Counterparty, CounterpartySort},
+    graph::{GraphEdge, GraphFilter},
+    holder::AddressBalance,
+    insert_report::InsertReport,
+    integrity::{IntegrityIssue, NegativeBalance, TransferCheck},
+    summary::{DailyActivity, TransferTotals},
+    time_range::TimeRange,
+    transfer::Transfer,
+    user_stats::UserStats,
+};
 
 use super::errors::TransferRepoError;
 
 pub type TransferRepoResult<T> = Result<T, TransferRepoError>;
+pub type UserStatsStream = BoxStream<'static, TransferRepoResult<UserStats>>;
 
 #[automock]
 #[async_trait]
 pub trait TransferRepoAbstract {
-    async fn save_all(&self, transfers: &[Transfer]) -> TransferRepoResult<()>;
+    async fn save_all(&self, transfers: &[Transfer]) -> TransferRepoResult<InsertReport>;
+    /// Like `save_all`, with each row tagged with the block and log it was
+    /// decoded from. Inserts sent again with the same `dedup_token` are
+    /// dropped by the server instead of stored twice.
+    async fn save_chain_transfers(
+        &self,
+        transfers: &[ChainTransfer],
+        dedup_token: &str,
+    ) -> TransferRepoResult<InsertReport>;
     async fn calculate_user_stats(&self) -> TransferRepoResult<Vec<UserStats>>;
+    /// Same rows as `calculate_user_stats`, yielded as ClickHouse sends
+    /// them instead of being collected first.
+    fn stream_user_stats(&self) -> UserStatsStream;
+    /// The `calculate_user_stats` rows of `addresses` only; addresses
+    /// without transfers are left out.
+    async fn user_stats_for(&self, addresses: &[String]) -> TransferRepoResult<Vec<UserStats>>;
+    async fn calculate_user_stats_with_prices(
+        &self,
+        token: &str,
+    ) -> TransferRepoResult<Vec<UserStats>>;
+    async fn calculate_cluster_stats(
+        &self,
+        clusters: &[AddressCluster],
+    ) -> TransferRepoResult<Vec<UserStats>>;
+    async fn top_counterparties(
+        &self,
+        address: &str,
+        sort: CounterpartySort,
+        limit: usize,
+    ) -> TransferRepoResult<Vec<Counterparty>>;
+    async fn transfer_graph(&self, filter: &GraphFilter) -> TransferRepoResult<Vec<GraphEdge>>;
+    async fn get_transfers(&self, range: &TimeRange) -> TransferRepoResult<Vec<Transfer>>;
+    /// Net balance (received minus sent) of every address, counting only
+    /// transfers with `ts <= at` when given.
+    async fn balances(&self, at: Option<u64>) -> TransferRepoResult<Vec<AddressBalance>>;
+    async fn find_anomalies(
+        &self,
+        check: TransferCheck,
+        sample_limit: usize,
+    ) -> TransferRepoResult<IntegrityIssue<Transfer>>;
+    /// Addresses whose running balance drops below zero at any point.
+    async fn find_negative_balances(
+        &self,
+        sample_limit: usize,
+    ) -> TransferRepoResult<IntegrityIssue<NegativeBalance>>;
+    async fn transfer_totals(&self, range: &TimeRange) -> TransferRepoResult<TransferTotals>;
+    /// Rows stored, without a time filter.
+    async fn transfer_count(&self) -> TransferRepoResult<u64>;
+    /// Changes whenever a row is added or deleted, so results derived from
+    /// the whole table can be cached until it does.
+    async fn transfers_checksum(&self) -> TransferRepoResult<u64>;
+    async fn daily_active_addresses(
+        &self,
+        range: &TimeRange,
+    ) -> TransferRepoResult<Vec<DailyActivity>>;
+    /// Non-empty candles of `interval_secs`, ordered by `bucket_start`.
+    async fn candles(
+        &self,
+        interval_secs: u64,
+        range: &TimeRange,
+    ) -> TransferRepoResult<Vec<Candle>>;
 }
@@ -0,0 +1,241 @@
+use std::{collections::HashMap, sync::Arc};
+
+use tokio::sync::Mutex;
+
+use crate::domain::{
+    analysis::{
+        distribution::holder_distribution,
+        wash_trading::{WashTradingConfig, detect_wash_trading},
+    },
+    entities::{holder::HolderDistribution, time_range::TimeRange, wash_trading::WashTradingScore},
+    repositories::transfer_repo::TransferRepoAbstract,
+};
+
+use super::errors::TransferError;
+
+pub type AnalysisServiceResult<T> = Result<T, TransferError>;
+
+pub type SuspicionScores = Arc<HashMap<String, f64>>;
+
+/// Scores of the table state with `checksum`, under `config`.
+struct CachedScores {
+    checksum: u64,
+    config: WashTradingConfig,
+    scores: SuspicionScores,
+}
+
+pub struct AnalysisService<T>
+where
+    T: TransferRepoAbstract,
+{
+    transfer_repo: Arc<T>,
+    suspicion: Mutex<Option<CachedScores>>,
+}
+
+impl<T> AnalysisService<T>
+where
+    T: TransferRepoAbstract,
+{
+    pub fn new(transfer_repo: Arc<T>) -> Self {
+        Self {
+            transfer_repo,
+            suspicion: Mutex::new(None),
+        }
+    }
+
+    pub async fn wash_trading_report(
+        &self,
+        range: &TimeRange,
+        config: &WashTradingConfig,
+    ) -> AnalysisServiceResult<Vec<WashTradingScore>> {
+        let transfers = self.transfer_repo.get_transfers(range).await?;
+        Ok(detect_wash_trading(&transfers, config))
+    }
+
+    /// Suspicion score per address over the whole history. Addresses without
+    /// detected cycles are absent from the map.
+    ///
+    /// Scoring needs every transfer in memory, so the scores are kept until
+    /// the table's checksum changes; concurrent requests wait for one load.
+    pub async fn suspicion_scores(
+        &self,
+        config: &WashTradingConfig,
+    ) -> AnalysisServiceResult<SuspicionScores> {
+        let mut cached = self.suspicion.lock().await;
+        let checksum = self.transfer_repo.transfers_checksum().await?;
+        if let Some(hit) = cached
+            .as_ref()
+            .filter(|hit| hit.checksum == checksum && hit.config == *config)
+        {
+            return Ok(hit.scores.clone());
+        }
+
+        let transfers = self
+            .transfer_repo
+            .get_transfers(&TimeRange::default())
+            .await?;
+
+        let scores: SuspicionScores = Arc::new(
+            detect_wash_trading(&transfers, config)
+                .into_iter()
+                .map(|score| (score.address, score.suspicion_score))
+                .collect(),
+        );
+        *cached = Some(CachedScores {
+            checksum,
+            config: *config,
+            scores: scores.clone(),
+        });
+
+        Ok(scores)
+    }
+
+    pub async fn holder_distribution(
+        &self,
+        at: Option<u64>,
+        top_n: usize,
+    ) -> AnalysisServiceResult<HolderDistribution> {
+        let balances = self.transfer_repo.balances(at).await?;
+        Ok(holder_distribution(balances, top_n, at))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{
+        entities::{holder::AddressBalance, transfer::Transfer},
+        repositories::{errors::TransferRepoError, transfer_repo::MockTransferRepoAbstract},
+    };
+
+    fn create_cycle_transfers() -> Vec<Transfer> {
+        vec![
+            Transfer {
+                ts: 100,
+                from: "0x123".to_string(),
+                to: "0x456".to_string(),
+                amount: 100.0,
+                usd_price: 1.0,
+            },
+            Transfer {
+                ts: 200,
+                from: "0x456".to_string(),
+                to: "0x123".to_string(),
+                amount: 100.0,
+                usd_price: 1.0,
+            },
+        ]
+    }
+
+    #[actix_web::test]
+    async fn test_wash_trading_report_success() {
+        let mut mock_repo = MockTransferRepoAbstract::new();
+
+        mock_repo
+            .expect_get_transfers()
+            .times(1)
+            .returning(|_| Ok(create_cycle_transfers()));
+
+        let service = AnalysisService::new(Arc::new(mock_repo));
+        let report = service
+            .wash_trading_report(&TimeRange::default(), &WashTradingConfig::default())
+            .await
+            .unwrap();
+
+        assert_eq!(report.len(), 2);
+    }
+
+    #[actix_web::test]
+    async fn test_suspicion_scores() {
+        let mut mock_repo = MockTransferRepoAbstract::new();
+
+        mock_repo
+            .expect_transfers_checksum()
+            .times(1)
+            .returning(|| Ok(7));
+        mock_repo
+            .expect_get_transfers()
+            .withf(|range| *range == TimeRange::default())
+            .times(1)
+            .returning(|_| Ok(create_cycle_transfers()));
+
+        let service = AnalysisService::new(Arc::new(mock_repo));
+        let scores = service
+            .suspicion_scores(&WashTradingConfig::default())
+            .await
+            .unwrap();
+
+        assert_eq!(scores.len(), 2);
+        assert!((scores["0x123"] - 1.0).abs() < 1e-9);
+        assert!(!scores.contains_key("0x789"));
+    }
+
+    #[actix_web::test]
+    async fn test_suspicion_scores_reload_when_the_table_changes() {
+        let mut mock_repo = MockTransferRepoAbstract::new();
+        let mut checksums = [1, 1, 2].into_iter();
+        mock_repo
+            .expect_transfers_checksum()
+            .times(3)
+            .returning(move || Ok(checksums.next().unwrap()));
+        mock_repo
+            .expect_get_transfers()
+            .times(2)
+            .returning(|_| Ok(create_cycle_transfers()));
+        let service = AnalysisService::new(Arc::new(mock_repo));
+
+        for _ in 0..3 {
+            let scores = service
+                .suspicion_scores(&WashTradingConfig::default())
+                .await
+                .unwrap();
+            assert_eq!(scores.len(), 2);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_wash_trading_report_repo_error() {
+        let mut mock_repo = MockTransferRepoAbstract::new();
+
+        mock_repo
+            .expect_get_transfers()
+            .times(1)
+            .returning(|_| Err(TransferRepoError::QueryError("DB Error".to_string())));
+
+        let service = AnalysisService::new(Arc::new(mock_repo));
+        let result = service
+            .wash_trading_report(&TimeRange::default(), &WashTradingConfig::default())
+            .await;
+
+        assert!(result.is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_holder_distribution_as_of() {
+        let mut mock_repo = MockTransferRepoAbstract::new();
+
+        mock_repo
+            .expect_balances()
+            .withf(|at| *at == Some(150))
+            .times(1)
+            .returning(|_| {
+                Ok(vec![
+                    AddressBalance {
+                        address: "0x123".to_string(),
+                        balance: 100.0,
+                    },
+                    AddressBalance {
+                        address: "0x456".to_string(),
+                        balance: -100.0,
+                    },
+                ])
+            });
+
+        let service = AnalysisService::new(Arc::new(mock_repo));
+        let distribution = service.holder_distribution(Some(150), 10).await.unwrap();
+
+        assert_eq!(distribution.holder_count, 1);
+        assert_eq!(distribution.as_of, Some(150));
+        assert_eq!(distribution.top_holders[0].address, "0x123");
+    }
+}
@@ -0,0 +1,153 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::domain::{
+    analysis::clustering::{ClusteringConfig, cluster_addresses},
+    entities::{
+        cluster::{AddressCluster, Cluster, ClusterStats},
+        graph::GraphFilter,
+    },
+    repositories::transfer_repo::TransferRepoAbstract,
+};
+
+use super::errors::TransferError;
+
+pub type ClusterServiceResult<T> = Result<T, TransferError>;
+
+pub struct ClusterService<T>
+where
+    T: TransferRepoAbstract,
+{
+    transfer_repo: Arc<T>,
+    config: ClusteringConfig,
+}
+
+impl<T> ClusterService<T>
+where
+    T: TransferRepoAbstract,
+{
+    pub fn new(transfer_repo: Arc<T>, config: ClusteringConfig) -> Self {
+        Self {
+            transfer_repo,
+            config,
+        }
+    }
+
+    pub async fn clusters(&self) -> ClusterServiceResult<Vec<Cluster>> {
+        let edges = self
+            .transfer_repo
+            .transfer_graph(&GraphFilter::default())
+            .await?;
+        Ok(cluster_addresses(&edges, &self.config))
+    }
+
+    pub async fn cluster_stats(&self) -> ClusterServiceResult<Vec<ClusterStats>> {
+        let clusters = self.clusters().await?;
+
+        let assignments: Vec<AddressCluster> = clusters
+            .iter()
+            .flat_map(|cluster| {
+                cluster.addresses.iter().map(|address| AddressCluster {
+                    address: address.clone(),
+                    cluster_id: cluster.cluster_id.clone(),
+                })
+            })
+            .collect();
+
+        let mut members: HashMap<String, Vec<String>> = clusters
+            .into_iter()
+            .map(|cluster| (cluster.cluster_id, cluster.addresses))
+            .collect();
+
+        let stats = self
+            .transfer_repo
+            .calculate_cluster_stats(&assignments)
+            .await?;
+
+        let cluster_stats = stats
+            .into_iter()
+            .map(|stats| {
+                let addresses = members
+                    .remove(&stats.address)
+                    .unwrap_or_else(|| vec![stats.address.clone()]);
+                ClusterStats {
+                    cluster_id: stats.address,
+                    addresses,
+                    total_volume: stats.total_volume,
+                    avg_buy_price: stats.avg_buy_price,
+                    avg_sell_price: stats.avg_sell_price,
+                    max_balance: stats.max_balance,
+                }
+            })
+            .collect();
+
+        Ok(cluster_stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{
+        entities::{graph::GraphEdge, user_stats::UserStats},
+        repositories::{errors::TransferRepoError, transfer_repo::MockTransferRepoAbstract},
+    };
+
+    fn create_mutual_edges() -> Vec<GraphEdge> {
+        vec![
+            GraphEdge {
+                from: "0x123".to_string(),
+                to: "0x456".to_string(),
+                total_volume: 300.0,
+                transfer_count: 3,
+            },
+            GraphEdge {
+                from: "0x456".to_string(),
+                to: "0x123".to_string(),
+                total_volume: 300.0,
+                transfer_count: 3,
+            },
+        ]
+    }
+
+    #[actix_web::test]
+    async fn test_cluster_stats_maps_members() {
+        let mut mock_repo = MockTransferRepoAbstract::new();
+
+        mock_repo
+            .expect_transfer_graph()
+            .times(1)
+            .returning(|_| Ok(create_mutual_edges()));
+        mock_repo
+            .expect_calculate_cluster_stats()
+            .withf(|clusters| clusters.len() == 2 && clusters[1].cluster_id == "0x123")
+            .times(1)
+            .returning(|_| {
+                Ok(vec![
+                    UserStats::new("0x123".to_string(), 900.0, 1.0, 1.2, 300.0),
+                    UserStats::new("0x789".to_string(), 100.0, 1.1, 1.0, 50.0),
+                ])
+            });
+
+        let service = ClusterService::new(Arc::new(mock_repo), ClusteringConfig::default());
+        let stats = service.cluster_stats().await.unwrap();
+
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].addresses, vec!["0x123", "0x456"]);
+        assert_eq!(stats[1].addresses, vec!["0x789"]);
+    }
+
+    #[actix_web::test]
+    async fn test_clusters_repo_error() {
+        let mut mock_repo = MockTransferRepoAbstract::new();
+
+        mock_repo
+            .expect_transfer_graph()
+            .times(1)
+            .returning(|_| Err(TransferRepoError::QueryError("DB Error".to_string())));
+
+        let service = ClusterService::new(Arc::new(mock_repo), ClusteringConfig::default());
+        let result = service.clusters().await;
+
+        assert!(result.is_err());
+    }
+}
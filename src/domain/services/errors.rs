@@ -6,4 +6,30 @@ use crate::domain::repositories::errors::TransferRepoError;
 pub enum TransferError {
     #[error("Repository error: {0}")]
     RepositoryError(#[from] TransferRepoError),
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+    #[error("Label not found for address: {address}")]
+    LabelNotFound { address: String },
+    #[error("No price for token {token} at or before {ts}")]
+    PriceNotFound { token: String, ts: u64 },
+}
+
+impl TransferError {
+    /// Stable machine-readable identifier of the error kind.
+    pub fn code(&self) -> &'static str {
+        match self {
+            TransferError::RepositoryError(error) => error.code(),
+            TransferError::InvalidInput(_) => "invalid_input",
+            TransferError::LabelNotFound { .. } => "label_not_found",
+            TransferError::PriceNotFound { .. } => "price_not_found",
+        }
+    }
+
+    /// Whether repeating the same request may succeed.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            TransferError::RepositoryError(error) => error.is_retryable(),
+            _ => false,
+        }
+    }
 }
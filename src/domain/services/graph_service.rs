@@ -0,0 +1,203 @@
+use std::sync::Arc;
+
+use crate::domain::{
+    entities::{
+        counterparty::{Counterparty, CounterpartySort},
+        graph::{GraphFilter, TransferGraph},
+    },
+    repositories::transfer_repo::TransferRepoAbstract,
+};
+
+use super::errors::TransferError;
+
+pub type GraphServiceResult<T> = Result<T, TransferError>;
+
+/// Edges exported when neither the config nor the request ask for fewer.
+pub const DEFAULT_MAX_EDGES: usize = 10_000;
+
+pub struct GraphService<T>
+where
+    T: TransferRepoAbstract,
+{
+    transfer_repo: Arc<T>,
+    max_edges: usize,
+}
+
+impl<T> GraphService<T>
+where
+    T: TransferRepoAbstract,
+{
+    pub fn new(transfer_repo: Arc<T>) -> Self {
+        Self {
+            transfer_repo,
+            max_edges: DEFAULT_MAX_EDGES,
+        }
+    }
+
+    /// Caps the edges of one export, so a hub address can't produce an
+    /// unbounded graph.
+    pub fn with_max_edges(mut self, max_edges: usize) -> Self {
+        self.max_edges = max_edges;
+        self
+    }
+
+    pub async fn top_counterparties(
+        &self,
+        address: &str,
+        sort: CounterpartySort,
+        limit: usize,
+    ) -> GraphServiceResult<Vec<Counterparty>> {
+        let counterparties = self
+            .transfer_repo
+            .top_counterparties(address, sort, limit)
+            .await?;
+        Ok(counterparties)
+    }
+
+    /// The heaviest edges, at most `filter.limit` and the configured cap.
+    pub async fn transfer_graph(&self, filter: &GraphFilter) -> GraphServiceResult<TransferGraph> {
+        let limit = filter.limit.unwrap_or(self.max_edges).min(self.max_edges);
+        // One extra edge tells whether anything was cut off.
+        let filter = GraphFilter {
+            limit: Some(limit.saturating_add(1)),
+            ..filter.clone()
+        };
+        let mut edges = self.transfer_repo.transfer_graph(&filter).await?;
+        let truncated = edges.len() > limit;
+        edges.truncate(limit);
+        Ok(TransferGraph { edges, truncated })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::graph::GraphEdge;
+    use crate::domain::repositories::{
+        errors::TransferRepoError, transfer_repo::MockTransferRepoAbstract,
+    };
+    use mockall::predicate::eq;
+
+    fn create_test_edges() -> Vec<GraphEdge> {
+        vec![
+            GraphEdge {
+                from: "0x123".to_string(),
+                to: "0x456".to_string(),
+                total_volume: 500.0,
+                transfer_count: 3,
+            },
+            GraphEdge {
+                from: "0x456".to_string(),
+                to: "0x123".to_string(),
+                total_volume: 200.0,
+                transfer_count: 1,
+            },
+        ]
+    }
+
+    #[actix_web::test]
+    async fn test_top_counterparties_passes_arguments() {
+        let mut mock_repo = MockTransferRepoAbstract::new();
+
+        mock_repo
+            .expect_top_counterparties()
+            .with(eq("0x123"), eq(CounterpartySort::Count), eq(5))
+            .times(1)
+            .returning(|_, _, _| {
+                Ok(vec![Counterparty {
+                    address: "0x456".to_string(),
+                    sent_volume: 500.0,
+                    received_volume: 200.0,
+                    total_volume: 700.0,
+                    transfer_count: 4,
+                }])
+            });
+
+        let service = GraphService::new(Arc::new(mock_repo));
+        let result = service
+            .top_counterparties("0x123", CounterpartySort::Count, 5)
+            .await
+            .unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].address, "0x456");
+        assert_eq!(result[0].transfer_count, 4);
+    }
+
+    #[actix_web::test]
+    async fn test_transfer_graph_success() {
+        let mut mock_repo = MockTransferRepoAbstract::new();
+
+        mock_repo
+            .expect_transfer_graph()
+            .times(1)
+            .returning(|_| Ok(create_test_edges()));
+
+        let service = GraphService::new(Arc::new(mock_repo));
+        let graph = service
+            .transfer_graph(&GraphFilter::default())
+            .await
+            .unwrap();
+
+        assert_eq!(graph.edges.len(), 2);
+        assert_eq!(graph.edges[0].from, "0x123");
+        assert!(!graph.truncated);
+    }
+
+    #[actix_web::test]
+    async fn test_transfer_graph_is_capped() {
+        let mut mock_repo = MockTransferRepoAbstract::new();
+
+        mock_repo
+            .expect_transfer_graph()
+            .withf(|filter| filter.limit == Some(2))
+            .times(1)
+            .returning(|_| Ok(create_test_edges()));
+
+        let service = GraphService::new(Arc::new(mock_repo)).with_max_edges(1);
+        let filter = GraphFilter {
+            limit: Some(50),
+            ..GraphFilter::default()
+        };
+        let graph = service.transfer_graph(&filter).await.unwrap();
+
+        assert_eq!(graph.edges.len(), 1);
+        assert_eq!(graph.edges[0].from, "0x123");
+        assert!(graph.truncated);
+    }
+
+    #[actix_web::test]
+    async fn test_transfer_graph_without_a_cap() {
+        let mut mock_repo = MockTransferRepoAbstract::new();
+
+        mock_repo
+            .expect_transfer_graph()
+            .withf(|filter| filter.limit == Some(usize::MAX))
+            .times(1)
+            .returning(|_| Ok(create_test_edges()));
+
+        let service = GraphService::new(Arc::new(mock_repo)).with_max_edges(usize::MAX);
+        let graph = service
+            .transfer_graph(&GraphFilter::default())
+            .await
+            .unwrap();
+
+        assert_eq!(graph.edges.len(), 2);
+        assert!(!graph.truncated);
+    }
+
+    #[actix_web::test]
+    async fn test_transfer_graph_repo_error() {
+        let mut mock_repo = MockTransferRepoAbstract::new();
+
+        mock_repo
+            .expect_transfer_graph()
+            .times(1)
+            .returning(|_| Err(TransferRepoError::QueryError("DB Error".to_string())));
+
+        let service = GraphService::new(Arc::new(mock_repo));
+        let result = service.transfer_graph(&GraphFilter::default()).await;
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,138 @@
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use crate::domain::{
+    entities::{insert_report::InsertReport, transfer::Transfer},
+    repositories::{transfer_buffer::TransferBufferAbstract, transfer_repo::TransferRepoAbstract},
+};
+
+use super::errors::TransferError;
+
+pub type IngestServiceResult<T> = Result<T, TransferError>;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum Ingested {
+    /// Stored in the write buffer, to be inserted by its flusher.
+    Buffered {
+        accepted: usize,
+    },
+    Inserted {
+        report: InsertReport,
+    },
+}
+
+pub struct IngestService<T, B>
+where
+    T: TransferRepoAbstract,
+    B: TransferBufferAbstract,
+{
+    transfer_repo: Arc<T>,
+    buffer: Option<Arc<B>>,
+}
+
+impl<T, B> IngestService<T, B>
+where
+    T: TransferRepoAbstract,
+    B: TransferBufferAbstract,
+{
+    /// Writes through `buffer` when there is one, straight to the
+    /// repository otherwise.
+    pub fn new(transfer_repo: Arc<T>, buffer: Option<Arc<B>>) -> Self {
+        Self {
+            transfer_repo,
+            buffer,
+        }
+    }
+
+    pub async fn ingest(&self, transfers: Vec<Transfer>) -> IngestServiceResult<Ingested> {
+        if transfers.is_empty() {
+            return Err(TransferError::InvalidInput(
+                "Expected at least one transfer".to_string(),
+            ));
+        }
+
+        match &self.buffer {
+            Some(buffer) => {
+                let accepted = transfers.len();
+                buffer.append(transfers).await?;
+                Ok(Ingested::Buffered { accepted })
+            }
+            None => {
+                let report = self.transfer_repo.save_all(&transfers).await?;
+                Ok(Ingested::Inserted { report })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::repositories::{
+        errors::TransferRepoError, transfer_buffer::MockTransferBufferAbstract,
+        transfer_repo::MockTransferRepoAbstract,
+    };
+
+    fn transfer() -> Transfer {
+        Transfer {
+            ts: 1,
+            from: "0xa".to_string(),
+            to: "0xb".to_string(),
+            amount: 1.0,
+            usd_price: 1.0,
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_buffer_takes_precedence() {
+        let mut repo = MockTransferRepoAbstract::new();
+        repo.expect_save_all().never();
+        let mut buffer = MockTransferBufferAbstract::new();
+        buffer
+            .expect_append()
+            .withf(|transfers| transfers.len() == 2)
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let service = IngestService::new(Arc::new(repo), Some(Arc::new(buffer)));
+        let ingested = service.ingest(vec![transfer(), transfer()]).await.unwrap();
+
+        assert_eq!(ingested, Ingested::Buffered { accepted: 2 });
+    }
+
+    #[actix_web::test]
+    async fn test_full_buffer_is_retryable() {
+        let mut buffer = MockTransferBufferAbstract::new();
+        buffer
+            .expect_append()
+            .returning(|_| Err(TransferRepoError::BufferFull("1 of 1 bytes".to_string())));
+
+        let service = IngestService::new(
+            Arc::new(MockTransferRepoAbstract::new()),
+            Some(Arc::new(buffer)),
+        );
+        let error = service.ingest(vec![transfer()]).await.unwrap_err();
+
+        assert!(error.is_retryable());
+        assert_eq!(error.code(), "buffer_full");
+    }
+
+    #[actix_web::test]
+    async fn test_without_buffer_inserts_directly() {
+        let mut repo = MockTransferRepoAbstract::new();
+        repo.expect_save_all()
+            .times(1)
+            .returning(|_| Ok(InsertReport::default()));
+
+        let service: IngestService<_, MockTransferBufferAbstract> =
+            IngestService::new(Arc::new(repo), None);
+
+        assert!(matches!(
+            service.ingest(vec![transfer()]).await.unwrap(),
+            Ingested::Inserted { .. }
+        ));
+        assert!(service.ingest(Vec::new()).await.is_err());
+    }
+}
@@ -0,0 +1,179 @@
+use std::{
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::domain::{
+    entities::integrity::{IntegrityReport, TransferCheck},
+    repositories::transfer_repo::TransferRepoAbstract,
+};
+
+use super::errors::TransferError;
+
+pub type IntegrityServiceResult<T> = Result<T, TransferError>;
+
+/// Transfers up to this far ahead of the server clock are not reported as
+/// future timestamps, to tolerate clock drift between producers.
+pub const FUTURE_TOLERANCE_SECS: u64 = 60;
+
+pub struct IntegrityService<T>
+where
+    T: TransferRepoAbstract,
+{
+    transfer_repo: Arc<T>,
+}
+
+impl<T> IntegrityService<T>
+where
+    T: TransferRepoAbstract,
+{
+    pub fn new(transfer_repo: Arc<T>) -> Self {
+        Self { transfer_repo }
+    }
+
+    pub async fn check(&self, sample_limit: usize) -> IntegrityServiceResult<IntegrityReport> {
+        let checked_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|now| now.as_secs())
+            .unwrap_or_default();
+        let repo = &self.transfer_repo;
+
+        Ok(IntegrityReport {
+            checked_at,
+            negative_balances: repo.find_negative_balances(sample_limit).await?,
+            self_transfers: repo
+                .find_anomalies(TransferCheck::SelfTransfer, sample_limit)
+                .await?,
+            invalid_amounts: repo
+                .find_anomalies(TransferCheck::InvalidAmount, sample_limit)
+                .await?,
+            invalid_prices: repo
+                .find_anomalies(TransferCheck::InvalidPrice, sample_limit)
+                .await?,
+            future_timestamps: repo
+                .find_anomalies(
+                    TransferCheck::FutureTimestamp {
+                        after: checked_at + FUTURE_TOLERANCE_SECS,
+                    },
+                    sample_limit,
+                )
+                .await?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{
+        entities::{
+            integrity::{IntegrityIssue, NegativeBalance},
+            transfer::Transfer,
+        },
+        repositories::{errors::TransferRepoError, transfer_repo::MockTransferRepoAbstract},
+    };
+
+    fn self_transfer() -> Transfer {
+        Transfer {
+            ts: 100,
+            from: "0x123".to_string(),
+            to: "0x123".to_string(),
+            amount: 10.0,
+            usd_price: 1.0,
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_check_collects_every_issue() {
+        let mut mock_repo = MockTransferRepoAbstract::new();
+
+        mock_repo
+            .expect_find_negative_balances()
+            .times(1)
+            .returning(|_| {
+                Ok(IntegrityIssue {
+                    count: 1,
+                    samples: vec![NegativeBalance {
+                        address: "0x456".to_string(),
+                        min_balance: -5.0,
+                        first_negative_ts: 200,
+                    }],
+                })
+            });
+        mock_repo
+            .expect_find_anomalies()
+            .times(4)
+            .returning(|check, _| {
+                let issue = match check {
+                    TransferCheck::SelfTransfer => IntegrityIssue {
+                        count: 2,
+                        samples: vec![self_transfer()],
+                    },
+                    _ => IntegrityIssue {
+                        count: 0,
+                        samples: Vec::new(),
+                    },
+                };
+                Ok(issue)
+            });
+
+        let service = IntegrityService::new(Arc::new(mock_repo));
+        let report = service.check(5).await.unwrap();
+
+        assert_eq!(report.negative_balances.count, 1);
+        assert_eq!(report.self_transfers.count, 2);
+        assert_eq!(report.invalid_amounts.count, 0);
+        assert_eq!(report.total_issues(), 3);
+    }
+
+    #[actix_web::test]
+    async fn test_check_future_cutoff_is_after_now() {
+        let mut mock_repo = MockTransferRepoAbstract::new();
+
+        mock_repo.expect_find_negative_balances().returning(|_| {
+            Ok(IntegrityIssue {
+                count: 0,
+                samples: Vec::new(),
+            })
+        });
+        mock_repo
+            .expect_find_anomalies()
+            .withf(|check, _| match check {
+                TransferCheck::FutureTimestamp { after } => {
+                    let now = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap()
+                        .as_secs();
+                    *after >= now
+                }
+                _ => true,
+            })
+            .times(4)
+            .returning(|_, _| {
+                Ok(IntegrityIssue {
+                    count: 0,
+                    samples: Vec::new(),
+                })
+            });
+
+        let service = IntegrityService::new(Arc::new(mock_repo));
+        let report = service.check(5).await.unwrap();
+
+        assert_eq!(report.total_issues(), 0);
+    }
+
+    #[actix_web::test]
+    async fn test_check_repo_error() {
+        let mut mock_repo = MockTransferRepoAbstract::new();
+
+        mock_repo
+            .expect_find_negative_balances()
+            .times(1)
+            .returning(|_| Err(TransferRepoError::QueryError("DB Error".to_string())));
+
+        let service = IntegrityService::new(Arc::new(mock_repo));
+        let result = service.check(5).await;
+
+        assert!(result.is_err());
+    }
+}
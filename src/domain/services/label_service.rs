@@ -0,0 +1,302 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::domain::{
+    entities::{
+        label::{AddressLabel, LabelCategory},
+        user_stats::EnrichedUserStats,
+    },
+    repositories::label_repo::LabelRepoAbstract,
+};
+
+use super::errors::TransferError;
+
+pub type LabelServiceResult<T> = Result<T, TransferError>;
+
+pub struct LabelService<L>
+where
+    L: LabelRepoAbstract,
+{
+    label_repo: Arc<L>,
+}
+
+impl<L> LabelService<L>
+where
+    L: LabelRepoAbstract,
+{
+    pub fn new(label_repo: Arc<L>) -> Self {
+        Self { label_repo }
+    }
+
+    pub async fn get(&self, address: &str) -> LabelServiceResult<AddressLabel> {
+        self.label_repo
+            .find_by_address(address)
+            .await?
+            .ok_or_else(|| TransferError::LabelNotFound {
+                address: address.to_string(),
+            })
+    }
+
+    pub async fn list(
+        &self,
+        category: Option<LabelCategory>,
+    ) -> LabelServiceResult<Vec<AddressLabel>> {
+        let labels = self.label_repo.find_all(category).await?;
+        Ok(labels)
+    }
+
+    pub async fn upsert(&self, label: AddressLabel) -> LabelServiceResult<AddressLabel> {
+        validate_label(&label)?;
+        self.label_repo
+            .save_all(std::slice::from_ref(&label))
+            .await?;
+        Ok(label)
+    }
+
+    pub async fn delete(&self, address: &str) -> LabelServiceResult<()> {
+        self.get(address).await?;
+        self.label_repo.delete(address).await?;
+        Ok(())
+    }
+
+    /// Imports `address,name,category,tags` rows, where `tags` is a
+    /// `;`-separated list. Returns the number of imported labels.
+    pub async fn import_csv(&self, content: &str) -> LabelServiceResult<usize> {
+        let labels = parse_labels_csv(content)?;
+        self.label_repo.save_all(&labels).await?;
+        Ok(labels.len())
+    }
+
+    /// Attaches labels to `stats` and drops addresses labelled with one of
+    /// `exclude`.
+    pub async fn apply_labels(
+        &self,
+        stats: Vec<EnrichedUserStats>,
+        attach: bool,
+        exclude: &[LabelCategory],
+    ) -> LabelServiceResult<Vec<EnrichedUserStats>> {
+        if !attach && exclude.is_empty() {
+            return Ok(stats);
+        }
+
+        let mut labels: HashMap<String, AddressLabel> = self
+            .label_repo
+            .find_all(None)
+            .await?
+            .into_iter()
+            .map(|label| (label.address.clone(), label))
+            .collect();
+
+        let result = stats
+            .into_iter()
+            .filter_map(|mut item| {
+                let label = labels.remove(&item.stats.address);
+                if label
+                    .as_ref()
+                    .is_some_and(|label| exclude.contains(&label.category))
+                {
+                    return None;
+                }
+                if attach {
+                    item.label = label;
+                }
+                Some(item)
+            })
+            .collect();
+
+        Ok(result)
+    }
+}
+
+fn validate_label(label: &AddressLabel) -> LabelServiceResult<()> {
+    if label.address.trim().is_empty() {
+        return Err(TransferError::InvalidInput(
+            "Label address must not be empty".to_string(),
+        ));
+    }
+    if label.name.trim().is_empty() {
+        return Err(TransferError::InvalidInput(format!(
+            "Label name must not be empty for address {}",
+            label.address
+        )));
+    }
+    Ok(())
+}
+
+/// Reads `address,name,category[,tags]` records. Fields may be quoted, so
+/// names can contain commas; a first record starting with `address` is
+/// taken as the header.
+fn parse_labels_csv(content: &str) -> LabelServiceResult<Vec<AddressLabel>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .comment(Some(b'#'))
+        .trim(csv::Trim::All)
+        .from_reader(content.as_bytes());
+    let mut labels = Vec::new();
+
+    for (index, record) in reader.records().enumerate() {
+        let record = record.map_err(|e| {
+            let line = e.position().map_or(0, |position| position.line());
+            TransferError::InvalidInput(format!("Line {line}: {e}"))
+        })?;
+        let line = record.position().map_or(0, |position| position.line());
+        let invalid =
+            |reason: String| TransferError::InvalidInput(format!("Line {line}: {reason}"));
+
+        if index == 0 && record.get(0) == Some("address") {
+            continue;
+        }
+        if record.iter().all(str::is_empty) {
+            continue;
+        }
+        if record.len() < 3 || record.len() > 4 {
+            return Err(invalid(format!(
+                "expected address,name,category[,tags], got {} fields",
+                record.len()
+            )));
+        }
+
+        let category = record[2].parse::<LabelCategory>().map_err(invalid)?;
+        let tags = record
+            .get(3)
+            .map(|tags| {
+                tags.split(';')
+                    .map(str::trim)
+                    .filter(|tag| !tag.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let label = AddressLabel {
+            address: record[0].to_string(),
+            name: record[1].to_string(),
+            category,
+            tags,
+        };
+        validate_label(&label).map_err(|e| invalid(e.to_string()))?;
+        labels.push(label);
+    }
+
+    Ok(labels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{
+        entities::user_stats::UserStats, repositories::label_repo::MockLabelRepoAbstract,
+    };
+
+    fn exchange_label() -> AddressLabel {
+        AddressLabel {
+            address: "0x123".to_string(),
+            name: "Big Exchange".to_string(),
+            category: LabelCategory::Exchange,
+            tags: vec!["hot-wallet".to_string()],
+        }
+    }
+
+    fn create_test_stats() -> Vec<EnrichedUserStats> {
+        vec![
+            UserStats::new("0x123".to_string(), 1000.0, 1.0, 1.5, 500.0).into(),
+            UserStats::new("0x456".to_string(), 800.0, 1.2, 1.1, 400.0).into(),
+        ]
+    }
+
+    #[test]
+    fn test_parse_labels_csv() {
+        let content =
+            "address,name,category,tags\n0x123,Big Exchange,exchange,hot;cex\n0x456,Alice,user\n";
+
+        let labels = parse_labels_csv(content).unwrap();
+
+        assert_eq!(labels.len(), 2);
+        assert_eq!(labels[0].category, LabelCategory::Exchange);
+        assert_eq!(labels[0].tags, vec!["hot", "cex"]);
+        assert!(labels[1].tags.is_empty());
+    }
+
+    #[test]
+    fn test_parse_labels_csv_reports_line() {
+        let err = parse_labels_csv("0x123,Big Exchange,exchange\n0x456,Bob,whale\n").unwrap_err();
+
+        assert!(err.to_string().contains("Line 2"));
+    }
+
+    #[test]
+    fn test_parse_labels_csv_quoted_fields() {
+        let content = "# exported labels\n\naddress,name,category,tags\n\
+            0x123,\"Exchange, Inc.\",exchange,\"hot;cex\"\n\
+            0x456,\"Bob \"\"the whale\"\"\",user\n";
+
+        let labels = parse_labels_csv(content).unwrap();
+
+        assert_eq!(labels.len(), 2);
+        assert_eq!(labels[0].name, "Exchange, Inc.");
+        assert_eq!(labels[0].tags, vec!["hot", "cex"]);
+        assert_eq!(labels[1].name, "Bob \"the whale\"");
+    }
+
+    #[test]
+    fn test_parse_labels_csv_header_only_first() {
+        let err = parse_labels_csv("0x123,Alice,user\naddress,name,category\n").unwrap_err();
+
+        assert!(err.to_string().contains("Line 2"));
+    }
+
+    #[actix_web::test]
+    async fn test_get_missing_label() {
+        let mut mock_repo = MockLabelRepoAbstract::new();
+
+        mock_repo
+            .expect_find_by_address()
+            .times(1)
+            .returning(|_| Ok(None));
+
+        let service = LabelService::new(Arc::new(mock_repo));
+        let result = service.get("0x999").await;
+
+        assert!(matches!(result, Err(TransferError::LabelNotFound { .. })));
+    }
+
+    #[actix_web::test]
+    async fn test_upsert_rejects_empty_name() {
+        let mut mock_repo = MockLabelRepoAbstract::new();
+        mock_repo.expect_save_all().never();
+
+        let service = LabelService::new(Arc::new(mock_repo));
+        let mut label = exchange_label();
+        label.name = " ".to_string();
+
+        assert!(service.upsert(label).await.is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_apply_labels_attaches_and_excludes() {
+        let mut mock_repo = MockLabelRepoAbstract::new();
+
+        mock_repo
+            .expect_find_all()
+            .times(2)
+            .returning(|_| Ok(vec![exchange_label()]));
+
+        let service = LabelService::new(Arc::new(mock_repo));
+
+        let labelled = service
+            .apply_labels(create_test_stats(), true, &[])
+            .await
+            .unwrap();
+        assert_eq!(labelled.len(), 2);
+        assert_eq!(labelled[0].label, Some(exchange_label()));
+        assert_eq!(labelled[1].label, None);
+
+        let filtered = service
+            .apply_labels(create_test_stats(), false, &[LabelCategory::Exchange])
+            .await
+            .unwrap();
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].stats.address, "0x456");
+        assert_eq!(filtered[0].label, None);
+    }
+}
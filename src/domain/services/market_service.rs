@@ -0,0 +1,198 @@
+use std::sync::Arc;
+
+use crate::domain::{
+    entities::{
+        candle::{Candle, CandleInterval},
+        time_range::TimeRange,
+    },
+    repositories::transfer_repo::TransferRepoAbstract,
+};
+
+use super::errors::TransferError;
+
+pub type MarketServiceResult<T> = Result<T, TransferError>;
+
+/// Upper bound on candles per response, so a `1m` interval over a long
+/// window cannot produce an unbounded payload.
+pub const MAX_CANDLES: u64 = 10_000;
+
+pub struct MarketService<T>
+where
+    T: TransferRepoAbstract,
+{
+    transfer_repo: Arc<T>,
+}
+
+impl<T> MarketService<T>
+where
+    T: TransferRepoAbstract,
+{
+    pub fn new(transfer_repo: Arc<T>) -> Self {
+        Self { transfer_repo }
+    }
+
+    pub async fn candles(
+        &self,
+        interval: CandleInterval,
+        range: &TimeRange,
+    ) -> MarketServiceResult<Vec<Candle>> {
+        let step = interval.secs();
+
+        if let (Some(from), Some(to)) = (range.from_ts, range.to_ts) {
+            if from > to {
+                return Err(TransferError::InvalidInput(
+                    "`from` must not be after `to`".to_string(),
+                ));
+            }
+            if (to - from) / step >= MAX_CANDLES {
+                return Err(TransferError::InvalidInput(format!(
+                    "Requested window spans more than {} candles",
+                    MAX_CANDLES
+                )));
+            }
+        }
+
+        let candles = self.transfer_repo.candles(step, range).await?;
+        Ok(fill_gaps(candles, step, range.from_ts, range.to_ts))
+    }
+}
+
+/// Inserts flat, zero-volume candles for empty buckets from `from` (or the
+/// first candle) to `to` (or the last candle). Each gap repeats the previous
+/// close; buckets before the first candle take its open. The result holds at
+/// most `MAX_CANDLES` buckets, the earliest ones.
+fn fill_gaps(candles: Vec<Candle>, step: u64, from: Option<u64>, to: Option<u64>) -> Vec<Candle> {
+    let (Some(first), Some(last)) = (candles.first(), candles.last()) else {
+        return candles;
+    };
+    let start = from
+        .map(|from| from - from % step)
+        .unwrap_or(first.bucket_start);
+    let end = to.map(|to| to - to % step).unwrap_or(last.bucket_start);
+    let mut price = first.open;
+
+    let mut candles = candles.into_iter().peekable();
+    let mut filled: Vec<Candle> = Vec::new();
+    let mut bucket = start;
+    while bucket <= end && filled.len() < MAX_CANDLES as usize {
+        match candles.next_if(|candle| candle.bucket_start == bucket) {
+            Some(candle) => {
+                price = candle.close;
+                filled.push(candle);
+            }
+            None => filled.push(flat_candle(bucket, price)),
+        }
+        bucket += step;
+    }
+
+    filled
+}
+
+fn flat_candle(bucket_start: u64, price: f64) -> Candle {
+    Candle {
+        bucket_start,
+        open: price,
+        high: price,
+        low: price,
+        close: price,
+        volume: 0.0,
+        trade_count: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::repositories::transfer_repo::MockTransferRepoAbstract;
+
+    fn candle(bucket_start: u64, open: f64, close: f64) -> Candle {
+        Candle {
+            bucket_start,
+            open,
+            high: open.max(close),
+            low: open.min(close),
+            close,
+            volume: 10.0,
+            trade_count: 2,
+        }
+    }
+
+    #[test]
+    fn test_fill_gaps_repeats_previous_close() {
+        let candles = vec![candle(60, 1.0, 1.2), candle(240, 1.3, 1.1)];
+
+        let filled = fill_gaps(candles, 60, None, None);
+
+        let buckets: Vec<u64> = filled.iter().map(|c| c.bucket_start).collect();
+        assert_eq!(buckets, vec![60, 120, 180, 240]);
+        assert_eq!(filled[1], flat_candle(120, 1.2));
+        assert_eq!(filled[2].volume, 0.0);
+        assert_eq!(filled[3].open, 1.3);
+    }
+
+    #[test]
+    fn test_fill_gaps_extends_to_end_of_range() {
+        let filled = fill_gaps(vec![candle(0, 1.0, 2.0)], 60, None, Some(150));
+
+        let buckets: Vec<u64> = filled.iter().map(|c| c.bucket_start).collect();
+        assert_eq!(buckets, vec![0, 60, 120]);
+        assert_eq!(filled[2].close, 2.0);
+    }
+
+    #[test]
+    fn test_fill_gaps_empty() {
+        assert!(fill_gaps(Vec::new(), 60, Some(0), Some(600)).is_empty());
+    }
+
+    #[test]
+    fn test_fill_gaps_starts_at_beginning_of_range() {
+        let filled = fill_gaps(vec![candle(120, 1.5, 2.0)], 60, Some(30), None);
+
+        let buckets: Vec<u64> = filled.iter().map(|c| c.bucket_start).collect();
+        assert_eq!(buckets, vec![0, 60, 120]);
+        assert_eq!(filled[0], flat_candle(0, 1.5));
+        assert_eq!(filled[2].close, 2.0);
+    }
+
+    #[test]
+    fn test_fill_gaps_keeps_earliest_buckets() {
+        let last = 60 * (MAX_CANDLES + 5);
+        let candles = vec![candle(0, 1.0, 1.0), candle(last, 2.0, 2.0)];
+
+        let filled = fill_gaps(candles, 60, None, None);
+
+        assert_eq!(filled.len(), MAX_CANDLES as usize);
+        assert_eq!(filled.last().unwrap().bucket_start, 60 * (MAX_CANDLES - 1));
+    }
+
+    #[actix_web::test]
+    async fn test_candles_rejects_oversized_window() {
+        let mut mock_repo = MockTransferRepoAbstract::new();
+        mock_repo.expect_candles().never();
+
+        let service = MarketService::new(Arc::new(mock_repo));
+        let range = TimeRange::new(Some(0), Some(60 * MAX_CANDLES));
+        let result = service.candles(CandleInterval::Minute, &range).await;
+
+        assert!(matches!(result, Err(TransferError::InvalidInput(_))));
+    }
+
+    #[actix_web::test]
+    async fn test_candles_uses_interval_step() {
+        let mut mock_repo = MockTransferRepoAbstract::new();
+
+        mock_repo
+            .expect_candles()
+            .withf(|step, _| *step == 3_600)
+            .times(1)
+            .returning(|_, _| Ok(vec![candle(0, 1.0, 1.1), candle(7_200, 1.2, 1.0)]));
+
+        let service = MarketService::new(Arc::new(mock_repo));
+        let candles = service
+            .candles(CandleInterval::Hour, &TimeRange::default())
+            .await
+            .unwrap();
+
+        assert_eq!(candles.len(), 3);
+    }
+}
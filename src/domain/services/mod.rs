@@ -1,2 +1,11 @@
+pub mod analysis_service;
+pub mod cluster_service;
 pub mod errors;
+pub mod graph_service;
+pub mod ingest_service;
+pub mod integrity_service;
+pub mod label_service;
+pub mod market_service;
+pub mod price_service;
 pub mod stats_service;
+pub mod stream_service;
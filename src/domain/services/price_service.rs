@@ -0,0 +1,127 @@
+use std::sync::Arc;
+
+use crate::domain::{
+    entities::{price::PricePoint, time_range::TimeRange},
+    repositories::price_repo::PriceRepoAbstract,
+};
+
+use super::errors::TransferError;
+
+pub type PriceServiceResult<T> = Result<T, TransferError>;
+
+pub struct PriceService<P>
+where
+    P: PriceRepoAbstract,
+{
+    price_repo: Arc<P>,
+}
+
+impl<P> PriceService<P>
+where
+    P: PriceRepoAbstract,
+{
+    pub fn new(price_repo: Arc<P>) -> Self {
+        Self { price_repo }
+    }
+
+    /// Stores a price series. Points with an existing `(token, ts)` replace
+    /// the stored price, which is how corrected feeds are applied.
+    pub async fn upload(&self, prices: &[PricePoint]) -> PriceServiceResult<usize> {
+        for point in prices {
+            if point.token.trim().is_empty() {
+                return Err(TransferError::InvalidInput(
+                    "Price token must not be empty".to_string(),
+                ));
+            }
+            if !point.price.is_finite() || point.price < 0.0 {
+                return Err(TransferError::InvalidInput(format!(
+                    "Invalid price {} for {} at {}",
+                    point.price, point.token, point.ts
+                )));
+            }
+        }
+
+        self.price_repo.save_all(prices).await?;
+        Ok(prices.len())
+    }
+
+    pub async fn price_at(&self, token: &str, at: u64) -> PriceServiceResult<PricePoint> {
+        self.price_repo
+            .price_at(token, at)
+            .await?
+            .ok_or_else(|| TransferError::PriceNotFound {
+                token: token.to_string(),
+                ts: at,
+            })
+    }
+
+    pub async fn prices(
+        &self,
+        token: &str,
+        range: &TimeRange,
+    ) -> PriceServiceResult<Vec<PricePoint>> {
+        let prices = self.price_repo.prices(token, range).await?;
+        Ok(prices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::repositories::price_repo::MockPriceRepoAbstract;
+
+    fn point(ts: u64, price: f64) -> PricePoint {
+        PricePoint {
+            token: "ETH".to_string(),
+            ts,
+            price,
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_upload_saves_series() {
+        let mut mock_repo = MockPriceRepoAbstract::new();
+
+        mock_repo
+            .expect_save_all()
+            .withf(|prices| prices.len() == 2)
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let service = PriceService::new(Arc::new(mock_repo));
+        let saved = service
+            .upload(&[point(100, 1.5), point(200, 1.6)])
+            .await
+            .unwrap();
+
+        assert_eq!(saved, 2);
+    }
+
+    #[actix_web::test]
+    async fn test_upload_rejects_non_finite_price() {
+        let mut mock_repo = MockPriceRepoAbstract::new();
+        mock_repo.expect_save_all().never();
+
+        let service = PriceService::new(Arc::new(mock_repo));
+        let result = service
+            .upload(&[point(100, 1.5), point(200, f64::NAN)])
+            .await;
+
+        assert!(matches!(result, Err(TransferError::InvalidInput(_))));
+    }
+
+    #[actix_web::test]
+    async fn test_price_at_missing() {
+        let mut mock_repo = MockPriceRepoAbstract::new();
+
+        mock_repo
+            .expect_price_at()
+            .times(1)
+            .returning(|_, _| Ok(None));
+
+        let service = PriceService::new(Arc::new(mock_repo));
+        let result = service.price_at("ETH", 50).await;
+
+        assert!(matches!(result, Err(TransferError::PriceNotFound { .. })));
+    }
+}
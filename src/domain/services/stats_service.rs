@@ -1,7 +1,10 @@
 use std::sync::Arc;
 
+use futures::{StreamExt, TryStreamExt, stream::BoxStream};
+
 use crate::domain::{
-    entities::user_stats::UserStats, repositories::transfer_repo::TransferRepoAbstract,
+    entities::{summary::MarketSummary, time_range::TimeRange, user_stats::UserStats},
+    repositories::transfer_repo::TransferRepoAbstract,
 };
 
 use super::errors::TransferError;
@@ -27,13 +30,54 @@ where
         let stats = self.transfer_repo.calculate_user_stats().await?;
         Ok(stats)
     }
+
+    /// Yields the same rows as `calculate_user_stats` one at a time, so
+    /// memory stays flat no matter how many addresses there are.
+    pub fn stream_user_stats(&self) -> BoxStream<'static, StatsServiceResult<UserStats>> {
+        self.transfer_repo
+            .stream_user_stats()
+            .map_err(TransferError::from)
+            .boxed()
+    }
+
+    /// Same as `calculate_user_stats`, but values every transfer with the
+    /// oracle price of `token` instead of its own `usd_price`.
+    pub async fn calculate_user_stats_with_prices(
+        &self,
+        token: &str,
+    ) -> StatsServiceResult<Vec<UserStats>> {
+        let stats = self
+            .transfer_repo
+            .calculate_user_stats_with_prices(token)
+            .await?;
+        Ok(stats)
+    }
+
+    pub async fn summary(&self, range: &TimeRange) -> StatsServiceResult<MarketSummary> {
+        if range.start() > range.end() {
+            return Err(TransferError::InvalidInput(
+                "`from_ts` must not be after `to_ts`".to_string(),
+            ));
+        }
+
+        let totals = self.transfer_repo.transfer_totals(range).await?;
+        let daily_active_addresses = self.transfer_repo.daily_active_addresses(range).await?;
+
+        Ok(MarketSummary {
+            totals,
+            daily_active_addresses,
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::domain::{
-        entities::user_stats::UserStats,
+        entities::{
+            summary::{DailyActivity, TransferTotals},
+            user_stats::UserStats,
+        },
         repositories::{errors::TransferRepoError, transfer_repo::MockTransferRepoAbstract},
     };
     use std::sync::Arc;
@@ -78,4 +122,98 @@ mod tests {
 
         assert!(result.is_err());
     }
+
+    #[actix_web::test]
+    async fn test_stream_user_stats() {
+        let mut mock_repo = MockTransferRepoAbstract::new();
+
+        mock_repo
+            .expect_stream_user_stats()
+            .times(1)
+            .returning(|| futures::stream::iter(create_test_stats().into_iter().map(Ok)).boxed());
+        mock_repo.expect_calculate_user_stats().never();
+
+        let service = StatsService::new(Arc::new(mock_repo));
+        let stats: Vec<_> = service.stream_user_stats().try_collect().await.unwrap();
+
+        assert_eq!(stats.len(), 3);
+        assert_eq!(stats[2].address, "0x789");
+    }
+
+    #[actix_web::test]
+    async fn test_calculate_user_stats_with_prices() {
+        let mut mock_repo = MockTransferRepoAbstract::new();
+
+        mock_repo
+            .expect_calculate_user_stats_with_prices()
+            .withf(|token| token == "ETH")
+            .times(1)
+            .returning(|_| Ok(create_test_stats()));
+        mock_repo.expect_calculate_user_stats().never();
+
+        let service = StatsService::new(Arc::new(mock_repo));
+        let stats = service
+            .calculate_user_stats_with_prices("ETH")
+            .await
+            .unwrap();
+
+        assert_eq!(stats.len(), 3);
+    }
+
+    #[actix_web::test]
+    async fn test_summary_combines_totals_and_daily_activity() {
+        let mut mock_repo = MockTransferRepoAbstract::new();
+
+        mock_repo.expect_transfer_totals().times(1).returning(|_| {
+            Ok(TransferTotals {
+                total_volume: 300.0,
+                total_usd_volume: 450.0,
+                transfer_count: 3,
+                unique_senders: 2,
+                unique_receivers: 3,
+                vwap: 1.5,
+                median_transfer_size: 100.0,
+                p25_transfer_size: 50.0,
+                p75_transfer_size: 150.0,
+                p90_transfer_size: 150.0,
+                p99_transfer_size: 150.0,
+            })
+        });
+        mock_repo
+            .expect_daily_active_addresses()
+            .withf(|range| range.from_ts == Some(0) && range.to_ts == Some(172_800))
+            .times(1)
+            .returning(|_| {
+                Ok(vec![
+                    DailyActivity {
+                        day: 0,
+                        active_addresses: 3,
+                    },
+                    DailyActivity {
+                        day: 86_400,
+                        active_addresses: 2,
+                    },
+                ])
+            });
+
+        let service = StatsService::new(Arc::new(mock_repo));
+        let summary = service
+            .summary(&TimeRange::new(Some(0), Some(172_800)))
+            .await
+            .unwrap();
+
+        assert_eq!(summary.totals.transfer_count, 3);
+        assert_eq!(summary.daily_active_addresses.len(), 2);
+    }
+
+    #[actix_web::test]
+    async fn test_summary_rejects_inverted_range() {
+        let mut mock_repo = MockTransferRepoAbstract::new();
+        mock_repo.expect_transfer_totals().never();
+
+        let service = StatsService::new(Arc::new(mock_repo));
+        let result = service.summary(&TimeRange::new(Some(10), Some(5))).await;
+
+        assert!(matches!(result, Err(TransferError::InvalidInput(_))));
+    }
 }
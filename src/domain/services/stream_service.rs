@@ -0,0 +1,553 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::domain::{
+    entities::{transfer::Transfer, user_stats::UserStats},
+    repositories::transfer_repo::TransferRepoAbstract,
+};
+
+use super::errors::TransferError;
+
+/// Inserted batches, handed to every live subscription. Publishing never
+/// waits: a subscriber that falls `capacity` batches behind skips ahead.
+#[derive(Clone)]
+pub struct TransferFeed {
+    sender: broadcast::Sender<Arc<[Transfer]>>,
+}
+
+impl TransferFeed {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        Self { sender }
+    }
+
+    pub fn publish(&self, transfers: &[Transfer]) {
+        // Copying the batch is only worth it when someone listens.
+        if self.sender.receiver_count() > 0 {
+            let _ = self.sender.send(transfers.into());
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<[Transfer]>> {
+        self.sender.subscribe()
+    }
+}
+
+/// What a subscription receives. Without `addresses` every transfer
+/// matches and no stats are sent.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StreamFilter {
+    /// Transfers from or to these addresses, and their stats.
+    pub addresses: Vec<String>,
+    /// Transfers of at least this amount; stats still count all of them.
+    pub min_amount: Option<f64>,
+}
+
+impl StreamFilter {
+    fn is_subscribed(&self, address: &str) -> bool {
+        self.addresses
+            .iter()
+            .any(|subscribed| subscribed.eq_ignore_ascii_case(address))
+    }
+
+    fn matches(&self, transfer: &Transfer) -> bool {
+        let involved = self.addresses.is_empty()
+            || self.is_subscribed(&transfer.from)
+            || self.is_subscribed(&transfer.to);
+        involved && self.min_amount.is_none_or(|min| transfer.amount >= min)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEvent {
+    Transfers {
+        transfers: Vec<Transfer>,
+    },
+    /// Current stats of the subscribed addresses the preceding transfers
+    /// touched.
+    Stats {
+        stats: Vec<UserStats>,
+    },
+    /// Batches dropped because the subscriber read too slowly; anything
+    /// they changed has to be fetched again.
+    Lagged {
+        skipped: u64,
+    },
+}
+
+/// Stats updates kept for subscriptions that have not read them yet.
+const STATS_CAPACITY: usize = 64;
+
+pub struct StreamService<T>
+where
+    T: TransferRepoAbstract,
+{
+    transfer_repo: Arc<T>,
+    feed: TransferFeed,
+    max_addresses: usize,
+    subscribed: Arc<SubscribedAddresses>,
+    stats: broadcast::Sender<Arc<[UserStats]>>,
+}
+
+impl<T> StreamService<T>
+where
+    T: TransferRepoAbstract,
+{
+    pub fn new(transfer_repo: Arc<T>, feed: TransferFeed, max_addresses: usize) -> Self {
+        let (stats, _) = broadcast::channel(STATS_CAPACITY);
+        Self {
+            transfer_repo,
+            feed,
+            max_addresses,
+            subscribed: Arc::default(),
+            stats,
+        }
+    }
+
+    /// Follows the batches inserted from now on.
+    pub fn subscribe(&self, filter: StreamFilter) -> Result<Subscription, TransferError> {
+        let mut subscription = Subscription {
+            receiver: self.feed.subscribe(),
+            stats: self.stats.subscribe(),
+            stats_open: true,
+            subscribed: self.subscribed.clone(),
+            filter: StreamFilter::default(),
+            max_addresses: self.max_addresses,
+        };
+        subscription.set_filter(filter)?;
+        Ok(subscription)
+    }
+
+    /// The task that sends stats to the subscriptions; it follows the
+    /// batches published from now on.
+    pub fn stats_refresher(&self) -> StatsRefresher<T> {
+        StatsRefresher {
+            transfer_repo: self.transfer_repo.clone(),
+            receiver: self.feed.subscribe(),
+            subscribed: self.subscribed.clone(),
+            stats: self.stats.clone(),
+        }
+    }
+}
+
+/// Addresses any live subscription follows, lowercased, with the number of
+/// subscriptions following each.
+#[derive(Default)]
+struct SubscribedAddresses(Mutex<HashMap<String, usize>>);
+
+impl SubscribedAddresses {
+    fn add(&self, filter: &StreamFilter) {
+        let mut counts = self.0.lock().unwrap();
+        for address in distinct(filter) {
+            *counts.entry(address).or_default() += 1;
+        }
+    }
+
+    fn remove(&self, filter: &StreamFilter) {
+        let mut counts = self.0.lock().unwrap();
+        for address in distinct(filter) {
+            if let Some(count) = counts.get_mut(&address) {
+                *count -= 1;
+                if *count == 0 {
+                    counts.remove(&address);
+                }
+            }
+        }
+    }
+
+    /// The addresses of `batches` that someone follows, each once.
+    fn touched(&self, batches: &[Arc<[Transfer]>]) -> Vec<String> {
+        let counts = self.0.lock().unwrap();
+        let mut touched = Vec::new();
+        for transfer in batches.iter().flat_map(|batch| batch.iter()) {
+            for address in [&transfer.from, &transfer.to] {
+                if counts.contains_key(&address.to_ascii_lowercase()) && !touched.contains(address)
+                {
+                    touched.push(address.clone());
+                }
+            }
+        }
+        touched
+    }
+}
+
+fn distinct(filter: &StreamFilter) -> HashSet<String> {
+    filter
+        .addresses
+        .iter()
+        .map(|address| address.to_ascii_lowercase())
+        .collect()
+}
+
+/// Queries the stats of the subscribed addresses each batch touched, once
+/// for all subscriptions, and hands the rows to every one of them.
+pub struct StatsRefresher<T> {
+    transfer_repo: Arc<T>,
+    receiver: broadcast::Receiver<Arc<[Transfer]>>,
+    subscribed: Arc<SubscribedAddresses>,
+    stats: broadcast::Sender<Arc<[UserStats]>>,
+}
+
+impl<T> StatsRefresher<T>
+where
+    T: TransferRepoAbstract,
+{
+    /// Runs until the feed is gone.
+    pub async fn run(mut self) {
+        loop {
+            let batch = match self.receiver.recv().await {
+                Ok(batch) => batch,
+                // Subscriptions are told about the batches themselves.
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return,
+            };
+
+            // Batches that queued up during the last query share the next.
+            let mut batches = vec![batch];
+            while let Ok(batch) = self.receiver.try_recv() {
+                batches.push(batch);
+            }
+
+            let touched = self.subscribed.touched(&batches);
+            if touched.is_empty() {
+                continue;
+            }
+            // Stats are totals rather than deltas, so after a failure the
+            // next change of an address brings it up to date.
+            match self.transfer_repo.user_stats_for(&touched).await {
+                Ok(stats) if !stats.is_empty() => {
+                    let _ = self.stats.send(stats.into());
+                }
+                Ok(_) => {}
+                Err(err) => log::warn!("Streaming stats failed: {err}"),
+            }
+        }
+    }
+}
+
+enum Received {
+    Batch(Result<Arc<[Transfer]>, RecvError>),
+    Stats(Result<Arc<[UserStats]>, RecvError>),
+}
+
+pub struct Subscription {
+    receiver: broadcast::Receiver<Arc<[Transfer]>>,
+    stats: broadcast::Receiver<Arc<[UserStats]>>,
+    /// Cleared once the refresher is gone, so its closed channel is not
+    /// polled again.
+    stats_open: bool,
+    subscribed: Arc<SubscribedAddresses>,
+    filter: StreamFilter,
+    max_addresses: usize,
+}
+
+impl Subscription {
+    /// Replaces the filter; batches already received are not matched again.
+    pub fn set_filter(&mut self, filter: StreamFilter) -> Result<(), TransferError> {
+        if filter.addresses.len() > self.max_addresses {
+            return Err(TransferError::InvalidInput(format!(
+                "At most {} addresses can be subscribed to",
+                self.max_addresses
+            )));
+        }
+        if filter
+            .min_amount
+            .is_some_and(|min| !(min.is_finite() && min >= 0.0))
+        {
+            return Err(TransferError::InvalidInput(
+                "`min_amount` must be a non-negative number".to_string(),
+            ));
+        }
+        self.subscribed.remove(&self.filter);
+        self.subscribed.add(&filter);
+        self.filter = filter;
+        Ok(())
+    }
+
+    /// The next event, or `None` once the feed is gone. Only waits on
+    /// channels, so a call dropped midway loses nothing and it can be raced
+    /// in a `select!`.
+    pub async fn next(&mut self) -> Option<StreamEvent> {
+        loop {
+            // Transfers first, so their stats don't overtake them.
+            let received = tokio::select! {
+                biased;
+                batch = self.receiver.recv() => Received::Batch(batch),
+                stats = self.stats.recv(), if self.stats_open => Received::Stats(stats),
+            };
+
+            match received {
+                Received::Batch(Ok(batch)) => {
+                    // Batches that queued up meanwhile go out as one event.
+                    let mut batches = vec![batch];
+                    while let Ok(batch) = self.receiver.try_recv() {
+                        batches.push(batch);
+                    }
+
+                    let transfers: Vec<Transfer> = batches
+                        .iter()
+                        .flat_map(|batch| batch.iter())
+                        .filter(|transfer| self.filter.matches(transfer))
+                        .cloned()
+                        .collect();
+                    if !transfers.is_empty() {
+                        return Some(StreamEvent::Transfers { transfers });
+                    }
+                }
+                Received::Batch(Err(RecvError::Lagged(skipped))) => {
+                    return Some(StreamEvent::Lagged { skipped });
+                }
+                Received::Batch(Err(RecvError::Closed)) => return None,
+                Received::Stats(Ok(stats)) => {
+                    let stats: Vec<UserStats> = stats
+                        .iter()
+                        .filter(|stats| self.filter.is_subscribed(&stats.address))
+                        .cloned()
+                        .collect();
+                    if !stats.is_empty() {
+                        return Some(StreamEvent::Stats { stats });
+                    }
+                }
+                // Later updates carry current totals.
+                Received::Stats(Err(RecvError::Lagged(_))) => {}
+                Received::Stats(Err(RecvError::Closed)) => self.stats_open = false,
+            }
+        }
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.subscribed.remove(&self.filter);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::FutureExt;
+
+    use super::*;
+    use crate::domain::repositories::{
+        errors::TransferRepoError, transfer_repo::MockTransferRepoAbstract,
+    };
+
+    fn transfer(from: &str, to: &str, amount: f64) -> Transfer {
+        Transfer {
+            ts: 1,
+            from: from.to_string(),
+            to: to.to_string(),
+            amount,
+            usd_price: 1.0,
+        }
+    }
+
+    fn stats(address: &str) -> UserStats {
+        UserStats::new(address.to_string(), 5.0, 1.0, 0.0, 5.0)
+    }
+
+    fn service(
+        transfer_repo: MockTransferRepoAbstract,
+    ) -> (StreamService<MockTransferRepoAbstract>, TransferFeed) {
+        let feed = TransferFeed::new(16);
+        let service = StreamService::new(Arc::new(transfer_repo), feed.clone(), 2);
+        actix_web::rt::spawn(service.stats_refresher().run());
+        (service, feed)
+    }
+
+    fn following(addresses: &[&str]) -> StreamFilter {
+        StreamFilter {
+            addresses: addresses.iter().map(|a| a.to_string()).collect(),
+            min_amount: None,
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_transfers_then_stats_of_subscribed_addresses() {
+        let mut transfer_repo = MockTransferRepoAbstract::new();
+        transfer_repo
+            .expect_user_stats_for()
+            .withf(|addresses| addresses == ["0xAAA".to_string()])
+            .times(1)
+            .returning(|_| Ok(vec![stats("0xAAA")]));
+        let (service, feed) = service(transfer_repo);
+        let filter = StreamFilter {
+            addresses: vec!["0xaaa".to_string()],
+            min_amount: Some(2.0),
+        };
+        let mut subscription = service.subscribe(filter).unwrap();
+
+        feed.publish(&[
+            transfer("0xAAA", "0xbbb", 1.0),
+            transfer("0xccc", "0xddd", 9.0),
+        ]);
+        feed.publish(&[transfer("0xbbb", "0xAAA", 3.0)]);
+
+        assert_eq!(
+            subscription.next().await,
+            Some(StreamEvent::Transfers {
+                transfers: vec![transfer("0xbbb", "0xAAA", 3.0)]
+            })
+        );
+        assert_eq!(
+            subscription.next().await,
+            Some(StreamEvent::Stats {
+                stats: vec![stats("0xAAA")]
+            })
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_unfiltered_subscription_gets_everything_without_stats() {
+        let (service, feed) = service(MockTransferRepoAbstract::new());
+        let mut subscription = service.subscribe(StreamFilter::default()).unwrap();
+
+        feed.publish(&[transfer("0xaaa", "0xbbb", 1.0)]);
+        drop(feed);
+        drop(service);
+
+        assert!(matches!(
+            subscription.next().await,
+            Some(StreamEvent::Transfers { transfers }) if transfers.len() == 1
+        ));
+        assert_eq!(subscription.next().await, None);
+    }
+
+    #[actix_web::test]
+    async fn test_failed_stats_are_skipped() {
+        let mut transfer_repo = MockTransferRepoAbstract::new();
+        transfer_repo.expect_user_stats_for().returning(|_| {
+            Err(TransferRepoError::DatabaseConnectionError(
+                "connection refused".to_string(),
+            ))
+        });
+        let (service, feed) = service(transfer_repo);
+        let filter = StreamFilter {
+            addresses: vec!["0xaaa".to_string()],
+            min_amount: None,
+        };
+        let mut subscription = service.subscribe(filter).unwrap();
+
+        feed.publish(&[transfer("0xaaa", "0xbbb", 1.0)]);
+        feed.publish(&[transfer("0xbbb", "0xaaa", 2.0)]);
+        assert!(matches!(
+            subscription.next().await,
+            Some(StreamEvent::Transfers { transfers }) if transfers.len() == 2
+        ));
+
+        feed.publish(&[transfer("0xaaa", "0xccc", 3.0)]);
+        assert!(matches!(
+            subscription.next().await,
+            Some(StreamEvent::Transfers { transfers }) if transfers.len() == 1
+        ));
+    }
+
+    #[actix_web::test]
+    async fn test_slow_subscriber_is_told_what_it_missed() {
+        let feed = TransferFeed::new(1);
+        let service =
+            StreamService::new(Arc::new(MockTransferRepoAbstract::new()), feed.clone(), 2);
+        let mut subscription = service.subscribe(StreamFilter::default()).unwrap();
+
+        for amount in [1.0, 2.0, 3.0] {
+            feed.publish(&[transfer("0xaaa", "0xbbb", amount)]);
+        }
+
+        assert_eq!(
+            subscription.next().await,
+            Some(StreamEvent::Lagged { skipped: 2 })
+        );
+        assert_eq!(
+            subscription.next().await,
+            Some(StreamEvent::Transfers {
+                transfers: vec![transfer("0xaaa", "0xbbb", 3.0)]
+            })
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_one_stats_query_per_batch_for_all_subscriptions() {
+        let mut transfer_repo = MockTransferRepoAbstract::new();
+        transfer_repo
+            .expect_user_stats_for()
+            .withf(|addresses| addresses == ["0xaaa".to_string(), "0xbbb".to_string()])
+            .times(1)
+            .returning(|_| Ok(vec![stats("0xaaa"), stats("0xbbb")]));
+        let (service, feed) = service(transfer_repo);
+        let mut first = service.subscribe(following(&["0xaaa"])).unwrap();
+        let mut second = service.subscribe(following(&["0xbbb", "0xccc"])).unwrap();
+        let unsubscribed = service.subscribe(following(&["0xddd"])).unwrap();
+        drop(unsubscribed);
+
+        feed.publish(&[
+            transfer("0xaaa", "0xbbb", 1.0),
+            transfer("0xddd", "0xeee", 1.0),
+        ]);
+
+        for (subscription, address) in [(&mut first, "0xaaa"), (&mut second, "0xbbb")] {
+            assert!(matches!(
+                subscription.next().await,
+                Some(StreamEvent::Transfers { transfers }) if transfers.len() == 1
+            ));
+            assert_eq!(
+                subscription.next().await,
+                Some(StreamEvent::Stats {
+                    stats: vec![stats(address)]
+                })
+            );
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_stats_survive_a_cancelled_wait() {
+        let mut transfer_repo = MockTransferRepoAbstract::new();
+        transfer_repo
+            .expect_user_stats_for()
+            .times(1)
+            .returning(|_| Ok(vec![stats("0xaaa")]));
+        let (service, feed) = service(transfer_repo);
+        let mut subscription = service.subscribe(following(&["0xaaa"])).unwrap();
+
+        feed.publish(&[transfer("0xaaa", "0xbbb", 1.0)]);
+        assert!(matches!(
+            subscription.next().await,
+            Some(StreamEvent::Transfers { .. })
+        ));
+        // As when a heartbeat wins the race in the handler.
+        assert!(subscription.next().now_or_never().is_none());
+
+        assert_eq!(
+            subscription.next().await,
+            Some(StreamEvent::Stats {
+                stats: vec![stats("0xaaa")]
+            })
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_filter_limits() {
+        let (service, _feed) = service(MockTransferRepoAbstract::new());
+        let addresses = vec!["0xa".to_string(), "0xb".to_string(), "0xc".to_string()];
+
+        assert!(matches!(
+            service.subscribe(StreamFilter {
+                addresses,
+                min_amount: None
+            }),
+            Err(TransferError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            service.subscribe(StreamFilter {
+                addresses: Vec::new(),
+                min_amount: Some(f64::NAN)
+            }),
+            Err(TransferError::InvalidInput(_))
+        ));
+    }
+}
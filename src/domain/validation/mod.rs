@@ -0,0 +1,167 @@
+pub mod errors;
+pub mod rules;
+
+use crate::domain::entities::transfer::Transfer;
+
+use errors::{ValidationError, Violation};
+use rules::{
+    AddressFormat, AddressRule, PositiveAmountRule, PriceBoundsRule, TimestampRule, TransferRule,
+};
+
+pub struct TransferValidator {
+    rules: Vec<Box<dyn TransferRule>>,
+}
+
+impl Default for TransferValidator {
+    fn default() -> Self {
+        Self::standard(AddressFormat::default())
+    }
+}
+
+impl TransferValidator {
+    /// A validator without rules; every transfer passes.
+    pub fn empty() -> Self {
+        Self { rules: Vec::new() }
+    }
+
+    /// Address format, positive amount, positive price and timestamp rules.
+    pub fn standard(address_format: AddressFormat) -> Self {
+        Self::empty()
+            .with_rule(AddressRule {
+                format: address_format,
+            })
+            .with_rule(PositiveAmountRule)
+            .with_rule(PriceBoundsRule::default())
+            .with_rule(TimestampRule::default())
+    }
+
+    pub fn with_rule(mut self, rule: impl TransferRule + 'static) -> Self {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    pub fn validate(&self, transfer: &Transfer) -> Result<(), ValidationError> {
+        let mut violations = Vec::new();
+        for rule in &self.rules {
+            rule.check(transfer, &mut violations);
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationError { violations })
+        }
+    }
+
+    /// Validates a batch; fields are reported as `transfers[i].field`.
+    pub fn validate_all(&self, transfers: &[Transfer]) -> Result<(), ValidationError> {
+        let mut violations = Vec::new();
+        for (idx, transfer) in transfers.iter().enumerate() {
+            if let Err(error) = self.validate(transfer) {
+                violations.extend(error.violations.into_iter().map(|violation| {
+                    Violation::new(
+                        format!("transfers[{}].{}", idx, violation.field),
+                        violation.message,
+                    )
+                }));
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationError { violations })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn valid_transfer() -> Transfer {
+        Transfer {
+            ts: 1_700_000_000,
+            from: "0xabc".to_string(),
+            to: "0xdef".to_string(),
+            amount: 10.0,
+            usd_price: 1.5,
+        }
+    }
+
+    fn fields(error: ValidationError) -> Vec<String> {
+        error.violations.into_iter().map(|v| v.field).collect()
+    }
+
+    #[test]
+    fn test_valid_transfer_passes() {
+        assert!(
+            TransferValidator::default()
+                .validate(&valid_transfer())
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_reports_every_violation() {
+        let transfer = Transfer {
+            ts: 0,
+            from: String::new(),
+            to: "0xdef".to_string(),
+            amount: -1.0,
+            usd_price: f64::NAN,
+        };
+
+        let error = TransferValidator::default()
+            .validate(&transfer)
+            .unwrap_err();
+
+        assert_eq!(fields(error), vec!["from", "amount", "usd_price", "ts"]);
+    }
+
+    #[test]
+    fn test_evm_address_format() {
+        let validator = TransferValidator::standard(AddressFormat::Evm);
+        let mut transfer = valid_transfer();
+        transfer.from = format!("0x{}", "a1".repeat(20));
+
+        let error = validator.validate(&transfer).unwrap_err();
+
+        assert_eq!(fields(error), vec!["to"]);
+    }
+
+    #[test]
+    fn test_rejects_far_future_timestamp() {
+        let mut transfer = valid_transfer();
+        transfer.ts = u64::MAX;
+
+        let error = TransferValidator::default()
+            .validate(&transfer)
+            .unwrap_err();
+
+        assert_eq!(fields(error), vec!["ts"]);
+    }
+
+    #[test]
+    fn test_custom_price_bounds() {
+        let validator =
+            TransferValidator::empty().with_rule(PriceBoundsRule { min: 1.0, max: 2.0 });
+        let mut transfer = valid_transfer();
+        transfer.usd_price = 2.5;
+
+        assert!(validator.validate(&transfer).is_err());
+    }
+
+    #[test]
+    fn test_validate_all_prefixes_index() {
+        let mut invalid = valid_transfer();
+        invalid.amount = 0.0;
+
+        let error = TransferValidator::default()
+            .validate_all(&[valid_transfer(), invalid])
+            .unwrap_err();
+
+        assert_eq!(fields(error.clone()), vec!["transfers[1].amount"]);
+        assert!(error.to_string().starts_with("transfers[1].amount: "));
+    }
+}
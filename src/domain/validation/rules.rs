@@ -0,0 +1,141 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::domain::entities::transfer::Transfer;
+
+use super::errors::Violation;
+
+pub trait TransferRule: Send + Sync {
+    fn check(&self, transfer: &Transfer, violations: &mut Vec<Violation>);
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressFormat {
+    /// Any non-empty address without whitespace, up to 128 characters.
+    #[default]
+    Generic,
+    /// `0x` followed by 40 hex digits.
+    Evm,
+}
+
+impl AddressFormat {
+    /// Why `address` doesn't match the format, if it doesn't.
+    pub fn check(&self, address: &str) -> Option<&'static str> {
+        if address.is_empty() {
+            return Some("must not be empty");
+        }
+        match self {
+            AddressFormat::Generic => {
+                if address.len() > 128 {
+                    Some("must be at most 128 characters")
+                } else if address.chars().any(char::is_whitespace) {
+                    Some("must not contain whitespace")
+                } else {
+                    None
+                }
+            }
+            AddressFormat::Evm => {
+                let valid = address.len() == 42
+                    && address.starts_with("0x")
+                    && address[2..].chars().all(|c| c.is_ascii_hexdigit());
+                (!valid).then_some("must be 0x followed by 40 hex digits")
+            }
+        }
+    }
+}
+
+pub struct AddressRule {
+    pub format: AddressFormat,
+}
+
+impl TransferRule for AddressRule {
+    fn check(&self, transfer: &Transfer, violations: &mut Vec<Violation>) {
+        for (field, address) in [("from", &transfer.from), ("to", &transfer.to)] {
+            if let Some(message) = self.format.check(address) {
+                violations.push(Violation::new(field, message));
+            }
+        }
+    }
+}
+
+pub struct PositiveAmountRule;
+
+impl TransferRule for PositiveAmountRule {
+    fn check(&self, transfer: &Transfer, violations: &mut Vec<Violation>) {
+        if !transfer.amount.is_finite() || transfer.amount <= 0.0 {
+            violations.push(Violation::new(
+                "amount",
+                format!("must be positive and finite, got {}", transfer.amount),
+            ));
+        }
+    }
+}
+
+pub struct PriceBoundsRule {
+    /// Exclusive lower bound.
+    pub min: f64,
+    /// Inclusive upper bound.
+    pub max: f64,
+}
+
+impl Default for PriceBoundsRule {
+    fn default() -> Self {
+        Self {
+            min: 0.0,
+            max: f64::MAX,
+        }
+    }
+}
+
+impl TransferRule for PriceBoundsRule {
+    fn check(&self, transfer: &Transfer, violations: &mut Vec<Violation>) {
+        let price = transfer.usd_price;
+        if !price.is_finite() || price <= self.min || price > self.max {
+            violations.push(Violation::new(
+                "usd_price",
+                format!(
+                    "must be finite, above {} and at most {}, got {}",
+                    self.min, self.max, price
+                ),
+            ));
+        }
+    }
+}
+
+pub struct TimestampRule {
+    /// How far `ts` may be ahead of the local clock.
+    pub max_future_skew_secs: u64,
+}
+
+impl Default for TimestampRule {
+    fn default() -> Self {
+        Self {
+            max_future_skew_secs: 60,
+        }
+    }
+}
+
+impl TransferRule for TimestampRule {
+    fn check(&self, transfer: &Transfer, violations: &mut Vec<Violation>) {
+        if transfer.ts == 0 {
+            violations.push(Violation::new("ts", "must not be 0"));
+            return;
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|now| now.as_secs())
+            .unwrap_or_default();
+        if transfer.ts > now.saturating_add(self.max_future_skew_secs) {
+            violations.push(Violation::new(
+                "ts",
+                format!(
+                    "must not be more than {}s in the future",
+                    self.max_future_skew_secs
+                ),
+            ));
+        }
+    }
+}
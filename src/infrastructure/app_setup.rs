@@ -1,58 +1,434 @@
-use std::sync::Arc;
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
 
 use crate::{
-    config::Config,
-    domain::services::stats_service::StatsService,
-    jobs::{JobRunner, startup::DataGenerationJob},
-    presentation::{handlers::stats_handler::stats_routes, shared::app_state::AppState},
+    config::{ClickHouseSettings, Config, ServerSettings, runtime::RuntimeConfig},
+    domain::{
+        analysis::clustering::ClusteringConfig,
+        repositories::errors::TransferRepoError,
+        services::{
+            analysis_service::AnalysisService,
+            cluster_service::ClusterService,
+            errors::TransferError,
+            graph_service::GraphService,
+            ingest_service::IngestService,
+            integrity_service::IntegrityService,
+            label_service::LabelService,
+            market_service::MarketService,
+            price_service::PriceService,
+            stats_service::StatsService,
+            stream_service::{StreamService, TransferFeed},
+        },
+        validation::TransferValidator,
+    },
+    jobs::{
+        Job, JobRunner, consumer::TransferConsumer, evm::EvmIngestJob,
+        integrity::IntegrityCheckJob, startup::DataGenerationJob,
+    },
+    presentation::{
+        handlers::{
+            admin_handler::admin_routes, analysis_handler::analysis_routes,
+            cluster_handler::cluster_routes, graph_handler::graph_routes,
+            health_handler::health_routes, integrity_handler::integrity_routes,
+            label_handler::label_routes, market_handler::market_routes,
+            price_handler::price_routes, stats_handler::stats_routes,
+            stream_handler::stream_routes, transfer_handler::transfer_routes,
+        },
+        shared::app_state::AppState,
+    },
 };
 use actix_web::{App, HttpResponse, HttpServer, middleware::Logger, web};
-use anyhow::Result;
-use env_logger::Env;
+use anyhow::{Context, Result};
 
 use super::{
-    clickhouse::db_connection::db_connect, repositories::transfer_repo::ClickHouseTransferRepo,
+    clickhouse::{
+        batching::InsertConfig,
+        db_connection::{create_client, db_connect},
+        schema::Schema,
+        settings::QuerySettings,
+    },
+    evm::rpc::JsonRpcClient,
+    kafka::client::{KafkaDeadLetters, KafkaSource},
+    ownership::load_ownership_labels,
+    reload::apply_revisions,
+    repositories::{
+        chain_repo::ClickHouseChainRepo,
+        generation_repo::ClickHouseGenerationRepo,
+        label_repo::ClickHouseLabelRepo,
+        price_repo::ClickHousePriceRepo,
+        resilient_transfer_repo::{ResilientTransferRepo, TransferRepo},
+        transfer_repo::ClickHouseTransferRepo,
+    },
+    resilience::{CircuitBreaker, Operation, Resilience, RetryPolicy},
+    shutdown::{Shutdown, termination},
+    write_buffer::{WriteBuffer, run_flusher},
 };
 
 pub struct AppDependencies {
     pub app_state: AppState,
+    pub transfer_repo: Arc<TransferRepo>,
+    pub label_repo: Arc<ClickHouseLabelRepo>,
+    pub price_repo: Arc<ClickHousePriceRepo>,
+    pub generation_repo: Arc<ClickHouseGenerationRepo>,
+    pub chain_repo: Arc<ClickHouseChainRepo>,
+    stats_settings: Arc<RwLock<QuerySettings>>,
+    startup_retry: RetryPolicy,
 }
 
 impl AppDependencies {
+    /// Connects, migrates and runs the startup jobs, as `serve` does.
     pub async fn init(config: &Config) -> Result<Self> {
-        let clickhouse_client = db_connect(config).await?;
+        let write_buffer = match &config.buffer.dir {
+            Some(dir) => Some(Arc::new(
+                WriteBuffer::open(
+                    dir,
+                    &config.buffer,
+                    TransferValidator::standard(config.validation.address_format),
+                )
+                .with_context(|| format!("Failed to open the write buffer in {dir}"))?,
+            )),
+            None => None,
+        };
+        let deps = Self::build(config, write_buffer).await?;
+        deps.migrate().await?;
+        deps.start(config).await?;
+        Ok(deps)
+    }
+
+    /// Builds the repositories and services without creating tables or
+    /// running any job. The write buffer is left to `serve`, since opening
+    /// it recovers the directory a running server may be appending to.
+    pub async fn connect(config: &Config) -> Result<Self> {
+        Self::build(config, None).await
+    }
+
+    async fn build(config: &Config, write_buffer: Option<Arc<WriteBuffer>>) -> Result<Self> {
+        let clickhouse = &config.clickhouse;
+        let runtime = Arc::new(RuntimeConfig::new(config));
+        let shutdown = Arc::new(Shutdown::new(Duration::from_secs(
+            config.server.shutdown_delay_secs + config.server.shutdown_timeout_secs,
+        )));
+        let stats_settings = Arc::new(RwLock::new(clickhouse.stats.clone()));
+        let schema = Schema::new(
+            clickhouse.database.clone(),
+            Some(clickhouse.table_prefix.clone()),
+            clickhouse.cluster.clone(),
+        )?;
+        let client = create_client(clickhouse)?;
+        let startup_retry = startup_retry_policy(clickhouse);
+        let clickhouse_client = startup_retry
+            .run(Operation::Idempotent, || async {
+                db_connect(&client, &schema)
+                    .await
+                    .map_err(TransferRepoError::from)
+            })
+            .await?;
 
-        let transfer_repo = Arc::new(ClickHouseTransferRepo::new(clickhouse_client));
-        transfer_repo.create_table().await?;
+        let feed = TransferFeed::new(config.stream.capacity);
+        let clickhouse_transfer_repo =
+            ClickHouseTransferRepo::with_schema(clickhouse_client.clone(), schema.clone())
+                .with_validator(TransferValidator::standard(
+                    config.validation.address_format,
+                ))
+                .with_insert_config(insert_config(clickhouse))
+                .with_stats_settings(stats_settings.clone())
+                .with_feed(feed.clone());
+        let transfer_repo = Arc::new(ResilientTransferRepo::new(
+            clickhouse_transfer_repo,
+            resilience(clickhouse),
+        ));
+        let label_repo = Arc::new(ClickHouseLabelRepo::with_schema(
+            clickhouse_client.clone(),
+            schema.clone(),
+        ));
+        let price_repo = Arc::new(ClickHousePriceRepo::with_schema(
+            clickhouse_client.clone(),
+            schema.clone(),
+        ));
+        let generation_repo = Arc::new(ClickHouseGenerationRepo::with_schema(
+            clickhouse_client.clone(),
+            schema.clone(),
+        ));
+        let chain_repo = Arc::new(ClickHouseChainRepo::with_schema(clickhouse_client, schema));
         let stats_service = Arc::new(StatsService::new(transfer_repo.clone()));
+        let graph_service = Arc::new(
+            GraphService::new(transfer_repo.clone())
+                .with_max_edges(config.analysis.graph_max_edges),
+        );
+        let analysis_service = Arc::new(AnalysisService::new(transfer_repo.clone()));
+        let cluster_service = Arc::new(ClusterService::new(
+            transfer_repo.clone(),
+            clustering_config(config)?,
+        ));
+        let label_service = Arc::new(LabelService::new(label_repo.clone()));
+        let price_service = Arc::new(PriceService::new(price_repo.clone()));
+        let market_service = Arc::new(MarketService::new(transfer_repo.clone()));
+        let integrity_service = Arc::new(IntegrityService::new(transfer_repo.clone()));
+        let stream_service = Arc::new(StreamService::new(
+            transfer_repo.clone(),
+            feed,
+            config.stream.max_addresses,
+        ));
+        let ingest_service = Arc::new(IngestService::new(
+            transfer_repo.clone(),
+            write_buffer.clone(),
+        ));
+
+        let app_state = AppState {
+            stats_service,
+            graph_service,
+            analysis_service,
+            cluster_service,
+            label_service,
+            price_service,
+            market_service,
+            integrity_service,
+            ingest_service,
+            stream_service,
+            stream_heartbeat: Duration::from_secs(config.stream.heartbeat_secs),
+            write_buffer,
+            runtime,
+            shutdown,
+        };
+
+        Ok(AppDependencies {
+            app_state,
+            transfer_repo,
+            label_repo,
+            price_repo,
+            generation_repo,
+            chain_repo,
+            stats_settings,
+            startup_retry,
+        })
+    }
+
+    /// Creates the tables that don't exist yet.
+    pub async fn migrate(&self) -> Result<()> {
+        self.startup_retry
+            .run(Operation::Idempotent, || {
+                self.transfer_repo.inner().create_table()
+            })
+            .await?;
+        self.startup_retry
+            .run(Operation::Idempotent, || self.label_repo.create_table())
+            .await?;
+        self.startup_retry
+            .run(Operation::Idempotent, || self.price_repo.create_table())
+            .await?;
+        self.startup_retry
+            .run(Operation::Idempotent, || {
+                self.generation_repo.create_table()
+            })
+            .await?;
+        self.startup_retry
+            .run(Operation::Idempotent, || self.chain_repo.create_table())
+            .await?;
+        Ok(())
+    }
 
-        let data_gen_job = DataGenerationJob::new(config.data_generation_count, transfer_repo);
+    /// Merges the `ReplacingMergeTree` tables so replaced labels and prices
+    /// are collapsed on disk rather than on each `FINAL` read. The other
+    /// tables have nothing to collapse, and stats are computed per request,
+    /// so nothing else is stored to rebuild.
+    pub async fn optimize(&self) -> Result<()> {
+        self.label_repo.optimize_table().await?;
+        self.price_repo.optimize_table().await?;
+        Ok(())
+    }
+
+    /// Runs the startup jobs and spawns the tasks that follow config
+    /// reloads.
+    pub async fn start(&self, config: &Config) -> Result<()> {
+        let data_gen_job = DataGenerationJob::new(
+            "startup",
+            config.jobs.data_generation_count,
+            config.jobs.data_generation_mode,
+            config.generator.clone(),
+            config.validation.address_format,
+            self.transfer_repo.clone(),
+            self.generation_repo.clone(),
+        );
         let job_runner = JobRunner::new().add_job(data_gen_job);
         job_runner.run_all().await?;
 
-        let app_state = AppState::new(stats_service);
+        let runtime = &self.app_state.runtime;
+        actix_web::rt::spawn(apply_revisions(
+            runtime.subscribe(),
+            self.stats_settings.clone(),
+        ));
+
+        actix_web::rt::spawn(self.app_state.stream_service.stats_refresher().run());
+
+        if let Some(buffer) = &self.app_state.write_buffer {
+            let shutdown = self.app_state.shutdown.clone();
+            actix_web::rt::spawn(run_flusher(
+                buffer.clone(),
+                self.transfer_repo.clone(),
+                shutdown.clone(),
+            ));
+            let (buffer, transfer_repo) = (buffer.clone(), self.transfer_repo.clone());
+            shutdown.on_drained("write buffer", async move {
+                buffer.flush_all(&*transfer_repo).await
+            });
+        }
+
+        if config.kafka.brokers.is_some() {
+            let consumer = TransferConsumer::new(
+                KafkaSource::connect(&config.kafka)?,
+                KafkaDeadLetters::connect(&config.kafka)?,
+                self.transfer_repo.clone(),
+                TransferValidator::standard(config.validation.address_format),
+                &config.kafka,
+            );
+            actix_web::rt::spawn(consumer.run(self.app_state.shutdown.clone()));
+        }
 
-        Ok(AppDependencies { app_state })
+        if let Some(url) = &config.evm.rpc_url {
+            let rpc =
+                JsonRpcClient::new(url, Duration::from_millis(config.evm.request_timeout_ms))?;
+            let job = EvmIngestJob::new(
+                rpc,
+                self.chain_repo.clone(),
+                self.transfer_repo.clone(),
+                config.evm.clone(),
+            );
+            actix_web::rt::spawn(job.run(self.app_state.shutdown.clone()));
+        }
+
+        if config.jobs.integrity_check {
+            // A full scan of a large table; it only reports, so serving
+            // doesn't wait for it and a failure doesn't stop startup.
+            let integrity_job = IntegrityCheckJob::new(self.app_state.integrity_service.clone());
+            let cancelled = self.app_state.shutdown.token();
+            actix_web::rt::spawn(async move {
+                tokio::select! {
+                    result = integrity_job.run() => {
+                        if let Err(err) = result {
+                            log::warn!("Integrity check failed: {err:#}");
+                        }
+                    }
+                    _ = cancelled.cancelled() => {}
+                }
+            });
+        }
+
+        Ok(())
     }
 }
 
-pub async fn server(app_state: AppState, port: &str) -> Result<()> {
-    env_logger::init_from_env(Env::default().default_filter_or("info"));
+fn clustering_config(config: &Config) -> Result<ClusteringConfig> {
+    let mut clustering = ClusteringConfig {
+        min_mutual_transfers: config.analysis.min_mutual_transfers,
+        ..ClusteringConfig::default()
+    };
+    if let Some(path) = &config.analysis.ownership_file {
+        clustering.ownership = load_ownership_labels(path)?;
+    }
+    Ok(clustering)
+}
 
-    let address = format!("0.0.0.0:{}", port);
+/// Reports extractor failures as `invalid_input` problems instead of
+/// actix-web's plain-text bodies.
+fn invalid_input(err: impl std::fmt::Display) -> actix_web::Error {
+    TransferError::InvalidInput(err.to_string()).into()
+}
+
+fn insert_config(config: &ClickHouseSettings) -> InsertConfig {
+    InsertConfig {
+        max_rows: config.insert.max_rows,
+        max_bytes: config.insert.max_bytes,
+        parallelism: config.insert.parallelism,
+        async_insert: config.insert.async_insert,
+        timeout: request_timeout(config),
+    }
+}
+
+fn request_timeout(config: &ClickHouseSettings) -> Option<Duration> {
+    config.request_timeout_ms.map(Duration::from_millis)
+}
+
+fn retry_policy(config: &ClickHouseSettings) -> RetryPolicy {
+    RetryPolicy {
+        max_attempts: config.retry.max_attempts,
+        base_delay: Duration::from_millis(config.retry.base_delay_ms),
+        max_delay: Duration::from_millis(config.retry.max_delay_ms),
+        retry_inserts: config.retry.inserts,
+        attempt_timeout: request_timeout(config),
+    }
+}
+
+/// ClickHouse often comes up after us, so startup keeps trying for longer
+/// than a request would.
+fn startup_retry_policy(config: &ClickHouseSettings) -> RetryPolicy {
+    RetryPolicy {
+        max_attempts: config.retry.startup_max_attempts,
+        ..retry_policy(config)
+    }
+}
+
+fn resilience(config: &ClickHouseSettings) -> Resilience {
+    let breaker = CircuitBreaker::new(
+        config.breaker.failure_threshold,
+        Duration::from_secs(config.breaker.open_secs),
+    );
+    Resilience::new(retry_policy(config), breaker)
+}
+
+/// Serves until `SIGTERM` or `SIGINT`, then fails readiness, waits
+/// `shutdown_delay_secs`, stops accepting connections and drains in-flight
+/// requests and background work within `shutdown_timeout_secs`.
+pub async fn server(app_state: AppState, settings: &ServerSettings) -> Result<()> {
+    let address = format!("{}:{}", settings.host, settings.port);
     println!("App address: {}", &address);
 
+    let shutdown = app_state.shutdown.clone();
     let app_state = web::Data::new(app_state);
 
-    HttpServer::new(move || {
+    let server = HttpServer::new(move || {
         App::new()
             .app_data(app_state.clone())
+            .app_data(web::QueryConfig::default().error_handler(|err, _| invalid_input(err)))
+            .app_data(web::PathConfig::default().error_handler(|err, _| invalid_input(err)))
+            .app_data(web::JsonConfig::default().error_handler(|err, _| invalid_input(err)))
             .configure(stats_routes)
+            .configure(graph_routes)
+            .configure(analysis_routes)
+            .configure(cluster_routes)
+            .configure(label_routes)
+            .configure(price_routes)
+            .configure(market_routes)
+            .configure(integrity_routes)
+            .configure(admin_routes)
+            .configure(health_routes)
+            .configure(transfer_routes)
+            .configure(stream_routes)
             .default_service(web::to(HttpResponse::MethodNotAllowed))
             .wrap(Logger::default())
     })
+    .disable_signals()
+    .shutdown_timeout(settings.shutdown_timeout_secs)
     .bind(address)?
-    .run()
-    .await?;
+    .run();
+
+    let handle = server.handle();
+    let delay = Duration::from_secs(settings.shutdown_delay_secs);
+    let stopping = shutdown.clone();
+    actix_web::rt::spawn(async move {
+        termination().await;
+        log::info!("Shutting down: readiness fails for {delay:?} before the listener closes");
+        stopping.begin();
+        actix_web::rt::time::sleep(delay).await;
+        handle.stop(true).await;
+    });
+
+    server.await?;
+    if shutdown.drain().await {
+        log::info!("Shutdown complete");
+    } else {
+        log::warn!("Shutdown deadline passed before all work finished");
+    }
     Ok(())
 }
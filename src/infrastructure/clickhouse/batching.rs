@@ -0,0 +1,171 @@
+use std::time::Duration;
+
+use crate::domain::entities::transfer::Transfer;
+
+#[derive(Debug, Clone)]
+pub struct InsertConfig {
+    /// Upper bound on rows per `INSERT`.
+    pub max_rows: usize,
+    /// Upper bound on the estimated RowBinary bytes per `INSERT`. A single
+    /// row larger than this still gets a chunk of its own.
+    pub max_bytes: usize,
+    /// Chunks in flight at the same time.
+    pub parallelism: usize,
+    /// Use server-side async inserts, waiting for the flush so the report
+    /// only lists committed rows.
+    pub async_insert: bool,
+    /// Limit on sending a chunk and on waiting for the server to confirm it.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for InsertConfig {
+    fn default() -> Self {
+        Self {
+            max_rows: 100_000,
+            max_bytes: 64 * 1024 * 1024,
+            parallelism: 1,
+            async_insert: false,
+            timeout: None,
+        }
+    }
+}
+
+/// Size of `transfer` in RowBinary: three 8-byte numbers plus two
+/// length-prefixed strings.
+pub fn row_size(transfer: &Transfer) -> usize {
+    3 * 8
+        + varint_len(transfer.from.len())
+        + transfer.from.len()
+        + varint_len(transfer.to.len())
+        + transfer.to.len()
+}
+
+fn varint_len(mut value: usize) -> usize {
+    let mut len = 1;
+    while value >= 0x80 {
+        value >>= 7;
+        len += 1;
+    }
+    len
+}
+
+/// A contiguous slice of the input together with its estimated size.
+#[derive(Debug)]
+pub struct Chunk<'a> {
+    pub index: usize,
+    pub rows: &'a [Transfer],
+    pub bytes: usize,
+}
+
+/// Splits `transfers` into consecutive chunks that respect both limits of
+/// `config`.
+pub fn chunk_transfers<'a>(transfers: &'a [Transfer], config: &InsertConfig) -> Vec<Chunk<'a>> {
+    let max_rows = config.max_rows.max(1);
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut bytes = 0;
+
+    for (i, transfer) in transfers.iter().enumerate() {
+        let size = row_size(transfer);
+        let rows = i - start;
+        if rows > 0 && (rows >= max_rows || bytes + size > config.max_bytes) {
+            chunks.push(Chunk {
+                index: chunks.len(),
+                rows: &transfers[start..i],
+                bytes,
+            });
+            start = i;
+            bytes = 0;
+        }
+        bytes += size;
+    }
+
+    if start < transfers.len() {
+        chunks.push(Chunk {
+            index: chunks.len(),
+            rows: &transfers[start..],
+            bytes,
+        });
+    }
+
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transfer(from: &str) -> Transfer {
+        Transfer {
+            ts: 1,
+            from: from.to_string(),
+            to: "0xto".to_string(),
+            amount: 1.0,
+            usd_price: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_row_size() {
+        // 24 bytes of numbers, 1 + 4 for "0xab", 1 + 4 for "0xto"
+        assert_eq!(row_size(&transfer("0xab")), 34);
+        assert_eq!(row_size(&transfer(&"a".repeat(200))), 24 + 2 + 200 + 5);
+    }
+
+    #[test]
+    fn test_chunks_by_row_count() {
+        let transfers: Vec<_> = (0..5).map(|_| transfer("0xab")).collect();
+        let config = InsertConfig {
+            max_rows: 2,
+            ..InsertConfig::default()
+        };
+
+        let chunks = chunk_transfers(&transfers, &config);
+
+        let sizes: Vec<_> = chunks.iter().map(|chunk| chunk.rows.len()).collect();
+        assert_eq!(sizes, vec![2, 2, 1]);
+        assert_eq!(chunks[2].index, 2);
+        assert_eq!(chunks[0].bytes, 68);
+    }
+
+    #[test]
+    fn test_chunks_by_byte_size() {
+        let transfers: Vec<_> = (0..5).map(|_| transfer("0xab")).collect();
+        let config = InsertConfig {
+            max_bytes: 100,
+            ..InsertConfig::default()
+        };
+
+        let sizes: Vec<_> = chunk_transfers(&transfers, &config)
+            .iter()
+            .map(|chunk| chunk.rows.len())
+            .collect();
+
+        assert_eq!(sizes, vec![2, 2, 1]);
+    }
+
+    #[test]
+    fn test_oversized_row_gets_its_own_chunk() {
+        let transfers = vec![
+            transfer("0xab"),
+            transfer(&"a".repeat(500)),
+            transfer("0xab"),
+        ];
+        let config = InsertConfig {
+            max_bytes: 100,
+            ..InsertConfig::default()
+        };
+
+        let sizes: Vec<_> = chunk_transfers(&transfers, &config)
+            .iter()
+            .map(|chunk| chunk.rows.len())
+            .collect();
+
+        assert_eq!(sizes, vec![1, 1, 1]);
+    }
+
+    #[test]
+    fn test_empty_input() {
+        assert!(chunk_transfers(&[], &InsertConfig::default()).is_empty());
+    }
+}
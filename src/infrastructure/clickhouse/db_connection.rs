@@ -1,20 +1,149 @@
-use clickhouse::{Client, error::Error};
+use std::{fs, time::Duration};
 
-use crate::config::Config;
+use anyhow::{Context, Result, bail};
+use clickhouse::{Client, Compression, error::Error};
+use hyper_tls::HttpsConnector;
+use hyper_util::{
+    client::legacy::{Client as HyperClient, connect::HttpConnector},
+    rt::TokioExecutor,
+};
+use native_tls::{Certificate, Identity, TlsConnector};
+use serde::{Deserialize, Serialize};
 
-pub async fn db_connect(config: &Config) -> Result<Client, Error> {
-    let client = Client::default()
-        .with_url(config.clickhouse_url.to_owned())
-        .with_user(config.clickhouse_user.to_owned())
-        .with_password(config.clickhouse_password.to_owned());
-    test_connection(&client).await?;
+use crate::config::ClickHouseSettings;
 
-    Ok(client)
+use super::schema::Schema;
+
+const TCP_KEEPALIVE: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressionMode {
+    None,
+    #[default]
+    Lz4,
+}
+
+impl From<CompressionMode> for Compression {
+    fn from(mode: CompressionMode) -> Self {
+        match mode {
+            CompressionMode::None => Compression::None,
+            CompressionMode::Lz4 => Compression::Lz4,
+        }
+    }
+}
+
+/// Builds a client for `config` without contacting the server. `https://`
+/// URLs are verified against the system roots plus `ca_cert`, and present
+/// the client certificate when one is configured.
+pub fn create_client(config: &ClickHouseSettings) -> Result<Client> {
+    let mut http = HttpConnector::new();
+    http.enforce_http(false);
+    http.set_keepalive(Some(TCP_KEEPALIVE));
+    http.set_connect_timeout(config.connect_timeout_ms.map(Duration::from_millis));
+
+    let https = HttpsConnector::from((http, tls_connector(config)?.into()));
+
+    let mut pool = HyperClient::builder(TokioExecutor::new());
+    pool.pool_idle_timeout(Duration::from_millis(config.pool_idle_timeout_ms));
+    if let Some(max_idle) = config.pool_max_idle {
+        pool.pool_max_idle_per_host(max_idle);
+    }
+
+    Ok(Client::with_http_client(pool.build(https))
+        .with_url(config.url.to_owned())
+        .with_user(config.user.to_owned())
+        .with_password(config.password.to_owned())
+        .with_compression(config.compression.into()))
+}
+
+fn tls_connector(config: &ClickHouseSettings) -> Result<TlsConnector> {
+    let mut tls = TlsConnector::builder();
+
+    if let Some(path) = &config.ca_cert {
+        let bundle =
+            fs::read_to_string(path).with_context(|| format!("Failed to read CA bundle {path}"))?;
+        let certificates = pem_certificates(&bundle);
+        if certificates.is_empty() {
+            bail!("No certificates found in CA bundle {path}");
+        }
+        for pem in certificates {
+            let certificate = Certificate::from_pem(pem.as_bytes())
+                .with_context(|| format!("Invalid certificate in CA bundle {path}"))?;
+            tls.add_root_certificate(certificate);
+        }
+    }
+
+    match (&config.client_cert, &config.client_key) {
+        (Some(cert_path), Some(key_path)) => {
+            let cert = fs::read(cert_path)
+                .with_context(|| format!("Failed to read client certificate {cert_path}"))?;
+            let key = fs::read(key_path)
+                .with_context(|| format!("Failed to read client key {key_path}"))?;
+            let identity = Identity::from_pkcs8(&cert, &key)
+                .context("Client key must be an unencrypted PKCS#8 PEM key")?;
+            tls.identity(identity);
+        }
+        (None, None) => {}
+        _ => bail!("Client certificate and key must be set together"),
+    }
+
+    Ok(tls.build()?)
+}
+
+/// Splits a PEM bundle into its certificates; `Certificate::from_pem` only
+/// reads the first one.
+fn pem_certificates(bundle: &str) -> Vec<&str> {
+    const END: &str = "-----END CERTIFICATE-----";
+
+    let mut certificates = Vec::new();
+    let mut rest = bundle;
+    while let Some(start) = rest.find("-----BEGIN CERTIFICATE-----") {
+        let Some(end) = rest[start..].find(END) else {
+            break;
+        };
+        let end = start + end + END.len();
+        certificates.push(&rest[start..end]);
+        rest = &rest[end..];
+    }
+    certificates
+}
+
+/// Connects and, when `schema` names a database, creates it and makes it
+/// the default for every query of the returned client.
+pub async fn db_connect(client: &Client, schema: &Schema) -> Result<Client, Error> {
+    test_connection(client).await?;
+
+    match (schema.database(), schema.create_database()) {
+        (Some(database), Some(create_database)) => {
+            client.query(&create_database).execute().await?;
+            Ok(client.clone().with_database(database))
+        }
+        _ => Ok(client.clone()),
+    }
 }
 
 pub async fn test_connection(client: &Client) -> Result<(), Error> {
     let result: String = client.query("SELECT version()").fetch_one().await?;
 
-    println!("Connected to ClickHouse version: {}", result);
+    eprintln!("Connected to ClickHouse version: {}", result);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pem_certificates() {
+        let bundle = "# root\n-----BEGIN CERTIFICATE-----\nAAA\n-----END CERTIFICATE-----\n\
+                      -----BEGIN CERTIFICATE-----\nBBB\n-----END CERTIFICATE-----\n";
+
+        let certificates = pem_certificates(bundle);
+
+        assert_eq!(certificates.len(), 2);
+        assert!(certificates[0].contains("AAA") && !certificates[0].contains("BBB"));
+        assert!(certificates[1].ends_with("-----END CERTIFICATE-----"));
+        assert!(pem_certificates("not a pem").is_empty());
+    }
+}
@@ -5,7 +5,7 @@ impl From<Error> for TransferRepoError {
     fn from(value: Error) -> Self {
         match value {
             Error::InvalidParams(error) => {
-                TransferRepoError::QueryError(format!("Invalid parameters: {}", error))
+                TransferRepoError::BadRequest(format!("Invalid parameters: {}", error))
             }
             Error::Network(error) => {
                 TransferRepoError::DatabaseConnectionError(format!("Network error: {}", error))
@@ -36,17 +36,71 @@ impl From<Error> for TransferRepoError {
                 format!("Variant discriminator out of bound: {}", value),
             ),
             Error::Custom(msg) => TransferRepoError::QueryError(format!("Custom error: {}", msg)),
-            Error::BadResponse(msg) => {
-                TransferRepoError::DatabaseConnectionError(format!("Bad response: {}", msg))
-            }
-            Error::TimedOut => {
-                TransferRepoError::DatabaseConnectionError("Connection timed out".to_string())
-            }
+            Error::BadResponse(msg) => from_bad_response(msg),
+            Error::TimedOut => TransferRepoError::TimedOut("Connection timed out".to_string()),
             Error::Unsupported(msg) => {
-                TransferRepoError::QueryError(format!("Unsupported operation: {}", msg))
+                TransferRepoError::BadRequest(format!("Unsupported operation: {}", msg))
             }
             Error::Other(error) => TransferRepoError::QueryError(format!("Other error: {}", error)),
             _ => TransferRepoError::QueryError("Unknown ClickHouse error".to_string()),
         }
     }
 }
+
+/// Server-side exceptions arrive as `Code: <n>. DB::Exception: ...`; the code
+/// tells an overloaded or slow server apart from a broken query.
+fn from_bad_response(msg: String) -> TransferRepoError {
+    match exception_code(&msg) {
+        // TIMEOUT_EXCEEDED, SOCKET_TIMEOUT
+        Some(159 | 209) => TransferRepoError::TimedOut(msg),
+        // TOO_MANY_SIMULTANEOUS_QUERIES, NO_FREE_CONNECTION, NETWORK_ERROR,
+        // ALL_CONNECTION_TRIES_FAILED, TABLE_IS_READ_ONLY
+        Some(202 | 203 | 210 | 279 | 242) => TransferRepoError::DatabaseConnectionError(msg),
+        // SYNTAX_ERROR, UNKNOWN_IDENTIFIER, TYPE_MISMATCH, ILLEGAL_TYPE_OF_ARGUMENT,
+        // UNKNOWN_TABLE, UNKNOWN_DATABASE, ...
+        Some(_) => TransferRepoError::QueryError(msg),
+        None => TransferRepoError::DatabaseConnectionError(format!("Bad response: {}", msg)),
+    }
+}
+
+fn exception_code(msg: &str) -> Option<u32> {
+    let rest = &msg[msg.find("Code: ")? + "Code: ".len()..];
+    let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exception_code() {
+        assert_eq!(
+            exception_code("Code: 159. DB::Exception: Timeout exceeded"),
+            Some(159)
+        );
+        assert_eq!(exception_code("502 Bad Gateway"), None);
+    }
+
+    #[test]
+    fn test_bad_response_classification() {
+        let timeout: TransferRepoError =
+            Error::BadResponse("Code: 159. DB::Exception: Timeout exceeded".to_string()).into();
+        assert!(matches!(timeout, TransferRepoError::TimedOut(_)));
+
+        let syntax: TransferRepoError =
+            Error::BadResponse("Code: 62. DB::Exception: Syntax error".to_string()).into();
+        assert!(matches!(syntax, TransferRepoError::QueryError(_)));
+        assert!(!syntax.is_retryable());
+
+        let proxy: TransferRepoError = Error::BadResponse("503 Service Unavailable".into()).into();
+        assert!(proxy.is_retryable());
+    }
+
+    #[test]
+    fn test_timed_out_is_retryable() {
+        let error: TransferRepoError = Error::TimedOut.into();
+        assert_eq!(error.code(), "database_timeout");
+        assert!(error.is_retryable());
+    }
+}
@@ -1,2 +1,5 @@
+pub mod batching;
 pub mod db_connection;
 pub mod errors;
+pub mod schema;
+pub mod settings;
@@ -0,0 +1,235 @@
+use anyhow::{Result, bail};
+
+/// Engine of a table as it is declared on a single server; in cluster mode
+/// it is turned into its `Replicated*` counterpart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableEngine {
+    MergeTree,
+    /// Keeps the row with the highest `version`, or the last inserted one.
+    ReplacingMergeTree {
+        version: Option<&'static str>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct TableSpec {
+    pub name: &'static str,
+    pub columns: &'static str,
+    pub engine: TableEngine,
+    pub order_by: &'static str,
+    /// Distributes rows over shards; rows that must be merged or
+    /// deduplicated together need the same key.
+    pub sharding_key: &'static str,
+    pub settings: Option<&'static str>,
+}
+
+/// Where the service's tables live: database, name prefix and, when running
+/// against a replicated deployment, the cluster they are spread over.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Schema {
+    database: Option<String>,
+    table_prefix: String,
+    cluster: Option<String>,
+}
+
+impl Schema {
+    pub fn new(
+        database: Option<String>,
+        table_prefix: Option<String>,
+        cluster: Option<String>,
+    ) -> Result<Self> {
+        let table_prefix = table_prefix.unwrap_or_default();
+        if !table_prefix
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            bail!("Table prefix may only contain ASCII letters, digits and `_`: {table_prefix}");
+        }
+        for name in database.iter().chain(cluster.iter()) {
+            if name.is_empty() || name.contains(['`', '\'']) {
+                bail!("Invalid ClickHouse identifier: {name:?}");
+            }
+        }
+
+        Ok(Self {
+            database,
+            table_prefix,
+            cluster,
+        })
+    }
+
+    pub fn database(&self) -> Option<&str> {
+        self.database.as_deref()
+    }
+
+    pub fn is_clustered(&self) -> bool {
+        self.cluster.is_some()
+    }
+
+    /// Name to read from and insert into; the `Distributed` table in
+    /// cluster mode.
+    pub fn table(&self, name: &str) -> String {
+        format!("{}{}", self.table_prefix, name)
+    }
+
+    /// Table that holds the data on each server.
+    fn local_table(&self, name: &str) -> String {
+        match self.cluster {
+            Some(_) => format!("{}{}_local", self.table_prefix, name),
+            None => self.table(name),
+        }
+    }
+
+    /// ` ON CLUSTER ...` for DDL and mutations, empty on a single server.
+    pub fn on_cluster(&self) -> String {
+        match &self.cluster {
+            Some(cluster) => format!(" ON CLUSTER `{cluster}`"),
+            None => String::new(),
+        }
+    }
+
+    /// Target of `ALTER`/`DELETE` statements, which `Distributed` tables
+    /// don't accept.
+    pub fn mutation_target(&self, name: &str) -> String {
+        format!("{}{}", self.local_table(name), self.on_cluster())
+    }
+
+    /// Merges all parts of `name`, collapsing rows a `ReplacingMergeTree`
+    /// would otherwise only drop at read time.
+    pub fn optimize_table(&self, name: &str) -> String {
+        format!("OPTIMIZE TABLE {} FINAL", self.mutation_target(name))
+    }
+
+    pub fn create_database(&self) -> Option<String> {
+        self.database.as_ref().map(|database| {
+            format!(
+                "CREATE DATABASE IF NOT EXISTS `{database}`{}",
+                self.on_cluster()
+            )
+        })
+    }
+
+    /// Statements creating `spec`, in execution order.
+    pub fn create_table(&self, spec: &TableSpec) -> Vec<String> {
+        let local = self.local_table(spec.name);
+        let engine = match (spec.engine, self.is_clustered()) {
+            (TableEngine::MergeTree, false) => "MergeTree()".to_string(),
+            (TableEngine::ReplacingMergeTree { version }, false) => {
+                format!("ReplacingMergeTree({})", version.unwrap_or_default())
+            }
+            (TableEngine::MergeTree, true) => {
+                format!("ReplicatedMergeTree({})", Self::replication_args())
+            }
+            (TableEngine::ReplacingMergeTree { version }, true) => format!(
+                "ReplicatedReplacingMergeTree({}{})",
+                Self::replication_args(),
+                version.map(|v| format!(", {v}")).unwrap_or_default()
+            ),
+        };
+        let settings = spec
+            .settings
+            .map(|settings| format!("\nSETTINGS {settings}"))
+            .unwrap_or_default();
+
+        let mut statements = vec![format!(
+            "CREATE TABLE IF NOT EXISTS {local}{} (\n{}\n) ENGINE = {engine}\nORDER BY {}{settings}",
+            self.on_cluster(),
+            spec.columns,
+            spec.order_by,
+        )];
+
+        if let Some(cluster) = &self.cluster {
+            let database = match &self.database {
+                Some(database) => format!("'{database}'"),
+                None => "currentDatabase()".to_string(),
+            };
+            statements.push(format!(
+                "CREATE TABLE IF NOT EXISTS {}{} AS {local}\nENGINE = Distributed('{cluster}', {database}, '{local}', {})",
+                self.table(spec.name),
+                self.on_cluster(),
+                spec.sharding_key,
+            ));
+        }
+
+        statements
+    }
+
+    fn replication_args() -> &'static str {
+        "'/clickhouse/tables/{shard}/{database}/{table}', '{replica}'"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> TableSpec {
+        TableSpec {
+            name: "prices",
+            columns: "token String, ts UInt64, price Float64",
+            engine: TableEngine::ReplacingMergeTree {
+                version: Some("ts"),
+            },
+            order_by: "(token, ts)",
+            sharding_key: "cityHash64(token)",
+            settings: None,
+        }
+    }
+
+    #[test]
+    fn test_single_server_ddl() {
+        let schema = Schema::new(None, Some("app_".into()), None).unwrap();
+
+        let statements = schema.create_table(&spec());
+
+        assert_eq!(statements.len(), 1);
+        assert!(statements[0].starts_with("CREATE TABLE IF NOT EXISTS app_prices ("));
+        assert!(statements[0].contains("ENGINE = ReplacingMergeTree(ts)"));
+        assert_eq!(schema.table("prices"), "app_prices");
+        assert_eq!(schema.mutation_target("prices"), "app_prices");
+        assert_eq!(
+            schema.optimize_table("prices"),
+            "OPTIMIZE TABLE app_prices FINAL"
+        );
+    }
+
+    #[test]
+    fn test_cluster_ddl() {
+        let schema = Schema::new(Some("analytics".into()), None, Some("main".into())).unwrap();
+
+        let statements = schema.create_table(&spec());
+
+        assert_eq!(statements.len(), 2);
+        assert!(
+            statements[0].starts_with("CREATE TABLE IF NOT EXISTS prices_local ON CLUSTER `main`")
+        );
+        assert!(statements[0].contains(
+            "ReplicatedReplacingMergeTree('/clickhouse/tables/{shard}/{database}/{table}', '{replica}', ts)"
+        ));
+        assert!(statements[1].contains("AS prices_local"));
+        assert!(
+            statements[1]
+                .contains("Distributed('main', 'analytics', 'prices_local', cityHash64(token))")
+        );
+        assert_eq!(schema.table("prices"), "prices");
+        assert_eq!(
+            schema.mutation_target("prices"),
+            "prices_local ON CLUSTER `main`"
+        );
+        assert_eq!(
+            schema.optimize_table("prices"),
+            "OPTIMIZE TABLE prices_local ON CLUSTER `main` FINAL"
+        );
+        assert_eq!(
+            schema.create_database().unwrap(),
+            "CREATE DATABASE IF NOT EXISTS `analytics` ON CLUSTER `main`"
+        );
+    }
+
+    #[test]
+    fn test_rejects_unsafe_names() {
+        assert!(Schema::new(None, Some("a-b".into()), None).is_err());
+        assert!(Schema::new(Some("x`y".into()), None, None).is_err());
+        assert!(Schema::new(None, None, Some(String::new())).is_err());
+    }
+}
@@ -0,0 +1,55 @@
+use clickhouse::query::Query;
+use serde::{Deserialize, Serialize};
+
+/// ClickHouse settings sent with a single query, used as guardrails on the
+/// queries that scan the whole `transfers` table.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QuerySettings {
+    /// Seconds before the server aborts the query.
+    pub max_execution_time: Option<u64>,
+    /// Bytes of memory the query may use on a single server.
+    pub max_memory_usage: Option<u64>,
+}
+
+impl QuerySettings {
+    pub fn options(&self) -> Vec<(&'static str, String)> {
+        let mut options = Vec::new();
+        if let Some(secs) = self.max_execution_time {
+            options.push(("max_execution_time", secs.to_string()));
+        }
+        if let Some(bytes) = self.max_memory_usage {
+            options.push(("max_memory_usage", bytes.to_string()));
+        }
+        options
+    }
+
+    pub fn apply(&self, query: Query) -> Query {
+        self.options()
+            .into_iter()
+            .fold(query, |query, (name, value)| query.with_option(name, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_options() {
+        assert!(QuerySettings::default().options().is_empty());
+
+        let settings = QuerySettings {
+            max_execution_time: Some(30),
+            max_memory_usage: Some(1 << 30),
+        };
+
+        assert_eq!(
+            settings.options(),
+            vec![
+                ("max_execution_time", "30".to_string()),
+                ("max_memory_usage", "1073741824".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,102 @@
+//! ERC-20 `Transfer` events read from an Ethereum JSON-RPC node.
+
+pub mod rpc;
+
+use anyhow::{Context, Result};
+
+use rpc::Log;
+
+/// `keccak256("Transfer(address,address,uint256)")`.
+pub const TRANSFER_TOPIC: &str =
+    "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+
+/// Parses a hex-encoded JSON-RPC quantity such as `0x1b4`.
+pub fn parse_quantity(quantity: &str) -> Result<u64> {
+    let digits = quantity.strip_prefix("0x").unwrap_or(quantity);
+    u64::from_str_radix(digits, 16).with_context(|| format!("Invalid quantity {quantity:?}"))
+}
+
+/// Sender, recipient and amount, scaled down by `decimals`, of an ERC-20
+/// `Transfer` log. `None` for logs that only share the signature, such as
+/// ERC-721 transfers, which index the token id.
+pub fn decode_transfer(log: &Log, decimals: u8) -> Option<(String, String, f64)> {
+    let [topic, from, to] = log.topics.as_slice() else {
+        return None;
+    };
+    if !topic.eq_ignore_ascii_case(TRANSFER_TOPIC) {
+        return None;
+    }
+
+    let value = log.data.strip_prefix("0x")?;
+    if value.len() != 64 {
+        return None;
+    }
+    let amount = value.chars().try_fold(0f64, |amount, digit| {
+        Some(amount * 16.0 + f64::from(digit.to_digit(16)?))
+    })?;
+
+    Some((
+        topic_address(from)?,
+        topic_address(to)?,
+        amount / 10f64.powi(i32::from(decimals)),
+    ))
+}
+
+/// The address in the low 20 bytes of an indexed topic.
+fn topic_address(topic: &str) -> Option<String> {
+    let word = topic.strip_prefix("0x")?;
+    if word.len() != 64 || !word.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some(format!("0x{}", word[24..].to_ascii_lowercase()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(topics: &[&str], data: &str) -> Log {
+        Log {
+            topics: topics.iter().map(|topic| topic.to_string()).collect(),
+            data: data.to_string(),
+            block_number: "0x1".to_string(),
+            block_hash: "0xb1".to_string(),
+            transaction_hash: "0xt1".to_string(),
+            log_index: "0x0".to_string(),
+            removed: false,
+        }
+    }
+
+    const FROM: &str = "0x000000000000000000000000AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA";
+    const TO: &str = "0x000000000000000000000000bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb";
+
+    #[test]
+    fn test_decode_erc20_transfer() {
+        // 1.5 * 10^18
+        let data = "0x00000000000000000000000000000000000000000000000014d1120d7b160000";
+
+        let (from, to, amount) = decode_transfer(&log(&[TRANSFER_TOPIC, FROM, TO], data), 18)
+            .expect("an ERC-20 transfer");
+
+        assert_eq!(from, "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa");
+        assert_eq!(to, "0xbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb");
+        assert_eq!(amount, 1.5);
+    }
+
+    #[test]
+    fn test_ignores_other_layouts() {
+        let id = "0x0000000000000000000000000000000000000000000000000000000000000007";
+
+        // ERC-721: the token id is a fourth topic and there is no data.
+        assert!(decode_transfer(&log(&[TRANSFER_TOPIC, FROM, TO, id], "0x"), 0).is_none());
+        assert!(decode_transfer(&log(&[TRANSFER_TOPIC, FROM, TO], "0x01"), 0).is_none());
+        assert!(decode_transfer(&log(&[id, FROM, TO], id), 0).is_none());
+    }
+
+    #[test]
+    fn test_parse_quantity() {
+        assert_eq!(parse_quantity("0x1b4").unwrap(), 436);
+        assert_eq!(parse_quantity("0x0").unwrap(), 0);
+        assert!(parse_quantity("0xzz").is_err());
+    }
+}
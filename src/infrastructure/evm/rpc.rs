@@ -0,0 +1,187 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+
+use super::{TRANSFER_TOPIC, parse_quantity};
+
+/// Blocks requested per JSON-RPC batch.
+const BLOCKS_PER_BATCH: usize = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub number: u64,
+    pub hash: String,
+    pub timestamp: u64,
+}
+
+#[derive(Deserialize)]
+struct RawBlock {
+    number: String,
+    hash: String,
+    timestamp: String,
+}
+
+impl TryFrom<RawBlock> for Block {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: RawBlock) -> Result<Self> {
+        Ok(Block {
+            number: parse_quantity(&raw.number)?,
+            hash: raw.hash,
+            timestamp: parse_quantity(&raw.timestamp)?,
+        })
+    }
+}
+
+/// An `eth_getLogs` entry, quantities still hex-encoded.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Log {
+    pub topics: Vec<String>,
+    pub data: String,
+    pub block_number: String,
+    pub block_hash: String,
+    pub transaction_hash: String,
+    pub log_index: String,
+    /// Set when a reorg dropped the log; only seen on subscriptions.
+    #[serde(default)]
+    pub removed: bool,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    id: u64,
+    result: Option<Value>,
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+/// Ethereum JSON-RPC over HTTP.
+pub struct JsonRpcClient {
+    client: reqwest::Client,
+    url: String,
+    next_id: AtomicU64,
+}
+
+impl JsonRpcClient {
+    pub fn new(url: &str, timeout: Duration) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .context("Failed to create the JSON-RPC client")?;
+        Ok(Self {
+            client,
+            url: url.to_string(),
+            next_id: AtomicU64::new(1),
+        })
+    }
+
+    pub async fn block_number(&self) -> Result<u64> {
+        let number: String = self.call("eth_blockNumber", json!([])).await?;
+        parse_quantity(&number)
+    }
+
+    /// Blocks by number, in the same order; `None` for blocks the node
+    /// doesn't have yet.
+    pub async fn blocks(&self, numbers: &[u64]) -> Result<Vec<Option<Block>>> {
+        let mut blocks = Vec::with_capacity(numbers.len());
+        for chunk in numbers.chunks(BLOCKS_PER_BATCH) {
+            let calls = chunk
+                .iter()
+                .map(|number| {
+                    let params = json!([format!("{number:#x}"), false]);
+                    ("eth_getBlockByNumber", params)
+                })
+                .collect();
+            for raw in self.batch::<Option<RawBlock>>(calls).await? {
+                blocks.push(raw.map(Block::try_from).transpose()?);
+            }
+        }
+        Ok(blocks)
+    }
+
+    /// `Transfer` events emitted by `token` in blocks `from..=to`.
+    pub async fn transfer_logs(&self, token: &str, from: u64, to: u64) -> Result<Vec<Log>> {
+        let filter = json!({
+            "address": token,
+            "topics": [TRANSFER_TOPIC],
+            "fromBlock": format!("{from:#x}"),
+            "toBlock": format!("{to:#x}"),
+        });
+        self.call("eth_getLogs", json!([filter])).await
+    }
+
+    async fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let request = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+        let response: Response = self.send(&request).await?;
+        decode(method, response)
+    }
+
+    async fn batch<T: DeserializeOwned>(&self, calls: Vec<(&str, Value)>) -> Result<Vec<T>> {
+        let first_id = self
+            .next_id
+            .fetch_add(calls.len() as u64, Ordering::Relaxed);
+        let requests: Vec<Value> = calls
+            .iter()
+            .zip(first_id..)
+            .map(|((method, params), id)| {
+                json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params})
+            })
+            .collect();
+
+        // Answers may come back in any order.
+        let mut responses: Vec<Response> = self.send(&requests).await?;
+        responses.sort_by_key(|response| response.id);
+        if responses.len() != calls.len()
+            || !responses
+                .iter()
+                .zip(first_id..)
+                .all(|(response, id)| response.id == id)
+        {
+            bail!("JSON-RPC batch answered with mismatched ids");
+        }
+
+        calls
+            .iter()
+            .zip(responses)
+            .map(|((method, _), response)| decode(method, response))
+            .collect()
+    }
+
+    async fn send<T: DeserializeOwned>(&self, body: &impl serde::Serialize) -> Result<T> {
+        let response = self
+            .client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(serde_json::to_vec(body)?)
+            .send()
+            .await
+            .context("JSON-RPC request failed")?
+            .error_for_status()?;
+        let bytes = response.bytes().await?;
+        serde_json::from_slice(&bytes).context("Invalid JSON-RPC response")
+    }
+}
+
+fn decode<T: DeserializeOwned>(method: &str, response: Response) -> Result<T> {
+    if let Some(error) = response.error {
+        return Err(anyhow!(
+            "{method} failed with code {}: {}",
+            error.code,
+            error.message
+        ));
+    }
+    let result = response.result.unwrap_or(Value::Null);
+    serde_json::from_value(result).with_context(|| format!("Invalid {method} result"))
+}
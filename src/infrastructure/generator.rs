@@ -1,13 +1,20 @@
-use crate::domain::entities::transfer::Transfer;
+use crate::domain::{
+    entities::transfer::Transfer,
+    validation::{TransferValidator, rules::AddressFormat},
+};
 use anyhow::Result;
-use rand::{Rng, distributions::Alphanumeric};
+use rand::{Rng, SeedableRng, distributions::Alphanumeric, rngs::StdRng};
+use serde::{Deserialize, Serialize};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 pub trait TransferGenerator {
-    fn generate(&self, count: usize) -> Result<Vec<Transfer>>;
+    /// `count` transfers with addresses in `address_format`, checked by the
+    /// standard validator for that format.
+    fn generate(&self, count: usize, address_format: AddressFormat) -> Result<Vec<Transfer>>;
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct TransferGenConfig {
     pub min_amount: f64,
     pub max_amount: f64,
@@ -30,16 +37,67 @@ impl Default for TransferGenConfig {
     }
 }
 
+/// When the startup run generates transfers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GenerationMode {
+    /// Never.
+    Off,
+    /// Once, while the database holds no transfers at all.
+    #[default]
+    Once,
+    /// `count` more transfers on every run.
+    Always,
+    /// Until `count` generated transfers are stored.
+    TopUp,
+}
+
+impl GenerationMode {
+    /// Transfers to generate given the `generated` rows recorded by earlier
+    /// runs and the rows `stored` overall.
+    pub fn rows_to_generate(self, count: usize, generated: u64, stored: u64) -> usize {
+        match self {
+            GenerationMode::Off => 0,
+            GenerationMode::Once if generated > 0 || stored > 0 => 0,
+            GenerationMode::Once | GenerationMode::Always => count,
+            GenerationMode::TopUp => {
+                count.saturating_sub(usize::try_from(generated).unwrap_or(usize::MAX))
+            }
+        }
+    }
+}
+
 impl TransferGenerator for TransferGenConfig {
-    fn generate(&self, count: usize) -> Result<Vec<Transfer>> {
-        let mut rng = rand::thread_rng();
+    fn generate(&self, count: usize, address_format: AddressFormat) -> Result<Vec<Transfer>> {
+        self.generate_with(count, address_format, &mut rand::thread_rng())
+    }
+}
+
+impl TransferGenConfig {
+    /// Generates the same addresses, amounts, prices and ages for the same
+    /// `seed`; timestamps still count back from the current time.
+    pub fn generate_seeded(
+        &self,
+        count: usize,
+        address_format: AddressFormat,
+        seed: u64,
+    ) -> Result<Vec<Transfer>> {
+        self.generate_with(count, address_format, &mut StdRng::seed_from_u64(seed))
+    }
+
+    fn generate_with(
+        &self,
+        count: usize,
+        address_format: AddressFormat,
+        rng: &mut impl Rng,
+    ) -> Result<Vec<Transfer>> {
         let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
 
         let address_pool: Vec<String> = (0..self.address_pool_size)
-            .map(|_| rand_address(&mut rng))
+            .map(|_| rand_address(address_format, rng))
             .collect();
 
-        let data = (0..count)
+        let data: Vec<Transfer> = (0..count)
             .map(|_| {
                 let from_idx = rng.gen_range(0..address_pool.len());
                 let mut to_idx = rng.gen_range(0..address_pool.len());
@@ -64,16 +122,23 @@ impl TransferGenerator for TransferGenConfig {
             })
             .collect();
 
+        TransferValidator::standard(address_format).validate_all(&data)?;
+
         Ok(data)
     }
 }
 
-fn rand_address(rng: &mut impl Rng) -> String {
-    let suffix: String = rng
-        .sample_iter(&Alphanumeric)
-        .take(10)
-        .map(char::from)
-        .collect();
+fn rand_address(address_format: AddressFormat, rng: &mut impl Rng) -> String {
+    let suffix: String = match address_format {
+        AddressFormat::Generic => rng
+            .sample_iter(&Alphanumeric)
+            .take(10)
+            .map(char::from)
+            .collect(),
+        AddressFormat::Evm => (0..40)
+            .map(|_| char::from_digit(rng.gen_range(0..16), 16).unwrap())
+            .collect(),
+    };
     format!("0x{}", suffix)
 }
 
@@ -84,14 +149,14 @@ mod tests {
     #[test]
     fn test_generate_count() {
         let config = TransferGenConfig::default();
-        let transfers = config.generate(10).unwrap();
+        let transfers = config.generate(10, AddressFormat::Generic).unwrap();
         assert_eq!(transfers.len(), 10);
     }
 
     #[test]
     fn test_generate_zero_count() {
         let config = TransferGenConfig::default();
-        let transfers = config.generate(0).unwrap();
+        let transfers = config.generate(0, AddressFormat::Generic).unwrap();
         assert_eq!(transfers.len(), 0);
     }
 
@@ -106,7 +171,7 @@ mod tests {
             address_pool_size: 10,
         };
 
-        let transfers = config.generate(100).unwrap();
+        let transfers = config.generate(100, AddressFormat::Generic).unwrap();
 
         for transfer in transfers {
             assert!(transfer.amount >= 50.0 && transfer.amount < 100.0);
@@ -122,7 +187,7 @@ mod tests {
     #[test]
     fn test_addresses_are_different() {
         let config = TransferGenConfig::default();
-        let transfers = config.generate(100).unwrap();
+        let transfers = config.generate(100, AddressFormat::Generic).unwrap();
 
         for transfer in transfers {
             assert_ne!(
@@ -143,7 +208,7 @@ mod tests {
             address_pool_size: 5,
         };
 
-        let transfers = config.generate(50).unwrap();
+        let transfers = config.generate(50, AddressFormat::Generic).unwrap();
 
         let mut all_addresses = std::collections::HashSet::new();
         for transfer in &transfers {
@@ -170,7 +235,7 @@ mod tests {
             address_pool_size: 3,
         };
 
-        let transfers = config.generate(20).unwrap();
+        let transfers = config.generate(20, AddressFormat::Generic).unwrap();
 
         let mut address_usage: std::collections::HashMap<String, usize> =
             std::collections::HashMap::new();
@@ -190,12 +255,48 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_generate_seeded_is_repeatable() {
+        let config = TransferGenConfig::default();
+
+        let first = config
+            .generate_seeded(20, AddressFormat::Generic, 7)
+            .unwrap();
+        let second = config
+            .generate_seeded(20, AddressFormat::Generic, 7)
+            .unwrap();
+        let other = config
+            .generate_seeded(20, AddressFormat::Generic, 8)
+            .unwrap();
+
+        let key = |t: &Transfer| (t.from.clone(), t.to.clone(), t.amount, t.usd_price);
+        assert_eq!(
+            first.iter().map(key).collect::<Vec<_>>(),
+            second.iter().map(key).collect::<Vec<_>>()
+        );
+        assert_ne!(
+            first.iter().map(key).collect::<Vec<_>>(),
+            other.iter().map(key).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_rows_to_generate() {
+        assert_eq!(GenerationMode::Off.rows_to_generate(40, 0, 0), 0);
+        assert_eq!(GenerationMode::Once.rows_to_generate(40, 0, 0), 40);
+        assert_eq!(GenerationMode::Once.rows_to_generate(40, 0, 5), 0);
+        assert_eq!(GenerationMode::Once.rows_to_generate(40, 40, 0), 0);
+        assert_eq!(GenerationMode::Always.rows_to_generate(40, 40, 40), 40);
+        assert_eq!(GenerationMode::TopUp.rows_to_generate(40, 25, 100), 15);
+        assert_eq!(GenerationMode::TopUp.rows_to_generate(40, 60, 60), 0);
+    }
+
     #[test]
     fn test_rand_address() {
         let mut rng = rand::thread_rng();
 
-        let addr1 = rand_address(&mut rng);
-        let addr2 = rand_address(&mut rng);
+        let addr1 = rand_address(AddressFormat::Generic, &mut rng);
+        let addr2 = rand_address(AddressFormat::Generic, &mut rng);
 
         assert!(addr1.starts_with("0x"));
         assert!(addr2.starts_with("0x"));
@@ -204,6 +305,18 @@ mod tests {
         assert_ne!(addr1, addr2);
     }
 
+    #[test]
+    fn test_generate_evm_addresses() {
+        let transfers = TransferGenConfig::default()
+            .generate(20, AddressFormat::Evm)
+            .unwrap();
+
+        for transfer in transfers {
+            assert_eq!(AddressFormat::Evm.check(&transfer.from), None);
+            assert_eq!(AddressFormat::Evm.check(&transfer.to), None);
+        }
+    }
+
     #[test]
     fn test_timestamp_generation() {
         let now = SystemTime::now()
@@ -220,7 +333,7 @@ mod tests {
             address_pool_size: 5,
         };
 
-        let transfers = config.generate(10).unwrap();
+        let transfers = config.generate(10, AddressFormat::Generic).unwrap();
 
         for transfer in transfers {
             assert!(transfer.ts <= now);
@@ -231,16 +344,18 @@ mod tests {
     #[test]
     fn test_error_handling() {
         let config = TransferGenConfig::default();
-        let result = config.generate(10);
+        let result = config.generate(10, AddressFormat::Generic);
         assert!(result.is_ok());
     }
 
     #[test]
     fn test_result_unwrap_or_else() {
         let config = TransferGenConfig::default();
-        let transfers = config.generate(5).unwrap_or_else(|e| {
-            panic!("Failed to generate transfers: {}", e);
-        });
+        let transfers = config
+            .generate(5, AddressFormat::Generic)
+            .unwrap_or_else(|e| {
+                panic!("Failed to generate transfers: {}", e);
+            });
         assert_eq!(transfers.len(), 5);
     }
 
@@ -267,7 +382,7 @@ mod tests {
             address_pool_size: 2,
         };
 
-        let transfers = config.generate(10).unwrap();
+        let transfers = config.generate(10, AddressFormat::Generic).unwrap();
 
         assert_eq!(transfers.len(), 10);
 
@@ -294,7 +409,7 @@ mod tests {
             address_pool_size: 100,
         };
 
-        let transfers = config.generate(50).unwrap();
+        let transfers = config.generate(50, AddressFormat::Generic).unwrap();
 
         let mut all_addresses = std::collections::HashSet::new();
         for transfer in &transfers {
@@ -0,0 +1,150 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{Context, Result, anyhow};
+use async_trait::async_trait;
+use rdkafka::{
+    ClientConfig, Message as _, Offset, TopicPartitionList,
+    consumer::{CommitMode, Consumer, StreamConsumer},
+    message::{BorrowedMessage, Header, OwnedHeaders},
+    producer::{FutureProducer, FutureRecord},
+};
+use tokio::time::{Instant, timeout_at};
+
+use crate::config::KafkaSettings;
+
+use super::{DeadLetterSink, Message, MessageSource, last_offsets};
+
+const DEAD_LETTER_TIMEOUT: Duration = Duration::from_secs(30);
+
+fn client_config(settings: &KafkaSettings) -> Result<ClientConfig> {
+    let brokers = settings
+        .brokers
+        .as_deref()
+        .context("`kafka.brokers` is not set")?;
+    let mut config = ClientConfig::new();
+    config.set("bootstrap.servers", brokers);
+    for (key, value) in &settings.properties {
+        config.set(key, value);
+    }
+    Ok(config)
+}
+
+/// Consumer group member subscribed to `kafka.topic`, committing offsets
+/// only when asked to.
+pub struct KafkaSource {
+    consumer: Arc<StreamConsumer>,
+}
+
+impl KafkaSource {
+    pub fn connect(settings: &KafkaSettings) -> Result<Self> {
+        let consumer: StreamConsumer = client_config(settings)?
+            .set("group.id", &settings.group_id)
+            .set("enable.auto.commit", "false")
+            .set("auto.offset.reset", "earliest")
+            .create()
+            .context("Failed to create the Kafka consumer")?;
+        consumer
+            .subscribe(&[&settings.topic])
+            .with_context(|| format!("Failed to subscribe to {}", settings.topic))?;
+        Ok(Self {
+            consumer: Arc::new(consumer),
+        })
+    }
+}
+
+fn to_message(message: &BorrowedMessage<'_>) -> Message {
+    Message {
+        topic: message.topic().to_string(),
+        partition: message.partition(),
+        offset: message.offset(),
+        key: message.key().map(<[u8]>::to_vec),
+        payload: message.payload().map(<[u8]>::to_vec),
+    }
+}
+
+#[async_trait]
+impl MessageSource for KafkaSource {
+    async fn poll(&self, max: usize, wait: Duration) -> Result<Vec<Message>> {
+        let first = self.consumer.recv().await?;
+        let mut batch = vec![to_message(&first)];
+
+        let deadline = Instant::now() + wait;
+        while batch.len() < max {
+            match timeout_at(deadline, self.consumer.recv()).await {
+                Ok(message) => batch.push(to_message(&message?)),
+                Err(_) => break,
+            }
+        }
+        Ok(batch)
+    }
+
+    async fn commit(&self, messages: &[Message]) -> Result<()> {
+        let mut offsets = TopicPartitionList::new();
+        for (topic, partition, offset) in last_offsets(messages) {
+            offsets.add_partition_offset(topic, partition, Offset::Offset(offset + 1))?;
+        }
+
+        // A synchronous commit reports failures, but blocks until the broker
+        // answers.
+        let consumer = self.consumer.clone();
+        tokio::task::spawn_blocking(move || consumer.commit(&offsets, CommitMode::Sync)).await??;
+        Ok(())
+    }
+}
+
+/// Produces failed messages to `kafka.dead_letter_topic`, with the original
+/// key and payload and the reason and source position as headers.
+pub struct KafkaDeadLetters {
+    producer: FutureProducer,
+    topic: String,
+}
+
+impl KafkaDeadLetters {
+    pub fn connect(settings: &KafkaSettings) -> Result<Self> {
+        let producer = client_config(settings)?
+            .create()
+            .context("Failed to create the Kafka dead letter producer")?;
+        Ok(Self {
+            producer,
+            topic: settings.dead_letter_topic.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl DeadLetterSink for KafkaDeadLetters {
+    async fn send(&self, message: &Message, reason: &str) -> Result<()> {
+        let (partition, offset) = (message.partition.to_string(), message.offset.to_string());
+        let headers = OwnedHeaders::new()
+            .insert(Header {
+                key: "error",
+                value: Some(reason),
+            })
+            .insert(Header {
+                key: "source_topic",
+                value: Some(&message.topic),
+            })
+            .insert(Header {
+                key: "source_partition",
+                value: Some(&partition),
+            })
+            .insert(Header {
+                key: "source_offset",
+                value: Some(&offset),
+            });
+
+        let mut record = FutureRecord::<Vec<u8>, Vec<u8>>::to(&self.topic).headers(headers);
+        if let Some(key) = &message.key {
+            record = record.key(key);
+        }
+        if let Some(payload) = &message.payload {
+            record = record.payload(payload);
+        }
+
+        self.producer
+            .send(record, DEAD_LETTER_TIMEOUT)
+            .await
+            .map_err(|(err, _)| anyhow!("Failed to dead-letter to {}: {err}", self.topic))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,220 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::domain::entities::transfer::Transfer;
+
+/// Confluent wire format: a zero magic byte and a 4-byte schema id.
+const CONFLUENT_HEADER_BYTES: usize = 5;
+
+/// How a message payload encodes one transfer.
+///
+/// Avro payloads are a single binary datum of the record
+/// `{"type": "record", "name": "Transfer", "fields": [{"name": "ts", "type": "long"},
+/// {"name": "from", "type": "string"}, {"name": "to", "type": "string"},
+/// {"name": "amount", "type": "double"}, {"name": "usd_price", "type": "double"}]}`;
+/// writers must use this schema, as it is not resolved against theirs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageFormat {
+    /// A `Transfer` JSON object, as accepted by `POST /api/v1/transfers`.
+    #[default]
+    Json,
+    /// The bare Avro datum.
+    Avro,
+    /// The Avro datum behind the Confluent schema registry header; the
+    /// schema id is not checked.
+    AvroConfluent,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum DecodeError {
+    #[error("Message has no payload")]
+    Empty,
+    #[error("Invalid JSON: {0}")]
+    Json(String),
+    #[error("Invalid Avro: {0}")]
+    Avro(&'static str),
+}
+
+impl MessageFormat {
+    pub fn decode(self, payload: Option<&[u8]>) -> Result<Transfer, DecodeError> {
+        let payload = payload.filter(|payload| !payload.is_empty());
+        let payload = payload.ok_or(DecodeError::Empty)?;
+
+        match self {
+            MessageFormat::Json => {
+                serde_json::from_slice(payload).map_err(|err| DecodeError::Json(err.to_string()))
+            }
+            MessageFormat::Avro => decode_avro(payload),
+            MessageFormat::AvroConfluent => {
+                match payload.split_at_checked(CONFLUENT_HEADER_BYTES) {
+                    Some(([0, ..], datum)) => decode_avro(datum),
+                    _ => Err(DecodeError::Avro("missing the Confluent header")),
+                }
+            }
+        }
+    }
+}
+
+fn decode_avro(datum: &[u8]) -> Result<Transfer, DecodeError> {
+    let mut reader = AvroReader { datum };
+    let ts = reader.long()?;
+    let transfer = Transfer {
+        ts: u64::try_from(ts).map_err(|_| DecodeError::Avro("negative ts"))?,
+        from: reader.string()?,
+        to: reader.string()?,
+        amount: reader.double()?,
+        usd_price: reader.double()?,
+    };
+    if !reader.datum.is_empty() {
+        return Err(DecodeError::Avro("trailing bytes after the record"));
+    }
+    Ok(transfer)
+}
+
+struct AvroReader<'a> {
+    datum: &'a [u8],
+}
+
+impl AvroReader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8], DecodeError> {
+        let (taken, rest) = self
+            .datum
+            .split_at_checked(len)
+            .ok_or(DecodeError::Avro("truncated record"))?;
+        self.datum = rest;
+        Ok(taken)
+    }
+
+    /// Zig-zag encoded variable-length integer.
+    fn long(&mut self) -> Result<i64, DecodeError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok((value >> 1) as i64 ^ -((value & 1) as i64));
+            }
+        }
+        Err(DecodeError::Avro("long is longer than 10 bytes"))
+    }
+
+    fn string(&mut self) -> Result<String, DecodeError> {
+        let len =
+            usize::try_from(self.long()?).map_err(|_| DecodeError::Avro("negative length"))?;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError::Avro("string is not UTF-8"))
+    }
+
+    fn double(&mut self) -> Result<f64, DecodeError> {
+        let bytes = self.take(8)?;
+        Ok(f64::from_le_bytes(bytes.try_into().expect("took 8 bytes")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_avro(transfer: &Transfer) -> Vec<u8> {
+        fn long(out: &mut Vec<u8>, value: i64) {
+            let mut zigzag = ((value << 1) ^ (value >> 63)) as u64;
+            while zigzag >= 0x80 {
+                out.push((zigzag as u8) | 0x80);
+                zigzag >>= 7;
+            }
+            out.push(zigzag as u8);
+        }
+        fn string(out: &mut Vec<u8>, value: &str) {
+            long(out, value.len() as i64);
+            out.extend_from_slice(value.as_bytes());
+        }
+
+        let mut out = Vec::new();
+        long(&mut out, transfer.ts as i64);
+        string(&mut out, &transfer.from);
+        string(&mut out, &transfer.to);
+        out.extend_from_slice(&transfer.amount.to_le_bytes());
+        out.extend_from_slice(&transfer.usd_price.to_le_bytes());
+        out
+    }
+
+    fn transfer() -> Transfer {
+        Transfer {
+            ts: 1_718_000_000,
+            from: "0xaaa".to_string(),
+            to: "0xbbb".to_string(),
+            amount: 12.5,
+            usd_price: 1.01,
+        }
+    }
+
+    fn assert_same(decoded: Transfer, expected: &Transfer) {
+        assert_eq!(decoded.ts, expected.ts);
+        assert_eq!(decoded.from, expected.from);
+        assert_eq!(decoded.to, expected.to);
+        assert_eq!(decoded.amount, expected.amount);
+        assert_eq!(decoded.usd_price, expected.usd_price);
+    }
+
+    #[test]
+    fn test_decode_json() {
+        let payload = serde_json::to_vec(&transfer()).unwrap();
+
+        assert_same(
+            MessageFormat::Json.decode(Some(&payload)).unwrap(),
+            &transfer(),
+        );
+        assert!(matches!(
+            MessageFormat::Json.decode(Some(b"{\"ts\": 1}")).err(),
+            Some(DecodeError::Json(_))
+        ));
+        assert_eq!(
+            MessageFormat::Json.decode(None).err(),
+            Some(DecodeError::Empty)
+        );
+    }
+
+    #[test]
+    fn test_decode_avro() {
+        let datum = encode_avro(&transfer());
+        assert_same(
+            MessageFormat::Avro.decode(Some(&datum)).unwrap(),
+            &transfer(),
+        );
+
+        let mut framed = vec![0, 0, 0, 0, 42];
+        framed.extend_from_slice(&datum);
+        assert_same(
+            MessageFormat::AvroConfluent.decode(Some(&framed)).unwrap(),
+            &transfer(),
+        );
+        assert_eq!(
+            MessageFormat::AvroConfluent.decode(Some(&datum)).err(),
+            Some(DecodeError::Avro("missing the Confluent header"))
+        );
+    }
+
+    #[test]
+    fn test_decode_avro_rejects_malformed_data() {
+        let datum = encode_avro(&transfer());
+
+        assert_eq!(
+            MessageFormat::Avro
+                .decode(Some(&datum[..datum.len() - 1]))
+                .err(),
+            Some(DecodeError::Avro("truncated record"))
+        );
+        let mut trailing = datum.clone();
+        trailing.push(0);
+        assert_eq!(
+            MessageFormat::Avro.decode(Some(&trailing)).err(),
+            Some(DecodeError::Avro("trailing bytes after the record"))
+        );
+        // ts = -1
+        assert_eq!(
+            MessageFormat::Avro.decode(Some(&[0x01])).err(),
+            Some(DecodeError::Avro("negative ts"))
+        );
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::{
+    sync::Notify,
+    time::{Instant, timeout_at},
+};
+
+use super::{DeadLetterSink, Message, MessageSource, last_offsets};
+
+/// In-process stand-in for a broker: single-partition topics and one
+/// consumer group. A new consumer resumes from the last commit, like a
+/// Kafka consumer after a restart.
+#[derive(Default)]
+pub struct MemoryBroker {
+    state: Mutex<BrokerState>,
+    produced: Notify,
+}
+
+#[derive(Default)]
+struct BrokerState {
+    topics: HashMap<String, Vec<Message>>,
+    /// Next offset to consume, per topic.
+    committed: HashMap<String, i64>,
+}
+
+impl MemoryBroker {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Appends to `topic` and returns the message's offset.
+    pub fn produce(&self, topic: &str, key: Option<&[u8]>, payload: Option<&[u8]>) -> i64 {
+        let mut state = self.state.lock().unwrap();
+        let messages = state.topics.entry(topic.to_string()).or_default();
+        let offset = messages.len() as i64;
+        messages.push(Message {
+            topic: topic.to_string(),
+            partition: 0,
+            offset,
+            key: key.map(<[u8]>::to_vec),
+            payload: payload.map(<[u8]>::to_vec),
+        });
+        drop(state);
+        self.produced.notify_waiters();
+        offset
+    }
+
+    pub fn committed(&self, topic: &str) -> i64 {
+        let state = self.state.lock().unwrap();
+        state.committed.get(topic).copied().unwrap_or(0)
+    }
+
+    pub fn consumer(self: &Arc<Self>, topic: &str) -> MemoryConsumer {
+        MemoryConsumer {
+            broker: self.clone(),
+            topic: topic.to_string(),
+            position: Mutex::new(self.committed(topic)),
+        }
+    }
+}
+
+pub struct MemoryConsumer {
+    broker: Arc<MemoryBroker>,
+    topic: String,
+    position: Mutex<i64>,
+}
+
+impl MemoryConsumer {
+    fn take(&self, max: usize) -> Vec<Message> {
+        let state = self.broker.state.lock().unwrap();
+        let mut position = self.position.lock().unwrap();
+        let messages = state.topics.get(&self.topic).map_or(&[][..], Vec::as_slice);
+        let taken: Vec<Message> = messages
+            .iter()
+            .skip(*position as usize)
+            .take(max)
+            .cloned()
+            .collect();
+        *position += taken.len() as i64;
+        taken
+    }
+}
+
+#[async_trait]
+impl MessageSource for MemoryConsumer {
+    async fn poll(&self, max: usize, wait: Duration) -> Result<Vec<Message>> {
+        let mut batch = loop {
+            let produced = self.broker.produced.notified();
+            let taken = self.take(max);
+            if !taken.is_empty() {
+                break taken;
+            }
+            produced.await;
+        };
+
+        let deadline = Instant::now() + wait;
+        while batch.len() < max {
+            let produced = self.broker.produced.notified();
+            let taken = self.take(max - batch.len());
+            if !taken.is_empty() {
+                batch.extend(taken);
+            } else if timeout_at(deadline, produced).await.is_err() {
+                break;
+            }
+        }
+        Ok(batch)
+    }
+
+    async fn commit(&self, messages: &[Message]) -> Result<()> {
+        let mut state = self.broker.state.lock().unwrap();
+        for (topic, _, offset) in last_offsets(messages) {
+            state.committed.insert(topic.to_string(), offset + 1);
+        }
+        Ok(())
+    }
+}
+
+/// Keeps dead-lettered messages with their reasons.
+#[derive(Default)]
+pub struct MemoryDeadLetters {
+    messages: Mutex<Vec<(Message, String)>>,
+}
+
+impl MemoryDeadLetters {
+    pub fn messages(&self) -> Vec<(Message, String)> {
+        self.messages.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl DeadLetterSink for MemoryDeadLetters {
+    async fn send(&self, message: &Message, reason: &str) -> Result<()> {
+        let mut messages = self.messages.lock().unwrap();
+        messages.push((message.clone(), reason.to_string()));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_uncommitted_messages_are_delivered_again() {
+        let broker = MemoryBroker::new();
+        for payload in [b"a", b"b", b"c"] {
+            broker.produce("transfers", None, Some(payload));
+        }
+
+        let consumer = broker.consumer("transfers");
+        let first = consumer.poll(2, Duration::ZERO).await.unwrap();
+        assert_eq!(first.len(), 2);
+        consumer.commit(&first[..1]).await.unwrap();
+        assert_eq!(broker.committed("transfers"), 1);
+
+        let restarted = broker.consumer("transfers");
+        let again = restarted.poll(10, Duration::ZERO).await.unwrap();
+        let offsets: Vec<i64> = again.iter().map(|message| message.offset).collect();
+        assert_eq!(offsets, vec![1, 2]);
+    }
+
+    #[actix_web::test]
+    async fn test_poll_waits_for_the_first_message() {
+        let broker = MemoryBroker::new();
+        let consumer = broker.consumer("transfers");
+
+        let producer = broker.clone();
+        actix_web::rt::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            producer.produce("transfers", None, Some(b"a"));
+        });
+
+        let batch = consumer.poll(10, Duration::from_millis(20)).await.unwrap();
+        assert_eq!(batch.len(), 1);
+    }
+}
@@ -0,0 +1,78 @@
+//! Kafka-protocol topic consumption: the broker client, an in-process
+//! stand-in with the same contract, and message decoding.
+
+pub mod client;
+pub mod codec;
+pub mod memory;
+
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+/// A consumed message, owned so a batch can outlive the poll.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub topic: String,
+    pub partition: i32,
+    pub offset: i64,
+    pub key: Option<Vec<u8>>,
+    pub payload: Option<Vec<u8>>,
+}
+
+#[async_trait]
+pub trait MessageSource: Send + Sync {
+    /// Waits for a message, then collects up to `max` arriving within
+    /// `wait`. Messages not committed are delivered again after a restart
+    /// or rebalance.
+    async fn poll(&self, max: usize, wait: Duration) -> Result<Vec<Message>>;
+
+    /// Marks `messages`, and everything before them in their partitions, as
+    /// consumed.
+    async fn commit(&self, messages: &[Message]) -> Result<()>;
+}
+
+#[async_trait]
+pub trait DeadLetterSink: Send + Sync {
+    /// Stores `message` with the reason it could not be inserted.
+    async fn send(&self, message: &Message, reason: &str) -> Result<()>;
+}
+
+/// Highest offset per topic and partition; committing it commits the rest.
+fn last_offsets(messages: &[Message]) -> Vec<(&str, i32, i64)> {
+    let mut last: Vec<(&str, i32, i64)> = Vec::new();
+    for message in messages {
+        match last.iter_mut().find(|(topic, partition, _)| {
+            *topic == message.topic && *partition == message.partition
+        }) {
+            Some(entry) => entry.2 = entry.2.max(message.offset),
+            None => last.push((&message.topic, message.partition, message.offset)),
+        }
+    }
+    last
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(partition: i32, offset: i64) -> Message {
+        Message {
+            topic: "transfers".to_string(),
+            partition,
+            offset,
+            key: None,
+            payload: None,
+        }
+    }
+
+    #[test]
+    fn test_last_offsets_per_partition() {
+        let messages = [message(0, 4), message(1, 7), message(0, 5), message(1, 6)];
+
+        assert_eq!(
+            last_offsets(&messages),
+            vec![("transfers", 0, 5), ("transfers", 1, 7)]
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use std::sync::{OnceLock, RwLock};
+
+use env_logger::Logger;
+use log::{Log, Metadata, Record};
+
+static LOGGER: OnceLock<ReloadableLogger> = OnceLock::new();
+
+/// `env_logger` behind a lock, so the filter can be replaced while running.
+struct ReloadableLogger {
+    inner: RwLock<Logger>,
+}
+
+impl Log for ReloadableLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.read().unwrap().enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        self.inner.read().unwrap().log(record)
+    }
+
+    fn flush(&self) {
+        self.inner.read().unwrap().flush()
+    }
+}
+
+fn build(filter: &str) -> Logger {
+    env_logger::Builder::new().parse_filters(filter).build()
+}
+
+/// Installs the global logger; later calls only change the filter.
+pub fn init(filter: &str) {
+    let mut installed = false;
+    let logger = LOGGER.get_or_init(|| {
+        installed = true;
+        ReloadableLogger {
+            inner: RwLock::new(build(filter)),
+        }
+    });
+
+    if installed {
+        if log::set_logger(logger).is_ok() {
+            log::set_max_level(logger.inner.read().unwrap().filter());
+        }
+    } else {
+        set_filter(filter);
+    }
+}
+
+/// Replaces the filter of the logger installed by [`init`].
+pub fn set_filter(filter: &str) {
+    if let Some(logger) = LOGGER.get() {
+        let replacement = build(filter);
+        log::set_max_level(replacement.filter());
+        *logger.inner.write().unwrap() = replacement;
+    }
+}
@@ -1,4 +1,12 @@
 pub mod app_setup;
 pub mod clickhouse;
+pub mod evm;
 pub mod generator;
+pub mod kafka;
+pub mod logging;
+pub mod ownership;
+pub mod reload;
 pub mod repositories;
+pub mod resilience;
+pub mod shutdown;
+pub mod write_buffer;
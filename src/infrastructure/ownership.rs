@@ -0,0 +1,66 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result, bail};
+
+use crate::domain::entities::cluster::OwnershipLabel;
+
+/// Reads `address,owner` lines. Blank lines, `#` comments and an optional
+/// `address,owner` header are skipped.
+pub fn load_ownership_labels(path: impl AsRef<Path>) -> Result<Vec<OwnershipLabel>> {
+    let path = path.as_ref();
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read ownership file {}", path.display()))?;
+    parse_ownership_labels(&content)
+}
+
+fn parse_ownership_labels(content: &str) -> Result<Vec<OwnershipLabel>> {
+    let mut labels = Vec::new();
+
+    for (line_no, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let Some((address, owner)) = line.split_once(',') else {
+            bail!("Invalid ownership line {}: {}", line_no + 1, line);
+        };
+        let (address, owner) = (address.trim(), owner.trim());
+
+        if line_no == 0 && address == "address" && owner == "owner" {
+            continue;
+        }
+        if address.is_empty() || owner.is_empty() {
+            bail!("Invalid ownership line {}: {}", line_no + 1, line);
+        }
+
+        labels.push(OwnershipLabel {
+            address: address.to_string(),
+            owner: owner.to_string(),
+        });
+    }
+
+    Ok(labels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_skips_header_and_comments() {
+        let content = "address,owner\n# treasury\n0xA, team\n\n0xB,team\n";
+
+        let labels = parse_ownership_labels(content).unwrap();
+
+        assert_eq!(labels.len(), 2);
+        assert_eq!(labels[0].address, "0xA");
+        assert_eq!(labels[0].owner, "team");
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_line() {
+        assert!(parse_ownership_labels("0xA team").is_err());
+        assert!(parse_ownership_labels("0xA,").is_err());
+    }
+}
@@ -0,0 +1,91 @@
+use std::{
+    fs,
+    path::Path,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use tokio::{
+    signal::unix::{SignalKind, signal},
+    sync::watch,
+    time::interval,
+};
+
+use crate::config::{
+    Config,
+    runtime::{Revision, RuntimeConfig},
+    sources::ConfigArgs,
+};
+
+use super::{clickhouse::settings::QuerySettings, logging};
+
+/// Reloads the config from `args` on `SIGHUP` and, when `watch_every` is
+/// set, whenever the config file is modified. A config that fails to load
+/// is logged and leaves the active revision in place.
+pub async fn watch_config(
+    args: ConfigArgs,
+    runtime: Arc<RuntimeConfig>,
+    watch_every: Option<Duration>,
+) {
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => Some(hangup),
+        Err(err) => {
+            log::warn!("Cannot listen for SIGHUP, config reload by signal is disabled: {err}");
+            None
+        }
+    };
+    let file = args.config_file().filter(|_| watch_every.is_some());
+    if hangup.is_none() && file.is_none() {
+        return;
+    }
+    let mut modified = file.as_deref().and_then(modified_at);
+    let mut ticker = interval(watch_every.unwrap_or(Duration::from_secs(1)));
+
+    loop {
+        let trigger = tokio::select! {
+            Some(()) = async { hangup.as_mut()?.recv().await } => "signal",
+            _ = ticker.tick(), if file.is_some() => {
+                let current = file.as_deref().and_then(modified_at);
+                if current == modified {
+                    continue;
+                }
+                modified = current;
+                "file"
+            }
+        };
+
+        match Config::load(&args) {
+            Ok(config) => match runtime.apply(&config, trigger) {
+                Some(revision) => log::info!(
+                    "Config revision {} loaded on {}: {} changes, restart pending for {:?}",
+                    revision.number,
+                    trigger,
+                    revision.changes.len(),
+                    revision.pending_restart
+                ),
+                None => log::info!("Config reloaded on {trigger}, nothing changed"),
+            },
+            Err(err) => log::error!(
+                "Config reload on {trigger} failed, keeping revision {}: {err}",
+                runtime.current().number
+            ),
+        }
+    }
+}
+
+fn modified_at(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+/// Pushes every new revision into the components that read reloadable
+/// settings.
+pub async fn apply_revisions(
+    mut revisions: watch::Receiver<Arc<Revision>>,
+    stats_settings: Arc<RwLock<QuerySettings>>,
+) {
+    while revisions.changed().await.is_ok() {
+        let revision = revisions.borrow_and_update().clone();
+        logging::set_filter(&revision.settings.log_level);
+        *stats_settings.write().unwrap() = revision.settings.stats.clone();
+    }
+}
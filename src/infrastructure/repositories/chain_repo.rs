@@ -0,0 +1,154 @@
+use async_trait::async_trait;
+use clickhouse::Client;
+
+use crate::{
+    domain::{
+        entities::chain::{ChainTransfer, Checkpoint},
+        repositories::chain_repo::{ChainRepoAbstract, ChainRepoResult},
+    },
+    infrastructure::clickhouse::schema::{Schema, TableEngine, TableSpec},
+};
+
+use super::transfer_repo::{CHAIN_SOURCE, TRANSFERS_TABLE};
+
+const EVM_CHECKPOINTS_TABLE: TableSpec = TableSpec {
+    name: "evm_checkpoints",
+    columns: r#"
+        updated_at UInt64,
+        block_number UInt64,
+        block_hash String
+    "#,
+    engine: TableEngine::MergeTree,
+    order_by: "updated_at",
+    sharding_key: "rand()",
+    settings: None,
+};
+
+// The transfers each block produced, so a range that was inserted but not
+// checkpointed can be recognised and sent again exactly as it was.
+const EVM_LOGS_TABLE: TableSpec = TableSpec {
+    name: "evm_logs",
+    columns: r#"
+        block_number UInt64,
+        log_index UInt64,
+        block_hash String,
+        tx_hash String,
+        ts UInt64,
+        from String,
+        to String,
+        amount Float64,
+        usd_price Float64
+    "#,
+    engine: TableEngine::MergeTree,
+    order_by: "(block_number, log_index)",
+    sharding_key: "block_number",
+    settings: None,
+};
+
+pub struct ClickHouseChainRepo {
+    client: Client,
+    schema: Schema,
+    checkpoints: String,
+    logs: String,
+}
+
+impl ClickHouseChainRepo {
+    pub fn new(client: Client) -> Self {
+        Self::with_schema(client, Schema::default())
+    }
+
+    pub fn with_schema(client: Client, schema: Schema) -> Self {
+        Self {
+            client,
+            checkpoints: schema.table(EVM_CHECKPOINTS_TABLE.name),
+            logs: schema.table(EVM_LOGS_TABLE.name),
+            schema,
+        }
+    }
+
+    pub async fn create_table(&self) -> ChainRepoResult<()> {
+        for spec in [&EVM_CHECKPOINTS_TABLE, &EVM_LOGS_TABLE] {
+            for statement in self.schema.create_table(spec) {
+                self.client.query(&statement).execute().await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ChainRepoAbstract for ClickHouseChainRepo {
+    async fn checkpoint(&self) -> ChainRepoResult<Option<Checkpoint>> {
+        let checkpoint = self
+            .client
+            .query(&format!(
+                "SELECT ?fields FROM {} ORDER BY updated_at DESC, block_number DESC LIMIT 1",
+                self.checkpoints
+            ))
+            .fetch_optional::<Checkpoint>()
+            .await?;
+
+        Ok(checkpoint)
+    }
+
+    async fn save_checkpoint(&self, checkpoint: &Checkpoint) -> ChainRepoResult<()> {
+        let mut insert = self.client.insert(&self.checkpoints)?;
+        insert.write(checkpoint).await?;
+        insert.end().await?;
+
+        Ok(())
+    }
+
+    async fn save_logs(&self, logs: &[ChainTransfer]) -> ChainRepoResult<()> {
+        if logs.is_empty() {
+            return Ok(());
+        }
+
+        let mut insert = self.client.insert(&self.logs)?;
+        for log in logs {
+            insert.write(log).await?;
+        }
+        insert.end().await?;
+
+        Ok(())
+    }
+
+    async fn logs_from(&self, from_block: u64) -> ChainRepoResult<Vec<ChainTransfer>> {
+        let logs = self
+            .client
+            .query(&format!(
+                "SELECT ?fields FROM {} WHERE block_number >= ? ORDER BY block_number, log_index",
+                self.logs
+            ))
+            .bind(from_block)
+            .fetch_all::<ChainTransfer>()
+            .await?;
+
+        Ok(logs)
+    }
+
+    async fn rollback(&self, from_block: u64) -> ChainRepoResult<()> {
+        // Transfers first, so the logs still show what is left to delete
+        // if this fails halfway.
+        self.client
+            .query(&format!(
+                "DELETE FROM {} WHERE source = ? AND block_number >= ?",
+                self.schema.mutation_target(TRANSFERS_TABLE.name),
+            ))
+            .bind(CHAIN_SOURCE)
+            .bind(from_block)
+            .execute()
+            .await?;
+        self.client
+            .query(&format!(
+                "DELETE FROM {} WHERE block_number >= ?",
+                self.schema.mutation_target(EVM_LOGS_TABLE.name)
+            ))
+            .bind(from_block)
+            .execute()
+            .await?;
+
+        Ok(())
+    }
+}
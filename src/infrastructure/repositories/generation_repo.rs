@@ -0,0 +1,193 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use clickhouse::{Client, Row};
+use serde::Serialize;
+
+use crate::{
+    domain::{
+        entities::generation::GenerationRun,
+        repositories::generation_repo::{GenerationRepoAbstract, GenerationRepoResult},
+    },
+    infrastructure::clickhouse::schema::{Schema, TableEngine, TableSpec},
+};
+
+// Marks the database as holding generated data, so a restart doesn't
+// generate it again. Truncating it lets `once` generate anew.
+const GENERATION_RUNS_TABLE: TableSpec = TableSpec {
+    name: "generation_runs",
+    columns: r#"
+        ts UInt64,
+        source LowCardinality(String),
+        rows UInt64
+    "#,
+    engine: TableEngine::MergeTree,
+    order_by: "ts",
+    sharding_key: "rand()",
+    settings: None,
+};
+
+// One row per claim of a key and round, plus a `released` row per round
+// that was given up. Claims of a round are inserted with the key and round
+// as deduplication token, so ClickHouse keeps only the first and everyone
+// reads back the same owner. Releasing moves the key to the next round,
+// whose token is new.
+const GENERATION_CLAIMS_TABLE: TableSpec = TableSpec {
+    name: "generation_claims",
+    columns: r#"
+        key String,
+        round UInt64,
+        owner UInt64,
+        ts UInt64,
+        released Bool
+    "#,
+    engine: TableEngine::MergeTree,
+    order_by: "(key, round)",
+    // All claims of a key meet on one shard, where they deduplicate.
+    sharding_key: "cityHash64(key)",
+    settings: Some("non_replicated_deduplication_window = 100"),
+};
+
+#[derive(Row, Serialize)]
+struct Claim<'a> {
+    key: &'a str,
+    round: u64,
+    owner: u64,
+    ts: u64,
+    released: bool,
+}
+
+pub struct ClickHouseGenerationRepo {
+    client: Client,
+    schema: Schema,
+    runs: String,
+    claims: String,
+}
+
+impl ClickHouseGenerationRepo {
+    pub fn new(client: Client) -> Self {
+        Self::with_schema(client, Schema::default())
+    }
+
+    pub fn with_schema(client: Client, schema: Schema) -> Self {
+        Self {
+            client,
+            runs: schema.table(GENERATION_RUNS_TABLE.name),
+            claims: schema.table(GENERATION_CLAIMS_TABLE.name),
+            schema,
+        }
+    }
+
+    /// Rounds of `key` given up so far, which is the number of the current
+    /// one.
+    async fn round(&self, key: &str) -> GenerationRepoResult<u64> {
+        let round = self
+            .client
+            .query(&format!(
+                "SELECT count() FROM {} WHERE key = ? AND released",
+                self.claims
+            ))
+            .bind(key)
+            .fetch_one::<u64>()
+            .await?;
+
+        Ok(round)
+    }
+
+    async fn insert_claim(&self, claim: &Claim<'_>, token: &str) -> GenerationRepoResult<()> {
+        let mut insert = self
+            .client
+            .insert(&self.claims)?
+            .with_option("insert_deduplication_token", token);
+        if self.schema.is_clustered() {
+            insert = insert.with_option("insert_distributed_sync", "1");
+        }
+        insert.write(claim).await?;
+        insert.end().await?;
+
+        Ok(())
+    }
+
+    pub async fn create_table(&self) -> GenerationRepoResult<()> {
+        for spec in [&GENERATION_RUNS_TABLE, &GENERATION_CLAIMS_TABLE] {
+            for statement in self.schema.create_table(spec) {
+                self.client.query(&statement).execute().await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl GenerationRepoAbstract for ClickHouseGenerationRepo {
+    async fn record(&self, run: &GenerationRun) -> GenerationRepoResult<()> {
+        let mut insert = self.client.insert(&self.runs)?;
+        insert.write(run).await?;
+        insert.end().await?;
+
+        Ok(())
+    }
+
+    async fn generated_rows(&self) -> GenerationRepoResult<u64> {
+        let rows = self
+            .client
+            .query(&format!("SELECT sum(rows) FROM {}", self.runs))
+            .fetch_one::<u64>()
+            .await?;
+
+        Ok(rows)
+    }
+
+    async fn claim(&self, key: &str) -> GenerationRepoResult<bool> {
+        let round = self.round(key).await?;
+        let owner = rand::random();
+        self.insert_claim(
+            &Claim {
+                key,
+                round,
+                owner,
+                ts: unix_now(),
+                released: false,
+            },
+            &format!("{key}-{round}"),
+        )
+        .await?;
+
+        let winner = self
+            .client
+            .query(&format!(
+                "SELECT owner FROM {} WHERE key = ? AND round = ? AND NOT released \
+                 ORDER BY ts, owner LIMIT 1",
+                self.claims
+            ))
+            .bind(key)
+            .bind(round)
+            .fetch_one::<u64>()
+            .await?;
+
+        Ok(winner == owner)
+    }
+
+    async fn release(&self, key: &str) -> GenerationRepoResult<()> {
+        let round = self.round(key).await?;
+        self.insert_claim(
+            &Claim {
+                key,
+                round,
+                owner: 0,
+                ts: unix_now(),
+                released: true,
+            },
+            &format!("{key}-{round}-released"),
+        )
+        .await
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
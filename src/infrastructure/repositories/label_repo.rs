@@ -0,0 +1,195 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use clickhouse::{Client, Row};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::{
+        entities::label::{AddressLabel, LabelCategory},
+        repositories::{
+            errors::TransferRepoError,
+            label_repo::{LabelRepoAbstract, LabelRepoResult},
+        },
+    },
+    infrastructure::clickhouse::schema::{Schema, TableEngine, TableSpec},
+};
+
+#[derive(Debug, Serialize, Deserialize, Row)]
+struct AddressLabelRow {
+    address: String,
+    name: String,
+    category: String,
+    tags: Vec<String>,
+    updated_at: u64,
+}
+
+impl AddressLabelRow {
+    fn from_label(label: &AddressLabel, updated_at: u64) -> Self {
+        Self {
+            address: label.address.clone(),
+            name: label.name.clone(),
+            category: label.category.as_str().to_string(),
+            tags: label.tags.clone(),
+            updated_at,
+        }
+    }
+
+    fn into_label(self) -> LabelRepoResult<AddressLabel> {
+        let category = self
+            .category
+            .parse::<LabelCategory>()
+            .map_err(TransferRepoError::QueryError)?;
+
+        Ok(AddressLabel {
+            address: self.address,
+            name: self.name,
+            category,
+            tags: self.tags,
+        })
+    }
+}
+
+const LABELS_TABLE: TableSpec = TableSpec {
+    name: "address_labels",
+    columns: r#"
+        address String,
+        name String,
+        category LowCardinality(String),
+        tags Array(String),
+        updated_at UInt64
+    "#,
+    engine: TableEngine::ReplacingMergeTree {
+        version: Some("updated_at"),
+    },
+    order_by: "address",
+    sharding_key: "cityHash64(address)",
+    settings: None,
+};
+
+pub struct ClickHouseLabelRepo {
+    client: Client,
+    schema: Schema,
+    labels: String,
+}
+
+impl ClickHouseLabelRepo {
+    pub fn new(client: Client) -> Self {
+        Self::with_schema(client, Schema::default())
+    }
+
+    pub fn with_schema(client: Client, schema: Schema) -> Self {
+        Self {
+            client,
+            labels: schema.table(LABELS_TABLE.name),
+            schema,
+        }
+    }
+
+    pub async fn create_table(&self) -> LabelRepoResult<()> {
+        for statement in self.schema.create_table(&LABELS_TABLE) {
+            self.client.query(&statement).execute().await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn optimize_table(&self) -> LabelRepoResult<()> {
+        self.client
+            .query(&self.schema.optimize_table(LABELS_TABLE.name))
+            .execute()
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl LabelRepoAbstract for ClickHouseLabelRepo {
+    async fn save_all(&self, labels: &[AddressLabel]) -> LabelRepoResult<()> {
+        if labels.is_empty() {
+            return Ok(());
+        }
+
+        let updated_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| TransferRepoError::QueryError(e.to_string()))?
+            .as_millis() as u64;
+
+        let mut insert = self.client.insert(&self.labels)?;
+
+        for label in labels {
+            insert
+                .write(&AddressLabelRow::from_label(label, updated_at))
+                .await?;
+        }
+
+        insert.end().await?;
+
+        Ok(())
+    }
+
+    async fn find_by_address(&self, address: &str) -> LabelRepoResult<Option<AddressLabel>> {
+        let query = format!(
+            r#"
+            SELECT address, name, category, tags, updated_at
+            FROM {labels} FINAL
+            WHERE address = ?
+        "#,
+            labels = self.labels
+        );
+
+        let row = self
+            .client
+            .query(&query)
+            .bind(address)
+            .fetch_optional::<AddressLabelRow>()
+            .await?;
+
+        row.map(AddressLabelRow::into_label).transpose()
+    }
+
+    async fn find_all(
+        &self,
+        category: Option<LabelCategory>,
+    ) -> LabelRepoResult<Vec<AddressLabel>> {
+        let filter = if category.is_some() {
+            "WHERE category = ?"
+        } else {
+            ""
+        };
+        let query = format!(
+            r#"
+            SELECT address, name, category, tags, updated_at
+            FROM {labels} FINAL
+            {filter}
+            ORDER BY address
+        "#,
+            labels = self.labels
+        );
+
+        let mut query = self.client.query(&query);
+        if let Some(category) = category {
+            query = query.bind(category.as_str());
+        }
+
+        query
+            .fetch_all::<AddressLabelRow>()
+            .await?
+            .into_iter()
+            .map(AddressLabelRow::into_label)
+            .collect()
+    }
+
+    async fn delete(&self, address: &str) -> LabelRepoResult<()> {
+        self.client
+            .query(&format!(
+                "DELETE FROM {} WHERE address = ?",
+                self.schema.mutation_target(LABELS_TABLE.name)
+            ))
+            .bind(address)
+            .execute()
+            .await?;
+
+        Ok(())
+    }
+}
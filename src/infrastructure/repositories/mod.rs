@@ -1 +1,6 @@
+pub mod chain_repo;
+pub mod generation_repo;
+pub mod label_repo;
+pub mod price_repo;
+pub mod resilient_transfer_repo;
 pub mod transfer_repo;
@@ -0,0 +1,125 @@
+use async_trait::async_trait;
+use clickhouse::Client;
+
+use crate::{
+    domain::{
+        entities::{price::PricePoint, time_range::TimeRange},
+        repositories::price_repo::{PriceRepoAbstract, PriceRepoResult},
+    },
+    infrastructure::clickhouse::schema::{Schema, TableEngine, TableSpec},
+};
+
+// Re-uploading a (token, ts) pair replaces the previous price.
+pub(crate) const PRICES_TABLE: TableSpec = TableSpec {
+    name: "prices",
+    columns: r#"
+        token LowCardinality(String),
+        ts UInt64,
+        price Float64
+    "#,
+    engine: TableEngine::ReplacingMergeTree { version: None },
+    order_by: "(token, ts)",
+    sharding_key: "cityHash64(token)",
+    settings: None,
+};
+
+pub struct ClickHousePriceRepo {
+    client: Client,
+    schema: Schema,
+    prices: String,
+}
+
+impl ClickHousePriceRepo {
+    pub fn new(client: Client) -> Self {
+        Self::with_schema(client, Schema::default())
+    }
+
+    pub fn with_schema(client: Client, schema: Schema) -> Self {
+        Self {
+            client,
+            prices: schema.table(PRICES_TABLE.name),
+            schema,
+        }
+    }
+
+    pub async fn create_table(&self) -> PriceRepoResult<()> {
+        for statement in self.schema.create_table(&PRICES_TABLE) {
+            self.client.query(&statement).execute().await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn optimize_table(&self) -> PriceRepoResult<()> {
+        self.client
+            .query(&self.schema.optimize_table(PRICES_TABLE.name))
+            .execute()
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl PriceRepoAbstract for ClickHousePriceRepo {
+    async fn save_all(&self, prices: &[PricePoint]) -> PriceRepoResult<()> {
+        if prices.is_empty() {
+            return Ok(());
+        }
+
+        let mut insert = self.client.insert(&self.prices)?;
+
+        for price in prices {
+            insert.write(price).await?;
+        }
+
+        insert.end().await?;
+
+        Ok(())
+    }
+
+    async fn price_at(&self, token: &str, at: u64) -> PriceRepoResult<Option<PricePoint>> {
+        let query = format!(
+            r#"
+            SELECT token, ts, price
+            FROM {prices} FINAL
+            WHERE token = ? AND ts <= ?
+            ORDER BY ts DESC
+            LIMIT 1
+        "#,
+            prices = self.prices
+        );
+
+        let price = self
+            .client
+            .query(&query)
+            .bind(token)
+            .bind(at)
+            .fetch_optional::<PricePoint>()
+            .await?;
+
+        Ok(price)
+    }
+
+    async fn prices(&self, token: &str, range: &TimeRange) -> PriceRepoResult<Vec<PricePoint>> {
+        let query = format!(
+            r#"
+            SELECT token, ts, price
+            FROM {prices} FINAL
+            WHERE token = ? AND ts >= ? AND ts <= ?
+            ORDER BY ts
+        "#,
+            prices = self.prices
+        );
+
+        let prices = self
+            .client
+            .query(&query)
+            .bind(token)
+            .bind(range.start())
+            .bind(range.end())
+            .fetch_all::<PricePoint>()
+            .await?;
+
+        Ok(prices)
+    }
+}
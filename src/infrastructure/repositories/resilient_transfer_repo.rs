@@ -0,0 +1,319 @@
+use async_trait::async_trait;
+use futures::{StreamExt, stream};
+
+use crate::{
+    domain::{
+        entities::{
+            candle::Candle,
+            chain::ChainTransfer,
+            cluster::AddressCluster,
+            counterparty::{Counterparty, CounterpartySort},
+            graph::{GraphEdge, GraphFilter},
+            holder::AddressBalance,
+            insert_report::InsertReport,
+            integrity::{IntegrityIssue, NegativeBalance, TransferCheck},
+            summary::{DailyActivity, TransferTotals},
+            time_range::TimeRange,
+            transfer::Transfer,
+            user_stats::UserStats,
+        },
+        repositories::transfer_repo::{TransferRepoAbstract, TransferRepoResult, UserStatsStream},
+    },
+    infrastructure::resilience::{Operation, Resilience},
+};
+
+use super::transfer_repo::ClickHouseTransferRepo;
+
+/// The transfer repository used by the application.
+pub type TransferRepo = ResilientTransferRepo<ClickHouseTransferRepo>;
+
+/// Wraps a transfer repository with retries and a circuit breaker.
+pub struct ResilientTransferRepo<R: TransferRepoAbstract> {
+    inner: R,
+    resilience: Resilience,
+}
+
+impl<R: TransferRepoAbstract> ResilientTransferRepo<R> {
+    pub fn new(inner: R, resilience: Resilience) -> Self {
+        Self { inner, resilience }
+    }
+
+    /// The wrapped repository, for calls that should not be retried or
+    /// counted by the breaker, such as schema changes.
+    pub fn inner(&self) -> &R {
+        &self.inner
+    }
+}
+
+#[async_trait]
+impl<R: TransferRepoAbstract + Send + Sync> TransferRepoAbstract for ResilientTransferRepo<R> {
+    async fn save_all(&self, transfers: &[Transfer]) -> TransferRepoResult<InsertReport> {
+        self.resilience
+            .call(Operation::Insert, || self.inner.save_all(transfers))
+            .await
+    }
+
+    async fn save_chain_transfers(
+        &self,
+        transfers: &[ChainTransfer],
+        dedup_token: &str,
+    ) -> TransferRepoResult<InsertReport> {
+        self.resilience
+            .call(Operation::Insert, || {
+                self.inner.save_chain_transfers(transfers, dedup_token)
+            })
+            .await
+    }
+
+    async fn calculate_user_stats(&self) -> TransferRepoResult<Vec<UserStats>> {
+        self.resilience
+            .call(Operation::Stats, || self.inner.calculate_user_stats())
+            .await
+    }
+
+    /// Not retried, since rows may already have reached the client; an open
+    /// breaker still fails it fast, and the stream's outcome is recorded.
+    fn stream_user_stats(&self) -> UserStatsStream {
+        match self.resilience.breaker.acquire() {
+            Ok(()) => self
+                .resilience
+                .breaker
+                .clone()
+                .record_stream(self.inner.stream_user_stats()),
+            Err(error) => stream::once(async { Err(error) }).boxed(),
+        }
+    }
+
+    async fn user_stats_for(&self, addresses: &[String]) -> TransferRepoResult<Vec<UserStats>> {
+        self.resilience
+            .call(Operation::Stats, || self.inner.user_stats_for(addresses))
+            .await
+    }
+
+    async fn calculate_user_stats_with_prices(
+        &self,
+        token: &str,
+    ) -> TransferRepoResult<Vec<UserStats>> {
+        self.resilience
+            .call(Operation::Stats, || {
+                self.inner.calculate_user_stats_with_prices(token)
+            })
+            .await
+    }
+
+    async fn calculate_cluster_stats(
+        &self,
+        clusters: &[AddressCluster],
+    ) -> TransferRepoResult<Vec<UserStats>> {
+        self.resilience
+            .call(Operation::Stats, || {
+                self.inner.calculate_cluster_stats(clusters)
+            })
+            .await
+    }
+
+    async fn top_counterparties(
+        &self,
+        address: &str,
+        sort: CounterpartySort,
+        limit: usize,
+    ) -> TransferRepoResult<Vec<Counterparty>> {
+        self.resilience
+            .call(Operation::Idempotent, || {
+                self.inner.top_counterparties(address, sort, limit)
+            })
+            .await
+    }
+
+    async fn transfer_graph(&self, filter: &GraphFilter) -> TransferRepoResult<Vec<GraphEdge>> {
+        self.resilience
+            .call(Operation::Idempotent, || self.inner.transfer_graph(filter))
+            .await
+    }
+
+    async fn get_transfers(&self, range: &TimeRange) -> TransferRepoResult<Vec<Transfer>> {
+        self.resilience
+            .call(Operation::Idempotent, || self.inner.get_transfers(range))
+            .await
+    }
+
+    async fn balances(&self, at: Option<u64>) -> TransferRepoResult<Vec<AddressBalance>> {
+        self.resilience
+            .call(Operation::Idempotent, || self.inner.balances(at))
+            .await
+    }
+
+    async fn find_anomalies(
+        &self,
+        check: TransferCheck,
+        sample_limit: usize,
+    ) -> TransferRepoResult<IntegrityIssue<Transfer>> {
+        self.resilience
+            .call(Operation::Idempotent, || {
+                self.inner.find_anomalies(check, sample_limit)
+            })
+            .await
+    }
+
+    async fn find_negative_balances(
+        &self,
+        sample_limit: usize,
+    ) -> TransferRepoResult<IntegrityIssue<NegativeBalance>> {
+        self.resilience
+            .call(Operation::Idempotent, || {
+                self.inner.find_negative_balances(sample_limit)
+            })
+            .await
+    }
+
+    async fn transfer_totals(&self, range: &TimeRange) -> TransferRepoResult<TransferTotals> {
+        self.resilience
+            .call(Operation::Idempotent, || self.inner.transfer_totals(range))
+            .await
+    }
+
+    async fn transfer_count(&self) -> TransferRepoResult<u64> {
+        self.resilience
+            .call(Operation::Idempotent, || self.inner.transfer_count())
+            .await
+    }
+
+    async fn transfers_checksum(&self) -> TransferRepoResult<u64> {
+        self.resilience
+            .call(Operation::Idempotent, || self.inner.transfers_checksum())
+            .await
+    }
+
+    async fn daily_active_addresses(
+        &self,
+        range: &TimeRange,
+    ) -> TransferRepoResult<Vec<DailyActivity>> {
+        self.resilience
+            .call(Operation::Idempotent, || {
+                self.inner.daily_active_addresses(range)
+            })
+            .await
+    }
+
+    async fn candles(
+        &self,
+        interval_secs: u64,
+        range: &TimeRange,
+    ) -> TransferRepoResult<Vec<Candle>> {
+        self.resilience
+            .call(Operation::Idempotent, || {
+                self.inner.candles(interval_secs, range)
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        domain::repositories::{
+            errors::TransferRepoError, transfer_repo::MockTransferRepoAbstract,
+        },
+        infrastructure::resilience::{CircuitBreaker, RetryPolicy},
+    };
+
+    fn resilience(retry_inserts: bool) -> Resilience {
+        Resilience::new(
+            RetryPolicy {
+                max_attempts: 3,
+                base_delay: Duration::ZERO,
+                max_delay: Duration::ZERO,
+                retry_inserts,
+                attempt_timeout: None,
+            },
+            CircuitBreaker::new(10, Duration::from_secs(60)),
+        )
+    }
+
+    #[actix_web::test]
+    async fn test_reads_are_retried() {
+        let mut mock_repo = MockTransferRepoAbstract::new();
+        let mut sequence = mockall::Sequence::new();
+        mock_repo
+            .expect_calculate_user_stats()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|| {
+                Err(TransferRepoError::DatabaseConnectionError(
+                    "refused".to_string(),
+                ))
+            });
+        mock_repo
+            .expect_calculate_user_stats()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|| Ok(vec![]));
+
+        let repo = ResilientTransferRepo::new(mock_repo, resilience(false));
+
+        assert!(repo.calculate_user_stats().await.is_ok());
+    }
+
+    #[actix_web::test]
+    async fn test_inserts_follow_retry_inserts() {
+        let mut mock_repo = MockTransferRepoAbstract::new();
+        mock_repo.expect_save_all().times(1).returning(|_| {
+            Err(TransferRepoError::DatabaseConnectionError(
+                "reset".to_string(),
+            ))
+        });
+        let repo = ResilientTransferRepo::new(mock_repo, resilience(false));
+        assert!(repo.save_all(&[]).await.is_err());
+
+        let mut mock_repo = MockTransferRepoAbstract::new();
+        mock_repo.expect_save_all().times(3).returning(|_| {
+            Err(TransferRepoError::DatabaseConnectionError(
+                "reset".to_string(),
+            ))
+        });
+        let repo = ResilientTransferRepo::new(mock_repo, resilience(true));
+        assert!(repo.save_all(&[]).await.is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_streamed_stats_are_recorded_by_the_breaker() {
+        let mut mock_repo = MockTransferRepoAbstract::new();
+        mock_repo.expect_stream_user_stats().times(1).returning(|| {
+            stream::once(async {
+                Err(TransferRepoError::DatabaseConnectionError(
+                    "reset".to_string(),
+                ))
+            })
+            .boxed()
+        });
+        let resilience = Resilience::new(
+            RetryPolicy::default(),
+            CircuitBreaker::new(1, Duration::from_secs(60)),
+        );
+        let repo = ResilientTransferRepo::new(mock_repo, resilience);
+
+        let rows: Vec<_> = repo.stream_user_stats().collect().await;
+
+        assert_eq!(rows.len(), 1);
+        assert!(repo.resilience.breaker.is_open());
+    }
+
+    #[actix_web::test]
+    async fn test_bad_requests_are_not_retried() {
+        let mut mock_repo = MockTransferRepoAbstract::new();
+        mock_repo
+            .expect_save_all()
+            .times(1)
+            .returning(|_| Err(TransferRepoError::BadRequest("bad".to_string())));
+
+        let repo = ResilientTransferRepo::new(mock_repo, resilience(true));
+
+        assert!(matches!(
+            repo.save_all(&[]).await,
+            Err(TransferRepoError::BadRequest(_))
+        ));
+    }
+}
@@ -1,147 +1,922 @@
+use std::sync::{Arc, RwLock};
+
 use async_trait::async_trait;
-use clickhouse::Client;
+use clickhouse::{Client, Row, insert::Insert, query::Query};
+use futures::{
+    StreamExt,
+    stream::{self, FuturesUnordered},
+};
+use serde::Serialize;
 
-use crate::domain::{
-    entities::{transfer::Transfer, user_stats::UserStats},
-    repositories::{
-        errors::TransferRepoError,
-        transfer_repo::{TransferRepoAbstract, TransferRepoResult},
+use crate::{
+    domain::{
+        entities::{
+            candle::Candle,
+            chain::ChainTransfer,
+            cluster::AddressCluster,
+            counterparty::{Counterparty, CounterpartySort},
+            graph::{GraphEdge, GraphFilter},
+            holder::AddressBalance,
+            insert_report::{ChunkReport, InsertReport},
+            integrity::{IntegrityIssue, NegativeBalance, TransferCheck},
+            summary::{DailyActivity, TransferTotals},
+            time_range::TimeRange,
+            transfer::Transfer,
+            user_stats::UserStats,
+        },
+        repositories::{
+            errors::TransferRepoError,
+            transfer_repo::{TransferRepoAbstract, TransferRepoResult, UserStatsStream},
+        },
+        services::stream_service::TransferFeed,
+        validation::TransferValidator,
+    },
+    infrastructure::clickhouse::{
+        batching::{Chunk, InsertConfig, chunk_transfers},
+        schema::{Schema, TableEngine, TableSpec},
+        settings::QuerySettings,
     },
+    infrastructure::repositories::price_repo::PRICES_TABLE,
+};
+
+/// Insert blocks remembered per table. A block identical to one of them is
+/// dropped, which makes retried inserts safe but also stores a legitimately
+/// repeated batch only once.
+const DEDUPLICATION_WINDOW: u64 = 1000;
+
+pub(crate) const TRANSFERS_TABLE: TableSpec = TableSpec {
+    name: "transfers",
+    columns: r#"
+        ts UInt64,
+        from String,
+        to String,
+        amount Float64,
+        usd_price Float64,
+        source LowCardinality(String) DEFAULT '',
+        block_number UInt64 DEFAULT 0,
+        log_index UInt64 DEFAULT 0
+    "#,
+    engine: TableEngine::MergeTree,
+    order_by: "ts",
+    // Deterministic, so a retried insert splits into the same blocks and
+    // is deduplicated on every shard.
+    sharding_key: "cityHash64(ts, `from`, `to`)",
+    settings: Some("non_replicated_deduplication_window = 1000"),
 };
 
+/// Where a row came from, added after the table was first released. Rows
+/// decoded from chain logs carry their block and log so a reorg can delete
+/// exactly them; other rows keep the defaults.
+const PROVENANCE_COLUMNS: [&str; 3] = [
+    "source LowCardinality(String) DEFAULT ''",
+    "block_number UInt64 DEFAULT 0",
+    "log_index UInt64 DEFAULT 0",
+];
+
+/// `source` of the rows inserted by EVM ingestion.
+pub(crate) const CHAIN_SOURCE: &str = "evm";
+
+/// A `transfers` row with its provenance.
+#[derive(Serialize, Row)]
+struct ChainTransferRow<'a> {
+    ts: u64,
+    from: &'a str,
+    to: &'a str,
+    amount: f64,
+    usd_price: f64,
+    source: &'a str,
+    block_number: u64,
+    log_index: u64,
+}
+
+impl<'a> From<&'a ChainTransfer> for ChainTransferRow<'a> {
+    fn from(transfer: &'a ChainTransfer) -> Self {
+        Self {
+            ts: transfer.ts,
+            from: &transfer.from,
+            to: &transfer.to,
+            amount: transfer.amount,
+            usd_price: transfer.usd_price,
+            source: CHAIN_SOURCE,
+            block_number: transfer.block_number,
+            log_index: transfer.log_index,
+        }
+    }
+}
+
 pub struct ClickHouseTransferRepo {
     client: Client,
+    schema: Schema,
+    transfers: String,
+    prices: String,
+    validator: TransferValidator,
+    insert_config: InsertConfig,
+    stats_settings: Arc<RwLock<QuerySettings>>,
+    feed: Option<TransferFeed>,
 }
 
 impl ClickHouseTransferRepo {
     pub fn new(client: Client) -> Self {
-        Self { client }
+        Self::with_schema(client, Schema::default())
+    }
+
+    pub fn with_schema(client: Client, schema: Schema) -> Self {
+        Self {
+            client,
+            transfers: schema.table(TRANSFERS_TABLE.name),
+            prices: schema.table(PRICES_TABLE.name),
+            schema,
+            validator: TransferValidator::default(),
+            insert_config: InsertConfig::default(),
+            stats_settings: Arc::default(),
+            feed: None,
+        }
+    }
+
+    pub fn with_validator(mut self, validator: TransferValidator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    /// Guardrails for the full-table stats queries, read each time one is
+    /// built so they can be changed while the repo is in use.
+    pub fn with_stats_settings(mut self, stats_settings: Arc<RwLock<QuerySettings>>) -> Self {
+        self.stats_settings = stats_settings;
+        self
+    }
+
+    fn stats_query(&self, sql: &str) -> Query {
+        self.stats_settings
+            .read()
+            .unwrap()
+            .apply(self.client.query(sql))
+    }
+
+    pub fn with_insert_config(mut self, insert_config: InsertConfig) -> Self {
+        self.insert_config = insert_config;
+        self
+    }
+
+    /// Publishes every batch once it is fully inserted.
+    pub fn with_feed(mut self, feed: TransferFeed) -> Self {
+        self.feed = Some(feed);
+        self
+    }
+
+    fn insert<T: Row>(&self) -> TransferRepoResult<Insert<T>> {
+        let mut insert = self
+            .client
+            .insert(&self.transfers)?
+            .with_timeouts(self.insert_config.timeout, self.insert_config.timeout);
+        if self.schema.is_clustered() {
+            // Report rows once they reached the shards, not the local queue.
+            insert = insert.with_option("insert_distributed_sync", "1");
+        }
+        if self.insert_config.async_insert {
+            insert = insert
+                .with_option("async_insert", "1")
+                .with_option("wait_for_async_insert", "1")
+                .with_option("async_insert_deduplicate", "1");
+        }
+        Ok(insert)
+    }
+
+    /// Inserts one chunk; with `origin`, the chain rows at the chunk's
+    /// `start` provide the provenance columns and the chunk is deduplicated
+    /// by a token of its own.
+    async fn insert_chunk(
+        &self,
+        chunk: Chunk<'_>,
+        start: usize,
+        origin: Option<ChainOrigin<'_>>,
+    ) -> TransferRepoResult<ChunkReport> {
+        match origin {
+            None => {
+                let mut insert = self.insert::<Transfer>()?;
+                for transfer in chunk.rows {
+                    insert.write(transfer).await?;
+                }
+                insert.end().await?;
+            }
+            Some(origin) => {
+                let mut insert = self.insert::<ChainTransferRow>()?.with_option(
+                    "insert_deduplication_token",
+                    format!("{}-{}", origin.dedup_token, chunk.index),
+                );
+                for transfer in &origin.transfers[start..start + chunk.rows.len()] {
+                    insert.write(&transfer.into()).await?;
+                }
+                insert.end().await?;
+            }
+        }
+
+        Ok(ChunkReport {
+            index: chunk.index,
+            rows: chunk.rows.len(),
+            bytes: chunk.bytes,
+        })
+    }
+
+    async fn insert_all(
+        &self,
+        transfers: &[Transfer],
+        origin: Option<ChainOrigin<'_>>,
+    ) -> TransferRepoResult<InsertReport> {
+        if transfers.is_empty() {
+            return Ok(InsertReport::default());
+        }
+
+        self.validator.validate_all(transfers)?;
+
+        // Once a chunk fails, chunks that haven't started are skipped rather
+        // than piling more load onto a struggling server.
+        let parallelism = self.insert_config.parallelism.max(1);
+        let mut pending = chunk_transfers(transfers, &self.insert_config).into_iter();
+        let mut start = 0;
+        let mut in_flight = FuturesUnordered::new();
+        let mut report = InsertReport::default();
+        let mut first_error = None;
+        loop {
+            while first_error.is_none() && in_flight.len() < parallelism {
+                match pending.next() {
+                    Some(chunk) => {
+                        let rows = chunk.rows.len();
+                        in_flight.push(self.insert_chunk(chunk, start, origin));
+                        start += rows;
+                    }
+                    None => break,
+                }
+            }
+            match in_flight.next().await {
+                Some(Ok(chunk)) => report.chunks.push(chunk),
+                Some(Err(error)) => {
+                    first_error.get_or_insert(error);
+                }
+                None => break,
+            }
+        }
+        report.chunks.sort_by_key(|chunk| chunk.index);
+
+        match first_error {
+            None => {
+                if let Some(feed) = &self.feed {
+                    feed.publish(transfers);
+                }
+                Ok(report)
+            }
+            Some(error) if report.chunks.is_empty() => Err(error),
+            Some(error) => Err(TransferRepoError::PartialInsert {
+                report,
+                source: Box::new(error),
+            }),
+        }
+    }
+
+    fn user_stats_for_query(&self, addresses: &[String]) -> Query {
+        // Every transfer of the addresses is kept, so their rows are
+        // complete; the counterparties' rows are not and are dropped.
+        let source = format!(
+            "(SELECT * FROM {} WHERE has(?, `from`) OR has(?, `to`))",
+            self.transfers
+        );
+        let query = user_stats_query(&source, "to", "from", "");
+
+        // `source` has two placeholders and is used on both sides of the query.
+        let mut query = self.stats_query(&query);
+        for _ in 0..4 {
+            query = query.bind(addresses);
+        }
+        query
     }
 
     pub async fn create_table(&self) -> TransferRepoResult<()> {
-        let query = r#"
-            CREATE TABLE IF NOT EXISTS transfers (
-                ts UInt64,
-                from String,
-                to String,
-                amount Float64,
-                usd_price Float64
-            ) ENGINE = MergeTree()
-            ORDER BY ts
-        "#;
+        for statement in self.schema.create_table(&TRANSFERS_TABLE) {
+            self.client.query(&statement).execute().await?;
+        }
 
-        self.client
-            .query(query)
-            .execute()
-            .await
-            .map_err(|e| TransferRepoError::QueryError(e.to_string()))?;
+        // Tables created before inserts were retried lack the setting.
+        // Replicated tables deduplicate on their own. The `ALTER` is only
+        // issued when the setting differs, so restarts don't rewrite the
+        // table metadata.
+        if !self.schema.is_clustered() {
+            let window = self
+                .client
+                .query(
+                    "SELECT toUInt64OrZero(extract(engine_full, \
+                     'non_replicated_deduplication_window = ([0-9]+)')) \
+                     FROM system.tables WHERE database = currentDatabase() AND name = ?",
+                )
+                .bind(&self.transfers)
+                .fetch_optional::<u64>()
+                .await?;
+            if window != Some(DEDUPLICATION_WINDOW) {
+                self.client
+                    .query(&format!(
+                        "ALTER TABLE {} MODIFY SETTING non_replicated_deduplication_window = {}",
+                        self.transfers, DEDUPLICATION_WINDOW
+                    ))
+                    .execute()
+                    .await?;
+            }
+        }
+
+        // Tables created before chain ingestion lack the provenance columns.
+        let columns = self
+            .client
+            .query(
+                "SELECT count() FROM system.columns \
+                 WHERE database = currentDatabase() AND table = ? AND name = 'log_index'",
+            )
+            .bind(&self.transfers)
+            .fetch_one::<u64>()
+            .await?;
+        if columns == 0 {
+            let add = PROVENANCE_COLUMNS
+                .map(|column| format!("ADD COLUMN IF NOT EXISTS {column}"))
+                .join(", ");
+            let mut targets = vec![self.schema.mutation_target(TRANSFERS_TABLE.name)];
+            if self.schema.is_clustered() {
+                targets.push(format!("{}{}", self.transfers, self.schema.on_cluster()));
+            }
+            for target in targets {
+                self.client
+                    .query(&format!("ALTER TABLE {target} {add}"))
+                    .execute()
+                    .await?;
+            }
+        }
 
         Ok(())
     }
 }
 
+/// Provenance of the rows passed to `insert_all`, index for index.
+#[derive(Clone, Copy)]
+struct ChainOrigin<'a> {
+    transfers: &'a [ChainTransfer],
+    dedup_token: &'a str,
+}
+
 #[async_trait]
 impl TransferRepoAbstract for ClickHouseTransferRepo {
-    async fn save_all(&self, transfers: &[Transfer]) -> TransferRepoResult<()> {
-        if transfers.is_empty() {
-            return Ok(());
+    async fn save_all(&self, transfers: &[Transfer]) -> TransferRepoResult<InsertReport> {
+        self.insert_all(transfers, None).await
+    }
+
+    async fn save_chain_transfers(
+        &self,
+        transfers: &[ChainTransfer],
+        dedup_token: &str,
+    ) -> TransferRepoResult<InsertReport> {
+        let rows: Vec<Transfer> = transfers.iter().map(ChainTransfer::to_transfer).collect();
+        let origin = ChainOrigin {
+            transfers,
+            dedup_token,
+        };
+        self.insert_all(&rows, Some(origin)).await
+    }
+
+    async fn calculate_user_stats(&self) -> TransferRepoResult<Vec<UserStats>> {
+        let query = user_stats_query(&self.transfers, "to", "from", "");
+
+        let user_stats = self.stats_query(&query).fetch_all::<UserStats>().await?;
+
+        Ok(user_stats)
+    }
+
+    fn stream_user_stats(&self) -> UserStatsStream {
+        let query = user_stats_query(&self.transfers, "to", "from", "");
+
+        let cursor = match self.stats_query(&query).fetch::<UserStats>() {
+            Ok(cursor) => cursor,
+            Err(error) => return stream::once(async { Err(error.into()) }).boxed(),
+        };
+
+        stream::try_unfold(cursor, |mut cursor| async move {
+            let row = cursor.next().await?;
+            Ok(row.map(|row| (row, cursor)))
+        })
+        .boxed()
+    }
+
+    async fn user_stats_for(&self, addresses: &[String]) -> TransferRepoResult<Vec<UserStats>> {
+        if addresses.is_empty() {
+            return Ok(Vec::new());
         }
 
-        let mut insert = self
-            .client
-            .insert("transfers")
-            .map_err(|e| TransferRepoError::DatabaseConnectionError(e.to_string()))?;
+        let mut user_stats = self
+            .user_stats_for_query(addresses)
+            .fetch_all::<UserStats>()
+            .await?;
+        user_stats.retain(|stats| addresses.contains(&stats.address));
+
+        Ok(user_stats)
+    }
 
-        for transfer in transfers {
-            insert
-                .write(transfer)
-                .await
-                .map_err(|e| TransferRepoError::QueryError(e.to_string()))?;
+    async fn calculate_cluster_stats(
+        &self,
+        clusters: &[AddressCluster],
+    ) -> TransferRepoResult<Vec<UserStats>> {
+        if clusters.is_empty() {
+            return self.calculate_user_stats().await;
         }
 
-        insert
-            .end()
-            .await
-            .map_err(|e| TransferRepoError::QueryError(e.to_string()))?;
+        let query = user_stats_query(
+            &self.transfers,
+            "transform(to, ?, ?, to)",
+            "transform(from, ?, ?, from)",
+            "WHERE transform(from, ?, ?, from) != transform(to, ?, ?, to)",
+        );
 
-        Ok(())
+        let addresses: Vec<&str> = clusters.iter().map(|c| c.address.as_str()).collect();
+        let cluster_ids: Vec<&str> = clusters.iter().map(|c| c.cluster_id.as_str()).collect();
+
+        // One (addresses, cluster_ids) pair per `transform` in the query.
+        let mut query = self.stats_query(&query);
+        for _ in 0..6 {
+            query = query.bind(&addresses).bind(&cluster_ids);
+        }
+
+        let cluster_stats = query.fetch_all::<UserStats>().await?;
+
+        Ok(cluster_stats)
     }
 
-    async fn calculate_user_stats(&self) -> TransferRepoResult<Vec<UserStats>> {
-        let query = r#"
-            WITH
-            address_operations AS (
+    async fn calculate_user_stats_with_prices(
+        &self,
+        token: &str,
+    ) -> TransferRepoResult<Vec<UserStats>> {
+        // Each transfer takes the latest oracle price at or before its `ts`;
+        // transfers older than the first price keep their own `usd_price`
+        // rather than the join's default of 0.
+        let source = format!(
+            r#"(
+                SELECT t.ts as ts, t.`from` as `from`, t.`to` as `to`, t.amount as amount,
+                    if(p.priced, p.price, t.usd_price) as usd_price
+                FROM (SELECT ts, `from`, `to`, amount, usd_price, ? as token FROM {transfers}) as t
+                ASOF LEFT JOIN (SELECT token, ts, price, true as priced FROM {prices} FINAL WHERE token = ?) as p
+                ON t.token = p.token AND t.ts >= p.ts
+            )"#,
+            transfers = self.transfers,
+            prices = self.prices
+        );
+        let query = user_stats_query(&source, "to", "from", "");
+
+        // `source` has two placeholders and is used on both sides of the query.
+        let mut query = self.stats_query(&query);
+        for _ in 0..4 {
+            query = query.bind(token);
+        }
+
+        let user_stats = query.fetch_all::<UserStats>().await?;
+
+        Ok(user_stats)
+    }
+
+    async fn top_counterparties(
+        &self,
+        address: &str,
+        sort: CounterpartySort,
+        limit: usize,
+    ) -> TransferRepoResult<Vec<Counterparty>> {
+        let order_by = match sort {
+            CounterpartySort::Volume => "total_volume DESC, transfer_count DESC",
+            CounterpartySort::Count => "transfer_count DESC, total_volume DESC",
+        };
+
+        let query = format!(
+            r#"
+            SELECT
+                counterparty as address,
+                sum(sent) as sent_volume,
+                sum(received) as received_volume,
+                sent_volume + received_volume as total_volume,
+                count() as transfer_count
+            FROM (
                 SELECT
-                    to as address,
-                    ts,
-                    amount,
-                    usd_price,
-                    'buy' as operation_type
-                FROM transfers
+                    to as counterparty,
+                    amount as sent,
+                    toFloat64(0) as received
+                FROM {transfers}
+                WHERE from = ?
 
                 UNION ALL
 
                 SELECT
-                    from as address,
-                    ts,
-                    -amount as amount,
-                    usd_price,
-                    'sell' as operation_type
-                FROM transfers
-                ORDER BY address, ts
-            ),
-
-            balance_calculations AS (
+                    from as counterparty,
+                    toFloat64(0) as sent,
+                    amount as received
+                FROM {transfers}
+                WHERE to = ?
+            )
+            GROUP BY counterparty
+            ORDER BY {order_by}
+            LIMIT ?
+        "#,
+            transfers = self.transfers
+        );
+
+        let counterparties = self
+            .client
+            .query(&query)
+            .bind(address)
+            .bind(address)
+            .bind(limit as u64)
+            .fetch_all::<Counterparty>()
+            .await?;
+
+        Ok(counterparties)
+    }
+
+    async fn transfer_graph(&self, filter: &GraphFilter) -> TransferRepoResult<Vec<GraphEdge>> {
+        let query = format!(
+            r#"
+            SELECT
+                `from`,
+                `to`,
+                sum(amount) as total_volume,
+                count() as transfer_count
+            FROM {transfers}
+            WHERE ts >= ? AND ts <= ?
+            GROUP BY `from`, `to`
+            HAVING total_volume >= ?
+            ORDER BY total_volume DESC
+            {limit}
+        "#,
+            transfers = self.transfers,
+            limit = if filter.limit.is_some() {
+                "LIMIT ?"
+            } else {
+                ""
+            }
+        );
+
+        let mut query = self
+            .client
+            .query(&query)
+            .bind(filter.from_ts.unwrap_or(0))
+            .bind(filter.to_ts.unwrap_or(u64::MAX))
+            .bind(filter.min_volume.unwrap_or(0.0));
+        if let Some(limit) = filter.limit {
+            query = query.bind(limit as u64);
+        }
+        let edges = query.fetch_all::<GraphEdge>().await?;
+
+        Ok(edges)
+    }
+
+    async fn get_transfers(&self, range: &TimeRange) -> TransferRepoResult<Vec<Transfer>> {
+        let query = format!(
+            r#"
+            SELECT ts, `from`, `to`, amount, usd_price
+            FROM {transfers}
+            WHERE ts >= ? AND ts <= ?
+            ORDER BY ts
+        "#,
+            transfers = self.transfers
+        );
+
+        let transfers = self
+            .client
+            .query(&query)
+            .bind(range.start())
+            .bind(range.end())
+            .fetch_all::<Transfer>()
+            .await?;
+
+        Ok(transfers)
+    }
+
+    async fn candles(
+        &self,
+        interval_secs: u64,
+        range: &TimeRange,
+    ) -> TransferRepoResult<Vec<Candle>> {
+        let query = format!(
+            r#"
+            SELECT
+                intDiv(ts, ?) * ? as bucket_start,
+                argMin(usd_price, ts) as open,
+                max(usd_price) as high,
+                min(usd_price) as low,
+                argMax(usd_price, ts) as close,
+                sum(amount) as volume,
+                count() as trade_count
+            FROM {transfers}
+            WHERE ts >= ? AND ts <= ?
+            GROUP BY bucket_start
+            ORDER BY bucket_start
+        "#,
+            transfers = self.transfers
+        );
+
+        let candles = self
+            .client
+            .query(&query)
+            .bind(interval_secs)
+            .bind(interval_secs)
+            .bind(range.start())
+            .bind(range.end())
+            .fetch_all::<Candle>()
+            .await?;
+
+        Ok(candles)
+    }
+
+    async fn balances(&self, at: Option<u64>) -> TransferRepoResult<Vec<AddressBalance>> {
+        let query = format!(
+            r#"
+            SELECT
+                address,
+                sum(delta) as balance
+            FROM (
+                SELECT `to` as address, amount as delta FROM {transfers} WHERE ts <= ?
+                UNION ALL
+                SELECT `from` as address, -amount as delta FROM {transfers} WHERE ts <= ?
+            )
+            GROUP BY address
+        "#,
+            transfers = self.transfers
+        );
+
+        let at = at.unwrap_or(u64::MAX);
+        let balances = self
+            .client
+            .query(&query)
+            .bind(at)
+            .bind(at)
+            .fetch_all::<AddressBalance>()
+            .await?;
+
+        Ok(balances)
+    }
+
+    async fn find_anomalies(
+        &self,
+        check: TransferCheck,
+        sample_limit: usize,
+    ) -> TransferRepoResult<IntegrityIssue<Transfer>> {
+        let (condition, after) = match check {
+            TransferCheck::SelfTransfer => ("`from` = `to`", None),
+            TransferCheck::InvalidAmount => ("NOT isFinite(amount) OR amount <= 0", None),
+            TransferCheck::InvalidPrice => ("NOT isFinite(usd_price) OR usd_price <= 0", None),
+            TransferCheck::FutureTimestamp { after } => ("ts > ?", Some(after)),
+        };
+
+        let transfers = &self.transfers;
+        let count_query = format!("SELECT count() FROM {transfers} WHERE {condition}");
+        let sample_query = format!(
+            "SELECT ts, `from`, `to`, amount, usd_price FROM {transfers} WHERE {condition} ORDER BY ts LIMIT ?"
+        );
+
+        let mut count = self.client.query(&count_query);
+        let mut samples = self.client.query(&sample_query);
+        if let Some(after) = after {
+            count = count.bind(after);
+            samples = samples.bind(after);
+        }
+
+        let count = count.fetch_one::<u64>().await?;
+        let samples = samples
+            .bind(sample_limit as u64)
+            .fetch_all::<Transfer>()
+            .await?;
+
+        Ok(IntegrityIssue { count, samples })
+    }
+
+    async fn find_negative_balances(
+        &self,
+        sample_limit: usize,
+    ) -> TransferRepoResult<IntegrityIssue<NegativeBalance>> {
+        // Incoming transfers are applied before outgoing ones with the same
+        // `ts`, so same-second round trips are not reported.
+        let negative = format!(
+            r#"
+            SELECT
+                address,
+                min(running_balance) as min_balance,
+                minIf(ts, running_balance < 0) as first_negative_ts
+            FROM (
                 SELECT
                     address,
                     ts,
-                    amount,
-                    usd_price,
-                    operation_type,
                     sum(amount) OVER (
                         PARTITION BY address
-                        ORDER BY ts
+                        ORDER BY ts, amount DESC
                         ROWS BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW
                     ) as running_balance
-                FROM address_operations
-            ),
+                FROM (
+                    SELECT `to` as address, ts, amount FROM {transfers}
+                    UNION ALL
+                    SELECT `from` as address, ts, -amount as amount FROM {transfers}
+                )
+            )
+            GROUP BY address
+            HAVING min_balance < 0
+        "#,
+            transfers = self.transfers
+        );
 
-            address_stats AS (
-                SELECT
-                    address,
-                    sum(abs(amount)) as total_volume,
+        let count = self
+            .client
+            .query(&format!("SELECT count() FROM ({negative})"))
+            .fetch_one::<u64>()
+            .await?;
+        let samples = self
+            .client
+            .query(&format!("{negative} ORDER BY min_balance LIMIT ?"))
+            .bind(sample_limit as u64)
+            .fetch_all::<NegativeBalance>()
+            .await?;
+
+        Ok(IntegrityIssue { count, samples })
+    }
 
-                    sum(CASE WHEN amount > 0 THEN amount ELSE 0 END) as buy_volume,
-                    sum(CASE WHEN amount > 0 THEN amount * usd_price ELSE 0 END) as buy_value,
+    async fn transfer_totals(&self, range: &TimeRange) -> TransferRepoResult<TransferTotals> {
+        let query = format!(
+            r#"
+            SELECT
+                sum(amount) as total_volume,
+                sum(amount * usd_price) as total_usd_volume,
+                count() as transfer_count,
+                uniqExact(`from`) as unique_senders,
+                uniqExact(`to`) as unique_receivers,
+                if(total_volume > 0, total_usd_volume / total_volume, 0) as vwap,
+                ifNotFinite(quantileExact(0.5)(amount), 0) as median_transfer_size,
+                ifNotFinite(quantileExact(0.25)(amount), 0) as p25_transfer_size,
+                ifNotFinite(quantileExact(0.75)(amount), 0) as p75_transfer_size,
+                ifNotFinite(quantileExact(0.9)(amount), 0) as p90_transfer_size,
+                ifNotFinite(quantileExact(0.99)(amount), 0) as p99_transfer_size
+            FROM {transfers}
+            WHERE ts >= ? AND ts <= ?
+        "#,
+            transfers = self.transfers
+        );
 
-                    sum(CASE WHEN amount < 0 THEN -amount ELSE 0 END) as sell_volume,
-                    sum(CASE WHEN amount < 0 THEN -amount * usd_price ELSE 0 END) as sell_value,
+        let totals = self
+            .client
+            .query(&query)
+            .bind(range.start())
+            .bind(range.end())
+            .fetch_one::<TransferTotals>()
+            .await?;
 
-                    max(running_balance) as max_balance
+        Ok(totals)
+    }
 
-                FROM balance_calculations
-                GROUP BY address
+    async fn transfer_count(&self) -> TransferRepoResult<u64> {
+        let count = self
+            .client
+            .query(&format!("SELECT count() FROM {}", self.transfers))
+            .fetch_one::<u64>()
+            .await?;
+
+        Ok(count)
+    }
+
+    async fn transfers_checksum(&self) -> TransferRepoResult<u64> {
+        // Order-independent, and unlike XOR a duplicated row still counts.
+        let query = format!(
+            "SELECT cityHash64(count(), sum(cityHash64(ts, `from`, `to`, amount, usd_price))) FROM {}",
+            self.transfers
+        );
+
+        let checksum = self.client.query(&query).fetch_one::<u64>().await?;
+
+        Ok(checksum)
+    }
+
+    async fn daily_active_addresses(
+        &self,
+        range: &TimeRange,
+    ) -> TransferRepoResult<Vec<DailyActivity>> {
+        let query = format!(
+            r#"
+            SELECT
+                intDiv(ts, 86400) * 86400 as day,
+                uniqExact(address) as active_addresses
+            FROM (
+                SELECT ts, `from` as address FROM {transfers} WHERE ts >= ? AND ts <= ?
+                UNION ALL
+                SELECT ts, `to` as address FROM {transfers} WHERE ts >= ? AND ts <= ?
             )
+            GROUP BY day
+            ORDER BY day
+        "#,
+            transfers = self.transfers
+        );
 
+        let days = self
+            .client
+            .query(&query)
+            .bind(range.start())
+            .bind(range.end())
+            .bind(range.start())
+            .bind(range.end())
+            .fetch_all::<DailyActivity>()
+            .await?;
+
+        Ok(days)
+    }
+}
+
+/// Builds the per-address stats query over `source` (a table or subquery with
+/// the `transfers` columns). `to_key`/`from_key` choose what the receiving and
+/// sending side are grouped by, `filter` is applied to both.
+fn user_stats_query(source: &str, to_key: &str, from_key: &str, filter: &str) -> String {
+    format!(
+        r#"
+        WITH
+        address_operations AS (
+            SELECT
+                {to_key} as address,
+                ts,
+                amount,
+                usd_price,
+                'buy' as operation_type
+            FROM {source}
+            {filter}
+
+            UNION ALL
+
+            SELECT
+                {from_key} as address,
+                ts,
+                -amount as amount,
+                usd_price,
+                'sell' as operation_type
+            FROM {source}
+            {filter}
+            ORDER BY address, ts
+        ),
+
+        balance_calculations AS (
             SELECT
                 address,
-                total_volume,
-                CASE
-                    WHEN buy_volume > 0 THEN buy_value / buy_volume
-                    ELSE 0
-                END as avg_buy_price,
-                CASE
-                    WHEN sell_volume > 0 THEN sell_value / sell_volume
-                    ELSE 0
-                END as avg_sell_price,
-                GREATEST(max_balance, 0) as max_balance
-            FROM address_stats
-            WHERE total_volume > 0
-            ORDER BY total_volume DESC
-        "#;
+                ts,
+                amount,
+                usd_price,
+                operation_type,
+                sum(amount) OVER (
+                    PARTITION BY address
+                    ORDER BY ts
+                    ROWS BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW
+                ) as running_balance
+            FROM address_operations
+        ),
+
+        address_stats AS (
+            SELECT
+                address,
+                sum(abs(amount)) as total_volume,
 
-        let user_stats = self.client.query(query).fetch_all::<UserStats>().await?;
+                sum(CASE WHEN amount > 0 THEN amount ELSE 0 END) as buy_volume,
+                sum(CASE WHEN amount > 0 THEN amount * usd_price ELSE 0 END) as buy_value,
 
-        Ok(user_stats)
+                sum(CASE WHEN amount < 0 THEN -amount ELSE 0 END) as sell_volume,
+                sum(CASE WHEN amount < 0 THEN -amount * usd_price ELSE 0 END) as sell_value,
+
+                max(running_balance) as max_balance
+
+            FROM balance_calculations
+            GROUP BY address
+        )
+
+        SELECT
+            address,
+            total_volume,
+            CASE
+                WHEN buy_volume > 0 THEN buy_value / buy_volume
+                ELSE 0
+            END as avg_buy_price,
+            CASE
+                WHEN sell_volume > 0 THEN sell_value / sell_volume
+                ELSE 0
+            END as avg_sell_price,
+            GREATEST(max_balance, 0) as max_balance
+        FROM address_stats
+        WHERE total_volume > 0
+        ORDER BY total_volume DESC
+    "#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_stats_for_binds_every_placeholder() {
+        let repo = ClickHouseTransferRepo::new(Client::default());
+        let addresses = vec!["0xaaa".to_string()];
+
+        let sql = repo
+            .user_stats_for_query(&addresses)
+            .sql_display()
+            .to_string();
+
+        assert!(!sql.contains('?'), "{sql}");
+        assert_eq!(sql.matches("['0xaaa']").count(), 4);
     }
 }
@@ -0,0 +1,498 @@
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use actix_web::rt::time::{sleep, timeout};
+use futures::{StreamExt, stream::BoxStream};
+use rand::Rng;
+
+use crate::domain::repositories::errors::TransferRepoError;
+
+/// Whether a call may be repeated without side effects. Inserts are only
+/// retried when the policy allows it, see [`RetryPolicy::retry_inserts`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// Reads and `IF NOT EXISTS` DDL.
+    Idempotent,
+    /// Stats scans over the whole table. They are bounded on the server by
+    /// `max_execution_time` rather than by `attempt_timeout`, and a timed
+    /// out scan is not repeated while the first may still be running.
+    Stats,
+    Insert,
+}
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total attempts including the first one; `1` disables retries.
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Retry failed inserts. Only safe because the `transfers` table
+    /// deduplicates identical insert blocks.
+    pub retry_inserts: bool,
+    /// Limit on a single idempotent attempt. Inserts are bounded per chunk
+    /// instead, by `InsertConfig::timeout`.
+    pub attempt_timeout: Option<Duration>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(5),
+            retry_inserts: true,
+            attempt_timeout: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Exponential backoff capped at `max_delay`, with the upper half
+    /// jittered so that restarted replicas don't retry in lockstep.
+    pub fn delay_for(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)));
+        let capped = exponential.min(self.max_delay);
+        let half = capped / 2;
+        half + half.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+    }
+
+    fn allows(&self, operation: Operation, attempt: u32, error: &TransferRepoError) -> bool {
+        attempt < self.max_attempts
+            && error.is_retryable()
+            && match operation {
+                Operation::Idempotent => true,
+                Operation::Stats => !matches!(error, TransferRepoError::TimedOut(_)),
+                Operation::Insert => self.retry_inserts,
+            }
+    }
+
+    /// Runs `call` until it succeeds, fails with a non-retryable error or
+    /// runs out of attempts.
+    pub async fn run<T, F, Fut>(
+        &self,
+        operation: Operation,
+        mut call: F,
+    ) -> Result<T, TransferRepoError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, TransferRepoError>>,
+    {
+        self.run_until(operation, || self.timed(operation, call()), || false)
+            .await
+    }
+
+    async fn timed<T>(
+        &self,
+        operation: Operation,
+        attempt: impl Future<Output = Result<T, TransferRepoError>>,
+    ) -> Result<T, TransferRepoError> {
+        match (operation, self.attempt_timeout) {
+            (Operation::Idempotent, Some(limit)) => {
+                timeout(limit, attempt).await.unwrap_or_else(|_| {
+                    Err(TransferRepoError::TimedOut(format!(
+                        "no response from ClickHouse within {:?}",
+                        limit
+                    )))
+                })
+            }
+            _ => attempt.await,
+        }
+    }
+
+    /// Like [`RetryPolicy::run`], but gives up early once `stop` returns true.
+    async fn run_until<T, F, Fut>(
+        &self,
+        operation: Operation,
+        mut call: F,
+        stop: impl Fn() -> bool,
+    ) -> Result<T, TransferRepoError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, TransferRepoError>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match call().await {
+                Ok(value) => return Ok(value),
+                Err(error) if !stop() && self.allows(operation, attempt, &error) => {
+                    let delay = self.delay_for(attempt);
+                    eprintln!(
+                        "ClickHouse call failed (attempt {}/{}), retrying in {:?}: {}",
+                        attempt, self.max_attempts, delay, error
+                    );
+                    sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BreakerState {
+    Closed {
+        failures: u32,
+    },
+    Open {
+        since: Instant,
+    },
+    /// A single probe is in flight; `since` lets another probe through if
+    /// the first one was dropped without reporting back.
+    HalfOpen {
+        since: Instant,
+    },
+}
+
+/// Stops sending calls to ClickHouse after `failure_threshold` consecutive
+/// retryable failures, then lets a single probe through every `open_for`.
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    open_for: Duration,
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, open_for: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            open_for,
+            state: Mutex::new(BreakerState::Closed { failures: 0 }),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        matches!(
+            *self.state.lock().unwrap(),
+            BreakerState::Open { .. } | BreakerState::HalfOpen { .. }
+        )
+    }
+
+    /// Fails fast while the breaker is open; lets a probe through once
+    /// `open_for` has passed.
+    pub fn acquire(&self) -> Result<(), TransferRepoError> {
+        let mut state = self.state.lock().unwrap();
+        match *state {
+            BreakerState::Closed { .. } => Ok(()),
+            BreakerState::Open { since } | BreakerState::HalfOpen { since }
+                if since.elapsed() >= self.open_for =>
+            {
+                *state = BreakerState::HalfOpen {
+                    since: Instant::now(),
+                };
+                Ok(())
+            }
+            BreakerState::Open { .. } | BreakerState::HalfOpen { .. } => {
+                Err(TransferRepoError::DatabaseConnectionError(
+                    "circuit breaker is open, ClickHouse is considered down".to_string(),
+                ))
+            }
+        }
+    }
+
+    /// Passes `rows` through, recording their first error, or a success
+    /// once they end. A stream dropped midway records nothing.
+    pub fn record_stream<T: Send + 'static>(
+        self: Arc<Self>,
+        rows: BoxStream<'static, Result<T, TransferRepoError>>,
+    ) -> BoxStream<'static, Result<T, TransferRepoError>> {
+        futures::stream::unfold(Some((rows, self)), |state| async move {
+            let (mut rows, breaker) = state?;
+            match rows.next().await {
+                Some(Ok(row)) => Some((Ok(row), Some((rows, breaker)))),
+                Some(Err(error)) => {
+                    let result = Err(error);
+                    breaker.record(&result);
+                    Some((result, None))
+                }
+                None => {
+                    breaker.record(&Ok::<(), TransferRepoError>(()));
+                    None
+                }
+            }
+        })
+        .boxed()
+    }
+
+    fn record<T>(&self, result: &Result<T, TransferRepoError>) {
+        // Rejected before reaching ClickHouse, so it says nothing about
+        // its health; a probe slot taken for it frees up after `open_for`.
+        if result
+            .as_ref()
+            .is_err_and(|error| !error.reached_database())
+        {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        *state = match (result, *state) {
+            (Err(error), BreakerState::Closed { failures }) if error.is_retryable() => {
+                if failures + 1 >= self.failure_threshold {
+                    eprintln!("ClickHouse circuit breaker opened: {}", error);
+                    BreakerState::Open {
+                        since: Instant::now(),
+                    }
+                } else {
+                    BreakerState::Closed {
+                        failures: failures + 1,
+                    }
+                }
+            }
+            (Err(error), _) if error.is_retryable() => BreakerState::Open {
+                since: Instant::now(),
+            },
+            // Any other outcome means ClickHouse answered.
+            (_, BreakerState::Closed { .. }) => BreakerState::Closed { failures: 0 },
+            _ => {
+                eprintln!("ClickHouse circuit breaker closed");
+                BreakerState::Closed { failures: 0 }
+            }
+        };
+    }
+}
+
+/// Retry policy and circuit breaker applied together: every attempt asks
+/// the breaker first, and retries stop as soon as it opens.
+pub struct Resilience {
+    pub retry: RetryPolicy,
+    pub breaker: Arc<CircuitBreaker>,
+}
+
+impl Resilience {
+    pub fn new(retry: RetryPolicy, breaker: CircuitBreaker) -> Self {
+        Self {
+            retry,
+            breaker: Arc::new(breaker),
+        }
+    }
+
+    pub async fn call<T, F, Fut>(
+        &self,
+        operation: Operation,
+        mut call: F,
+    ) -> Result<T, TransferRepoError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, TransferRepoError>>,
+    {
+        self.retry
+            .run_until(
+                operation,
+                || {
+                    let permit = self.breaker.acquire();
+                    let attempt = permit.map(|_| self.retry.timed(operation, call()));
+                    async move {
+                        let result = attempt?.await;
+                        self.breaker.record(&result);
+                        result
+                    }
+                },
+                || self.breaker.is_open(),
+            )
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn no_delay(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            base_delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+            retry_inserts: true,
+            attempt_timeout: None,
+        }
+    }
+
+    fn unavailable() -> TransferRepoError {
+        TransferRepoError::DatabaseConnectionError("refused".to_string())
+    }
+
+    #[test]
+    fn test_delay_grows_and_is_capped() {
+        let policy = RetryPolicy {
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(300),
+            ..RetryPolicy::default()
+        };
+
+        let first = policy.delay_for(1);
+        assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+        let third = policy.delay_for(3);
+        assert!(third >= Duration::from_millis(150) && third <= Duration::from_millis(300));
+        assert!(policy.delay_for(30) <= Duration::from_millis(300));
+    }
+
+    #[actix_web::test]
+    async fn test_retries_retryable_errors_until_success() {
+        let calls = AtomicU32::new(0);
+
+        let result = no_delay(3)
+            .run(Operation::Idempotent, || async {
+                match calls.fetch_add(1, Ordering::SeqCst) {
+                    0 => Err(TransferRepoError::TimedOut("slow".to_string())),
+                    _ => Ok(42),
+                }
+            })
+            .await;
+
+        assert_eq!(result.unwrap(), 42);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[actix_web::test]
+    async fn test_does_not_retry_query_errors_or_disabled_inserts() {
+        let calls = AtomicU32::new(0);
+        let result: Result<(), _> = no_delay(3)
+            .run(Operation::Idempotent, || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(TransferRepoError::QueryError("syntax".to_string()))
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let calls = AtomicU32::new(0);
+        let policy = RetryPolicy {
+            retry_inserts: false,
+            ..no_delay(3)
+        };
+        let result: Result<(), _> = policy
+            .run(Operation::Insert, || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(unavailable())
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_stats_have_no_attempt_timeout_and_timeouts_are_final() {
+        let policy = RetryPolicy {
+            attempt_timeout: Some(Duration::from_millis(10)),
+            ..no_delay(3)
+        };
+        let result = policy
+            .run(Operation::Stats, || async {
+                sleep(Duration::from_millis(30)).await;
+                Ok(1)
+            })
+            .await;
+        assert_eq!(result.unwrap(), 1);
+
+        let calls = AtomicU32::new(0);
+        let result: Result<(), _> = no_delay(3)
+            .run(Operation::Stats, || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(TransferRepoError::TimedOut("slow".to_string()))
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_slow_attempts_time_out_and_trip_breaker() {
+        let retry = RetryPolicy {
+            attempt_timeout: Some(Duration::from_millis(10)),
+            ..no_delay(2)
+        };
+        let resilience = Resilience::new(retry, CircuitBreaker::new(2, Duration::from_secs(60)));
+
+        let result = resilience
+            .call(Operation::Idempotent, || async {
+                sleep(Duration::from_secs(5)).await;
+                Ok(())
+            })
+            .await;
+
+        assert!(matches!(result, Err(TransferRepoError::TimedOut(_))));
+        assert!(resilience.breaker.is_open());
+    }
+
+    #[actix_web::test]
+    async fn test_breaker_stops_retries_and_fails_fast() {
+        let resilience =
+            Resilience::new(no_delay(5), CircuitBreaker::new(2, Duration::from_secs(60)));
+        let calls = AtomicU32::new(0);
+        let failing = || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err::<(), _>(unavailable())
+        };
+
+        assert!(
+            resilience
+                .call(Operation::Idempotent, failing)
+                .await
+                .is_err()
+        );
+        assert!(resilience.breaker.is_open());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        let result = resilience.call(Operation::Idempotent, failing).await;
+        assert!(matches!(
+            result,
+            Err(TransferRepoError::DatabaseConnectionError(_))
+        ));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[actix_web::test]
+    async fn test_breaker_closes_after_successful_probe() {
+        let resilience = Resilience::new(no_delay(1), CircuitBreaker::new(1, Duration::ZERO));
+
+        let _ = resilience
+            .call(Operation::Idempotent, || async {
+                Err::<(), _>(unavailable())
+            })
+            .await;
+        assert!(resilience.breaker.is_open());
+
+        let result = resilience
+            .call(Operation::Idempotent, || async { Ok(1) })
+            .await;
+        assert_eq!(result.unwrap(), 1);
+        assert!(!resilience.breaker.is_open());
+    }
+
+    #[actix_web::test]
+    async fn test_non_retryable_errors_do_not_trip_breaker() {
+        let resilience =
+            Resilience::new(no_delay(1), CircuitBreaker::new(1, Duration::from_secs(60)));
+
+        let _ = resilience
+            .call(Operation::Idempotent, || async {
+                Err::<(), _>(TransferRepoError::QueryError("syntax".to_string()))
+            })
+            .await;
+
+        assert!(!resilience.breaker.is_open());
+    }
+
+    #[actix_web::test]
+    async fn test_errors_raised_before_the_request_leave_breaker_open() {
+        let resilience = Resilience::new(no_delay(1), CircuitBreaker::new(1, Duration::ZERO));
+        let _ = resilience
+            .call(Operation::Idempotent, || async {
+                Err::<(), _>(unavailable())
+            })
+            .await;
+
+        let _ = resilience
+            .call(Operation::Insert, || async {
+                Err::<(), _>(TransferRepoError::BadRequest("too large".to_string()))
+            })
+            .await;
+
+        assert!(resilience.breaker.is_open());
+    }
+}
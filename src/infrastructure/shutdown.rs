@@ -0,0 +1,187 @@
+use std::{
+    future::Future,
+    sync::{
+        Mutex, OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use anyhow::Result;
+use futures::future::BoxFuture;
+use tokio::{
+    signal::unix::{SignalKind, signal},
+    time::{Instant, timeout_at},
+};
+use tokio_util::{
+    sync::CancellationToken,
+    task::{TaskTracker, task_tracker::TrackedFuture},
+};
+
+type Flush = (&'static str, BoxFuture<'static, Result<()>>);
+
+/// Coordinates a graceful stop: readiness turns to failing, background jobs
+/// are cancelled, in-flight work gets until the deadline to finish and
+/// buffered writes are flushed last.
+pub struct Shutdown {
+    timeout: Duration,
+    draining: AtomicBool,
+    deadline: OnceLock<Instant>,
+    cancel: CancellationToken,
+    tasks: TaskTracker,
+    flushes: Mutex<Vec<Flush>>,
+}
+
+impl Shutdown {
+    /// `timeout` bounds the whole drain, counted from [`Shutdown::begin`].
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            draining: AtomicBool::new(false),
+            deadline: OnceLock::new(),
+            cancel: CancellationToken::new(),
+            tasks: TaskTracker::new(),
+            flushes: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    /// Cancelled when shutdown begins; loops should stop at their next wait.
+    pub fn token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+
+    /// Wraps `work` so [`Shutdown::drain`] waits for it.
+    pub fn track<F: Future>(&self, work: F) -> TrackedFuture<F> {
+        self.tasks.track_future(work)
+    }
+
+    /// Runs `flush` once in-flight work has drained.
+    pub fn on_drained(
+        &self,
+        name: &'static str,
+        flush: impl Future<Output = Result<()>> + Send + 'static,
+    ) {
+        self.flushes.lock().unwrap().push((name, Box::pin(flush)));
+    }
+
+    /// Fails readiness and cancels background jobs. Later calls do nothing.
+    pub fn begin(&self) {
+        if self.draining.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        self.deadline.get_or_init(|| Instant::now() + self.timeout);
+        self.cancel.cancel();
+    }
+
+    /// Waits for tracked work until the deadline, then runs the flushes.
+    /// Returns whether everything finished in time.
+    pub async fn drain(&self) -> bool {
+        self.begin();
+        let deadline = *self.deadline.get().expect("set by begin");
+
+        self.tasks.close();
+        let mut finished = timeout_at(deadline, self.tasks.wait()).await.is_ok();
+        if !finished {
+            log::warn!(
+                "Abandoning {} background tasks still running at the shutdown deadline",
+                self.tasks.len()
+            );
+        }
+
+        let flushes = std::mem::take(&mut *self.flushes.lock().unwrap());
+        for (name, flush) in flushes {
+            match timeout_at(deadline, flush).await {
+                Ok(Ok(())) => log::info!("Flushed {name}"),
+                Ok(Err(err)) => {
+                    finished = false;
+                    log::error!("Failed to flush {name}: {err:#}");
+                }
+                Err(_) => {
+                    finished = false;
+                    log::error!("Flushing {name} did not finish before the shutdown deadline");
+                }
+            }
+        }
+
+        finished
+    }
+}
+
+/// Resolves on the first `SIGTERM` or `SIGINT`.
+pub async fn termination() {
+    let mut terminate = match signal(SignalKind::terminate()) {
+        Ok(terminate) => Some(terminate),
+        Err(err) => {
+            log::warn!("Cannot listen for SIGTERM, only SIGINT stops the server: {err}");
+            None
+        }
+    };
+
+    tokio::select! {
+        Some(()) = async { terminate.as_mut()?.recv().await } => {}
+        _ = tokio::signal::ctrl_c() => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_begin_fails_readiness_and_cancels_jobs() {
+        let shutdown = Shutdown::new(Duration::from_secs(1));
+        let token = shutdown.token();
+        assert!(!shutdown.is_draining());
+
+        shutdown.begin();
+
+        assert!(shutdown.is_draining());
+        assert!(token.is_cancelled());
+    }
+
+    #[actix_web::test]
+    async fn test_drain_waits_for_tracked_work_then_flushes() {
+        let shutdown = Arc::new(Shutdown::new(Duration::from_secs(5)));
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let work_order = order.clone();
+        actix_web::rt::spawn(shutdown.track(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            work_order.lock().unwrap().push("work");
+        }));
+        let flush_order = order.clone();
+        shutdown.on_drained("buffer", async move {
+            flush_order.lock().unwrap().push("flush");
+            Ok(())
+        });
+
+        assert!(shutdown.drain().await);
+        assert_eq!(*order.lock().unwrap(), vec!["work", "flush"]);
+    }
+
+    #[actix_web::test]
+    async fn test_drain_gives_up_at_the_deadline() {
+        let shutdown = Shutdown::new(Duration::from_millis(20));
+        let flushed = Arc::new(AtomicUsize::new(0));
+
+        actix_web::rt::spawn(shutdown.track(tokio::time::sleep(Duration::from_secs(60))));
+        let counter = flushed.clone();
+        shutdown.on_drained("buffer", async move {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        });
+
+        assert!(!shutdown.drain().await);
+        // Flushes still run: whatever they hold would be lost otherwise.
+        assert_eq!(flushed.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,878 @@
+//! Append-only on-disk buffer for accepted transfers.
+//!
+//! The buffer directory holds numbered segment files and a `cursor` file.
+//! Each record in a segment is one accepted batch: a little-endian `u32`
+//! payload length, the payload's CRC-32 and the batch as a JSON array. The
+//! cursor names the first record not yet inserted; segments before it are
+//! deleted. A record torn by a crash fails its checksum on the next start
+//! and is truncated away, since it was never acknowledged. Only the last
+//! segment can hold such a record: damage to an earlier one means
+//! acknowledged batches are lost, and opening the buffer fails instead.
+//! A `lock` file keeps a second process from opening the same directory.
+
+use std::{
+    collections::VecDeque,
+    fs::{self, File, OpenOptions, TryLockError},
+    io::{self, BufReader, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::Serialize;
+use tokio::sync::Notify;
+
+use crate::{
+    config::BufferSettings,
+    domain::{
+        entities::transfer::Transfer,
+        repositories::{
+            errors::TransferRepoError,
+            transfer_buffer::{TransferBufferAbstract, TransferBufferResult},
+            transfer_repo::TransferRepoAbstract,
+        },
+        validation::TransferValidator,
+    },
+};
+
+use super::shutdown::Shutdown;
+
+const HEADER_BYTES: u64 = 8;
+/// Limit for `max_bytes` and `segment_bytes`, so that a record's length
+/// always fits its `u32` header.
+pub const MAX_BUFFER_BYTES: u64 = u32::MAX as u64;
+const SEGMENT_EXTENSION: &str = "seg";
+const CURSOR_FILE: &str = "cursor";
+/// Held while a process has the buffer open; recovery truncates and removes
+/// files, which must not happen under a process still appending.
+const LOCK_FILE: &str = "lock";
+const MIN_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Buffer depth and flusher progress.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct BufferStats {
+    pub pending_records: u64,
+    pub pending_rows: u64,
+    pub pending_bytes: u64,
+    pub max_bytes: u64,
+    pub segments: usize,
+    pub flushed_records: u64,
+    pub flushed_rows: u64,
+    /// Records dropped because ClickHouse rejected them as invalid.
+    pub rejected_records: u64,
+    /// Unix seconds.
+    pub last_flush_at: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Position {
+    segment: u64,
+    offset: u64,
+}
+
+struct Segment {
+    id: u64,
+    len: u64,
+}
+
+struct Record {
+    transfers: Vec<Transfer>,
+    /// Position just past the record.
+    next: Position,
+    bytes: u64,
+}
+
+struct State {
+    /// Oldest first; the last one is appended to.
+    segments: VecDeque<Segment>,
+    active: File,
+    cursor: Position,
+    stats: BufferStats,
+}
+
+pub struct WriteBuffer {
+    log: Arc<SegmentLog>,
+    appended: Notify,
+    flushing: tokio::sync::Mutex<()>,
+}
+
+/// The files, with blocking access to them.
+struct SegmentLog {
+    dir: PathBuf,
+    /// Released when the file is closed, even if the process dies.
+    _lock: File,
+    segment_bytes: u64,
+    fsync: bool,
+    validator: TransferValidator,
+    state: Mutex<State>,
+}
+
+impl WriteBuffer {
+    /// Opens the buffer in `dir`, creating it if needed and recovering what
+    /// a previous process left behind.
+    pub fn open(
+        dir: impl Into<PathBuf>,
+        settings: &BufferSettings,
+        validator: TransferValidator,
+    ) -> io::Result<Self> {
+        Ok(Self {
+            log: Arc::new(SegmentLog::open(dir.into(), settings, validator)?),
+            appended: Notify::new(),
+            flushing: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    pub fn stats(&self) -> BufferStats {
+        self.log.state.lock().unwrap().stats.clone()
+    }
+
+    /// Like [`TransferBufferAbstract::append`], writing on this thread.
+    pub fn append_blocking(&self, transfers: &[Transfer]) -> TransferBufferResult<()> {
+        self.log.append(transfers)?;
+        self.appended.notify_one();
+        Ok(())
+    }
+
+    /// Inserts the oldest pending record. Returns `false` when nothing is
+    /// pending.
+    pub async fn flush_next<T: TransferRepoAbstract + ?Sized>(&self, repo: &T) -> Result<bool> {
+        let _flushing = self.flushing.lock().await;
+        let Some(record) = self.log.read_next()? else {
+            return Ok(false);
+        };
+
+        let rejected = match repo.save_all(&record.transfers).await {
+            Ok(_) => false,
+            // Only possible if validation rules changed since the append;
+            // retrying would block everything behind it.
+            Err(TransferRepoError::ValidationError(err)) => {
+                log::error!(
+                    "Dropping {} buffered transfers rejected by validation: {err}",
+                    record.transfers.len()
+                );
+                true
+            }
+            Err(err) => {
+                self.log.state.lock().unwrap().stats.last_error = Some(err.to_string());
+                return Err(err.into());
+            }
+        };
+
+        self.log.commit(&record, rejected)?;
+        Ok(true)
+    }
+
+    /// Inserts everything pending, stopping at the first failure.
+    pub async fn flush_all<T: TransferRepoAbstract + ?Sized>(&self, repo: &T) -> Result<()> {
+        while self.flush_next(repo).await? {}
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TransferBufferAbstract for WriteBuffer {
+    /// Writes and syncs on the blocking pool rather than a server worker.
+    async fn append(&self, transfers: Vec<Transfer>) -> TransferBufferResult<()> {
+        let log = self.log.clone();
+        actix_web::rt::task::spawn_blocking(move || log.append(&transfers))
+            .await
+            .map_err(buffer_failed)??;
+        self.appended.notify_one();
+        Ok(())
+    }
+}
+
+impl SegmentLog {
+    fn open(
+        dir: PathBuf,
+        settings: &BufferSettings,
+        validator: TransferValidator,
+    ) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        let lock = lock_dir(&dir)?;
+
+        let mut ids = segment_ids(&dir)?;
+        let mut cursor = read_cursor(&dir)?.unwrap_or(Position {
+            segment: ids.first().copied().unwrap_or(1),
+            offset: 0,
+        });
+        for id in ids.iter().filter(|id| **id < cursor.segment) {
+            fs::remove_file(segment_path(&dir, *id))?;
+        }
+        ids.retain(|id| *id >= cursor.segment);
+        if ids.first() != Some(&cursor.segment) {
+            // The cursor's segment is gone, so everything before the oldest
+            // remaining segment was flushed.
+            cursor = Position {
+                segment: ids.first().copied().unwrap_or(cursor.segment),
+                offset: 0,
+            };
+        }
+        if ids.is_empty() {
+            File::create(segment_path(&dir, cursor.segment))?;
+            ids.push(cursor.segment);
+        }
+
+        let mut stats = BufferStats {
+            max_bytes: settings.max_bytes,
+            ..BufferStats::default()
+        };
+        let mut segments = VecDeque::new();
+        let last = ids.last().copied();
+        for id in ids {
+            let start = if id == cursor.segment {
+                cursor.offset
+            } else {
+                0
+            };
+            let sealed = Some(id) != last;
+            let len = recover_segment(&segment_path(&dir, id), start, sealed, &mut stats)?;
+            segments.push_back(Segment { id, len });
+        }
+        stats.segments = segments.len();
+
+        let active_id = segments.back().map(|segment| segment.id).unwrap_or(1);
+        let active = OpenOptions::new()
+            .append(true)
+            .open(segment_path(&dir, active_id))?;
+
+        Ok(Self {
+            dir,
+            _lock: lock,
+            segment_bytes: settings.segment_bytes,
+            fsync: settings.fsync,
+            validator,
+            state: Mutex::new(State {
+                segments,
+                active,
+                cursor,
+                stats,
+            }),
+        })
+    }
+
+    fn append(&self, transfers: &[Transfer]) -> TransferBufferResult<()> {
+        self.validator.validate_all(transfers)?;
+
+        let payload = serde_json::to_vec(transfers)
+            .map_err(|err| TransferRepoError::BufferFailed(err.to_string()))?;
+        let payload_len = u32::try_from(payload.len()).map_err(|_| {
+            TransferRepoError::BadRequest(format!(
+                "batch of {} bytes exceeds the write buffer's record limit",
+                payload.len()
+            ))
+        })?;
+        let mut record = Vec::with_capacity(HEADER_BYTES as usize + payload.len());
+        record.extend_from_slice(&payload_len.to_le_bytes());
+        record.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+        record.extend_from_slice(&payload);
+        let size = record.len() as u64;
+
+        let mut state = self.state.lock().unwrap();
+        let max_bytes = state.stats.max_bytes;
+        if size > max_bytes {
+            return Err(TransferRepoError::BadRequest(format!(
+                "batch of {size} bytes exceeds the {max_bytes} byte write buffer"
+            )));
+        }
+        if state.stats.pending_bytes + size > max_bytes {
+            return Err(TransferRepoError::BufferFull(format!(
+                "{} of {max_bytes} bytes pending",
+                state.stats.pending_bytes
+            )));
+        }
+
+        self.write_record(&mut state, &record)
+            .map_err(buffer_failed)?;
+        state.stats.pending_records += 1;
+        state.stats.pending_rows += transfers.len() as u64;
+        state.stats.pending_bytes += size;
+        Ok(())
+    }
+
+    fn write_record(&self, state: &mut State, record: &[u8]) -> io::Result<()> {
+        let active = state.segments.back().expect("an active segment");
+        if active.len > 0 && active.len + record.len() as u64 > self.segment_bytes {
+            let id = active.id + 1;
+            let file = OpenOptions::new()
+                .append(true)
+                .create_new(true)
+                .open(segment_path(&self.dir, id))?;
+            if self.fsync {
+                sync_dir(&self.dir)?;
+            }
+            state.active = file;
+            state.segments.push_back(Segment { id, len: 0 });
+            state.stats.segments = state.segments.len();
+        }
+
+        let len = state.segments.back().expect("an active segment").len;
+        let written = state.active.write_all(record).and_then(|()| {
+            if self.fsync {
+                state.active.sync_data()
+            } else {
+                Ok(())
+            }
+        });
+        if let Err(err) = written {
+            // Drop whatever part of the record made it, so the next append
+            // doesn't land behind garbage.
+            state.active.set_len(len).ok();
+            return Err(err);
+        }
+
+        state.segments.back_mut().expect("an active segment").len += record.len() as u64;
+        Ok(())
+    }
+
+    fn read_next(&self) -> io::Result<Option<Record>> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            let cursor = state.cursor;
+            let segment = state
+                .segments
+                .iter()
+                .find(|segment| segment.id == cursor.segment)
+                .map(|segment| segment.len);
+            let is_active = state.segments.back().map(|segment| segment.id) == Some(cursor.segment);
+
+            match segment {
+                Some(len) if cursor.offset < len => {
+                    let mut file = File::open(segment_path(&self.dir, cursor.segment))?;
+                    file.seek(SeekFrom::Start(cursor.offset))?;
+                    let (transfers, bytes) = read_record(&mut BufReader::new(file))?
+                        .ok_or_else(|| invalid_data("segment ends inside a record"))?;
+                    return Ok(Some(Record {
+                        transfers,
+                        next: Position {
+                            segment: cursor.segment,
+                            offset: cursor.offset + bytes,
+                        },
+                        bytes,
+                    }));
+                }
+                _ if is_active => return Ok(None),
+                _ => {
+                    // Fully flushed; move on to the next segment.
+                    let next = state
+                        .segments
+                        .iter()
+                        .map(|segment| segment.id)
+                        .find(|id| *id > cursor.segment);
+                    let Some(next) = next else {
+                        return Ok(None);
+                    };
+                    state.cursor = Position {
+                        segment: next,
+                        offset: 0,
+                    };
+                    self.retire_segments(&mut state)?;
+                }
+            }
+        }
+    }
+
+    fn commit(&self, record: &Record, rejected: bool) -> io::Result<()> {
+        write_cursor(&self.dir, record.next, self.fsync)?;
+
+        let mut state = self.state.lock().unwrap();
+        state.cursor = record.next;
+        let rows = record.transfers.len() as u64;
+        let stats = &mut state.stats;
+        stats.pending_records = stats.pending_records.saturating_sub(1);
+        stats.pending_rows = stats.pending_rows.saturating_sub(rows);
+        stats.pending_bytes = stats.pending_bytes.saturating_sub(record.bytes);
+        if rejected {
+            stats.rejected_records += 1;
+        } else {
+            stats.flushed_records += 1;
+            stats.flushed_rows += rows;
+        }
+        stats.last_flush_at = Some(unix_now());
+        stats.last_error = None;
+        Ok(())
+    }
+
+    /// Deletes segments before the cursor's, after persisting the cursor so
+    /// a restart doesn't look for them.
+    fn retire_segments(&self, state: &mut State) -> io::Result<()> {
+        write_cursor(&self.dir, state.cursor, self.fsync)?;
+        while let Some(segment) = state.segments.front() {
+            if segment.id >= state.cursor.segment {
+                break;
+            }
+            fs::remove_file(segment_path(&self.dir, segment.id))?;
+            state.segments.pop_front();
+        }
+        state.stats.segments = state.segments.len();
+        Ok(())
+    }
+}
+
+/// Inserts buffered records in order as they arrive, backing off while
+/// ClickHouse is unavailable. Stops when shutdown begins; what is left is
+/// flushed by the drain or found again on the next start.
+pub async fn run_flusher<T: TransferRepoAbstract>(
+    buffer: Arc<WriteBuffer>,
+    transfer_repo: Arc<T>,
+    shutdown: Arc<Shutdown>,
+) {
+    let cancelled = shutdown.token();
+    let mut backoff = MIN_BACKOFF;
+
+    while !cancelled.is_cancelled() {
+        let appended = buffer.appended.notified();
+        let wait = match shutdown.track(buffer.flush_next(&*transfer_repo)).await {
+            Ok(true) => {
+                backoff = MIN_BACKOFF;
+                continue;
+            }
+            Ok(false) => None,
+            Err(err) => {
+                log::warn!("Write buffer flush failed, retrying in {backoff:?}: {err:#}");
+                let wait = backoff;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+                Some(wait)
+            }
+        };
+
+        tokio::select! {
+            _ = appended, if wait.is_none() => {}
+            _ = actix_web::rt::time::sleep(wait.unwrap_or_default()), if wait.is_some() => {}
+            _ = cancelled.cancelled() => return,
+        }
+    }
+}
+
+/// Validates the records of a segment from `start` and adds them to the
+/// pending counts. The active segment is truncated at the first torn or
+/// corrupt record; a `sealed` one was complete before the next segment was
+/// started, so a bad record there is an error.
+fn recover_segment(
+    path: &Path,
+    start: u64,
+    sealed: bool,
+    stats: &mut BufferStats,
+) -> io::Result<u64> {
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    let len = file.metadata()?.len();
+    if start > len {
+        return Err(invalid_data("cursor points past the end of its segment"));
+    }
+    file.seek(SeekFrom::Start(start))?;
+
+    let mut reader = BufReader::new(&mut file);
+    let mut offset = start;
+    loop {
+        match read_record(&mut reader) {
+            Ok(Some((transfers, bytes))) => {
+                offset += bytes;
+                stats.pending_records += 1;
+                stats.pending_rows += transfers.len() as u64;
+                stats.pending_bytes += bytes;
+            }
+            Ok(None) => break,
+            Err(err) if err.kind() == io::ErrorKind::InvalidData && sealed => {
+                return Err(invalid_data(format!(
+                    "sealed segment {} is corrupt at byte {offset}: {err}; \
+                     move it aside to start without its unflushed batches",
+                    path.display()
+                )));
+            }
+            Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+                log::warn!(
+                    "Truncating {} at byte {offset}: {err}, dropping {} unacknowledged bytes",
+                    path.display(),
+                    len - offset
+                );
+                drop(reader);
+                file.set_len(offset)?;
+                file.sync_all()?;
+                break;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(offset)
+}
+
+/// Reads one record, or `None` at a clean end of the segment.
+fn read_record(reader: &mut impl Read) -> io::Result<Option<(Vec<Transfer>, u64)>> {
+    let mut header = [0u8; HEADER_BYTES as usize];
+    let read = read_fully(reader, &mut header)?;
+    if read == 0 {
+        return Ok(None);
+    }
+    if read < header.len() {
+        return Err(invalid_data("truncated record header"));
+    }
+
+    let len = u32::from_le_bytes(header[..4].try_into().unwrap()) as usize;
+    let checksum = u32::from_le_bytes(header[4..].try_into().unwrap());
+    let mut payload = vec![0u8; len];
+    if read_fully(reader, &mut payload)? < len {
+        return Err(invalid_data("truncated record payload"));
+    }
+    if crc32fast::hash(&payload) != checksum {
+        return Err(invalid_data("record checksum mismatch"));
+    }
+
+    let transfers = serde_json::from_slice(&payload).map_err(invalid_data)?;
+    Ok(Some((transfers, HEADER_BYTES + len as u64)))
+}
+
+/// Like `read_exact`, but reports how much was read before the end.
+fn read_fully(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(filled)
+}
+
+fn lock_dir(dir: &Path) -> io::Result<File> {
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(dir.join(LOCK_FILE))?;
+    match file.try_lock() {
+        Ok(()) => Ok(file),
+        Err(TryLockError::WouldBlock) => Err(io::Error::new(
+            io::ErrorKind::WouldBlock,
+            "another process has the write buffer open",
+        )),
+        Err(TryLockError::Error(err)) => Err(err),
+    }
+}
+
+fn segment_ids(dir: &Path) -> io::Result<Vec<u64>> {
+    let mut ids = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(SEGMENT_EXTENSION) {
+            continue;
+        }
+        if let Some(id) = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.parse().ok())
+        {
+            ids.push(id);
+        }
+    }
+    ids.sort_unstable();
+    Ok(ids)
+}
+
+fn segment_path(dir: &Path, id: u64) -> PathBuf {
+    dir.join(format!("{id:020}.{SEGMENT_EXTENSION}"))
+}
+
+fn read_cursor(dir: &Path) -> io::Result<Option<Position>> {
+    let text = match fs::read_to_string(dir.join(CURSOR_FILE)) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let mut parts = text.split_whitespace().map(str::parse::<u64>);
+    match (parts.next(), parts.next()) {
+        (Some(Ok(segment)), Some(Ok(offset))) => Ok(Some(Position { segment, offset })),
+        _ => Err(invalid_data(format!("malformed cursor file: {text:?}"))),
+    }
+}
+
+/// Replaces the cursor file atomically.
+fn write_cursor(dir: &Path, cursor: Position, fsync: bool) -> io::Result<()> {
+    let temporary = dir.join(format!("{CURSOR_FILE}.tmp"));
+    let mut file = File::create(&temporary)?;
+    writeln!(file, "{} {}", cursor.segment, cursor.offset)?;
+    if fsync {
+        file.sync_all()?;
+    }
+    fs::rename(&temporary, dir.join(CURSOR_FILE))?;
+    if fsync {
+        sync_dir(dir)?;
+    }
+    Ok(())
+}
+
+/// Makes created, renamed and deleted entries of `dir` durable.
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    File::open(dir)?.sync_all()
+}
+
+fn invalid_data(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+fn buffer_failed(err: impl std::fmt::Display) -> TransferRepoError {
+    TransferRepoError::BufferFailed(err.to_string())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::domain::{
+        entities::insert_report::InsertReport,
+        repositories::transfer_repo::MockTransferRepoAbstract,
+    };
+
+    fn settings() -> BufferSettings {
+        BufferSettings {
+            dir: None,
+            max_bytes: 1024 * 1024,
+            segment_bytes: 1024 * 1024,
+            fsync: false,
+        }
+    }
+
+    fn open(dir: &Path, settings: &BufferSettings) -> WriteBuffer {
+        WriteBuffer::open(dir, settings, TransferValidator::empty()).unwrap()
+    }
+
+    fn batch(ts: u64, rows: usize) -> Vec<Transfer> {
+        (0..rows)
+            .map(|i| Transfer {
+                ts,
+                from: format!("0x{i}"),
+                to: "0xb".to_string(),
+                amount: 1.0,
+                usd_price: 1.0,
+            })
+            .collect()
+    }
+
+    /// A repository recording the `ts` of every batch it is given.
+    fn recording_repo(saved: Arc<Mutex<Vec<u64>>>) -> MockTransferRepoAbstract {
+        let mut repo = MockTransferRepoAbstract::new();
+        repo.expect_save_all().returning(move |transfers| {
+            saved.lock().unwrap().push(transfers[0].ts);
+            Ok(InsertReport::default())
+        });
+        repo
+    }
+
+    #[actix_web::test]
+    async fn test_flushes_in_order_and_resumes_after_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let buffer = open(dir.path(), &settings());
+        for ts in 1..=3 {
+            buffer.append_blocking(&batch(ts, 2)).unwrap();
+        }
+        assert_eq!(buffer.stats().pending_records, 3);
+        assert_eq!(buffer.stats().pending_rows, 6);
+
+        let saved = Arc::new(Mutex::new(Vec::new()));
+        assert!(
+            buffer
+                .flush_next(&recording_repo(saved.clone()))
+                .await
+                .unwrap()
+        );
+        drop(buffer);
+
+        let reopened = open(dir.path(), &settings());
+        assert_eq!(reopened.stats().pending_records, 2);
+        reopened
+            .flush_all(&recording_repo(saved.clone()))
+            .await
+            .unwrap();
+
+        assert_eq!(*saved.lock().unwrap(), vec![1, 2, 3]);
+        let stats = reopened.stats();
+        assert_eq!(stats.pending_records, 0);
+        assert_eq!(stats.pending_bytes, 0);
+        assert_eq!(stats.flushed_rows, 4);
+    }
+
+    #[actix_web::test]
+    async fn test_failed_insert_keeps_the_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let buffer = open(dir.path(), &settings());
+        buffer.append_blocking(&batch(1, 1)).unwrap();
+
+        let mut repo = MockTransferRepoAbstract::new();
+        repo.expect_save_all().returning(|_| {
+            Err(TransferRepoError::DatabaseConnectionError(
+                "refused".to_string(),
+            ))
+        });
+
+        assert!(buffer.flush_next(&repo).await.is_err());
+        let stats = buffer.stats();
+        assert_eq!(stats.pending_records, 1);
+        assert!(stats.last_error.unwrap().contains("refused"));
+    }
+
+    #[test]
+    fn test_torn_record_is_truncated_on_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let buffer = open(dir.path(), &settings());
+        buffer.append_blocking(&batch(1, 1)).unwrap();
+        buffer.append_blocking(&batch(2, 1)).unwrap();
+        drop(buffer);
+
+        let path = segment_path(dir.path(), 1);
+        let len = fs::metadata(&path).unwrap().len();
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(len - 3)
+            .unwrap();
+
+        let reopened = open(dir.path(), &settings());
+        assert_eq!(reopened.stats().pending_records, 1);
+        // Appends continue cleanly after the truncated record.
+        reopened.append_blocking(&batch(3, 1)).unwrap();
+        drop(reopened);
+        assert_eq!(open(dir.path(), &settings()).stats().pending_records, 2);
+    }
+
+    #[test]
+    fn test_second_open_of_a_directory_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let buffer = open(dir.path(), &settings());
+        buffer.append_blocking(&batch(1, 1)).unwrap();
+
+        let err = WriteBuffer::open(dir.path(), &settings(), TransferValidator::empty())
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+
+        drop(buffer);
+        assert_eq!(open(dir.path(), &settings()).stats().pending_records, 1);
+    }
+
+    #[test]
+    fn test_corrupt_sealed_segment_fails_to_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let small = BufferSettings {
+            segment_bytes: 64,
+            ..settings()
+        };
+        let buffer = open(dir.path(), &small);
+        buffer.append_blocking(&batch(1, 1)).unwrap();
+        buffer.append_blocking(&batch(2, 1)).unwrap();
+        drop(buffer);
+        assert!(segment_path(dir.path(), 2).exists());
+
+        let path = segment_path(dir.path(), 1);
+        let len = fs::metadata(&path).unwrap().len();
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(len - 3)
+            .unwrap();
+
+        let err = WriteBuffer::open(dir.path(), &small, TransferValidator::empty())
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(fs::metadata(&path).unwrap().len(), len - 3);
+    }
+
+    #[actix_web::test]
+    async fn test_full_buffer_applies_backpressure() {
+        let dir = tempfile::tempdir().unwrap();
+        let record_bytes = {
+            let probe = tempfile::tempdir().unwrap();
+            let buffer = open(probe.path(), &settings());
+            buffer.append_blocking(&batch(1, 1)).unwrap();
+            buffer.stats().pending_bytes
+        };
+        let settings = BufferSettings {
+            max_bytes: record_bytes * 2,
+            segment_bytes: record_bytes,
+            ..settings()
+        };
+        let buffer = open(dir.path(), &settings);
+
+        buffer.append_blocking(&batch(1, 1)).unwrap();
+        buffer.append_blocking(&batch(2, 1)).unwrap();
+        assert!(matches!(
+            buffer.append_blocking(&batch(3, 1)),
+            Err(TransferRepoError::BufferFull(_))
+        ));
+        assert!(matches!(
+            buffer.append_blocking(&batch(3, 3)),
+            Err(TransferRepoError::BadRequest(_))
+        ));
+        assert_eq!(buffer.stats().segments, 2);
+
+        let saved = Arc::new(Mutex::new(Vec::new()));
+        buffer
+            .flush_all(&recording_repo(saved.clone()))
+            .await
+            .unwrap();
+        // Flushed segments are deleted, making room again.
+        assert_eq!(buffer.stats().segments, 1);
+        buffer.append_blocking(&batch(3, 1)).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_transfers_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let buffer =
+            WriteBuffer::open(dir.path(), &settings(), TransferValidator::default()).unwrap();
+        let mut transfers = batch(1, 1);
+        transfers[0].amount = -1.0;
+
+        assert!(matches!(
+            buffer.append_blocking(&transfers),
+            Err(TransferRepoError::ValidationError(_))
+        ));
+        assert_eq!(buffer.stats().pending_records, 0);
+    }
+
+    #[actix_web::test]
+    async fn test_flusher_inserts_appended_records_until_shutdown() {
+        let dir = tempfile::tempdir().unwrap();
+        let buffer = Arc::new(open(dir.path(), &settings()));
+        let shutdown = Arc::new(Shutdown::new(Duration::from_secs(1)));
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let mut repo = MockTransferRepoAbstract::new();
+        repo.expect_save_all().returning(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(InsertReport::default())
+        });
+
+        let flusher = actix_web::rt::spawn(run_flusher(
+            buffer.clone(),
+            Arc::new(repo),
+            shutdown.clone(),
+        ));
+        buffer.append(batch(1, 1)).await.unwrap();
+        for _ in 0..100 {
+            if calls.load(Ordering::SeqCst) == 1 {
+                break;
+            }
+            actix_web::rt::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        shutdown.begin();
+        flusher.await.unwrap();
+    }
+}
@@ -0,0 +1,360 @@
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+use anyhow::Result;
+
+use crate::{
+    config::KafkaSettings,
+    domain::{
+        entities::transfer::Transfer,
+        repositories::{errors::TransferRepoError, transfer_repo::TransferRepoAbstract},
+        validation::{TransferValidator, errors::ValidationError},
+    },
+    infrastructure::{
+        kafka::{DeadLetterSink, Message, MessageSource, codec::MessageFormat},
+        shutdown::Shutdown,
+    },
+};
+
+const MIN_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Inserts transfers consumed from a topic. A batch's offsets are committed
+/// once its transfers are inserted and its invalid messages dead-lettered,
+/// so every message is handled at least once.
+pub struct TransferConsumer<S, D, T> {
+    source: S,
+    dead_letters: D,
+    transfer_repo: Arc<T>,
+    validator: TransferValidator,
+    format: MessageFormat,
+    batch_size: usize,
+    batch_wait: Duration,
+}
+
+/// A polled batch and how far it got; a retry picks up from there.
+struct Batch {
+    messages: Vec<Message>,
+    transfers: Vec<Transfer>,
+    /// Index into `messages` of each of `transfers`.
+    sources: Vec<usize>,
+    /// Index into `messages` and the reason.
+    rejected: Vec<(usize, String)>,
+    inserted: bool,
+    dead_lettered: usize,
+}
+
+impl Batch {
+    /// Moves the transfers `err` reports as `transfers[i].field` to
+    /// `rejected`, or all of them when it names none.
+    fn reject(&mut self, err: &ValidationError) {
+        let mut reasons: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+        for violation in &err.violations {
+            let named = violation
+                .field
+                .strip_prefix("transfers[")
+                .and_then(|rest| rest.split_once("]."))
+                .and_then(|(index, field)| Some((index.parse::<usize>().ok()?, field)))
+                .filter(|(index, _)| *index < self.transfers.len());
+            if let Some((index, field)) = named {
+                reasons
+                    .entry(index)
+                    .or_default()
+                    .push(format!("{field}: {}", violation.message));
+            }
+        }
+        if reasons.is_empty() {
+            reasons = (0..self.transfers.len())
+                .map(|index| (index, vec![err.to_string()]))
+                .collect();
+        }
+
+        for (index, reasons) in reasons.into_iter().rev() {
+            self.transfers.remove(index);
+            let source = self.sources.remove(index);
+            self.rejected
+                .push((source, format!("Validation failed: {}", reasons.join("; "))));
+        }
+        self.rejected.sort_by_key(|(index, _)| *index);
+    }
+}
+
+impl<S, D, T> TransferConsumer<S, D, T>
+where
+    S: MessageSource,
+    D: DeadLetterSink,
+    T: TransferRepoAbstract,
+{
+    pub fn new(
+        source: S,
+        dead_letters: D,
+        transfer_repo: Arc<T>,
+        validator: TransferValidator,
+        settings: &KafkaSettings,
+    ) -> Self {
+        Self {
+            source,
+            dead_letters,
+            transfer_repo,
+            validator,
+            format: settings.format,
+            batch_size: settings.batch_size,
+            batch_wait: Duration::from_millis(settings.batch_wait_ms),
+        }
+    }
+
+    /// Consumes until shutdown begins. A batch in progress is finished
+    /// first, unless it is waiting out a failure.
+    pub async fn run(self, shutdown: Arc<Shutdown>) {
+        let cancelled = shutdown.token();
+        let mut backoff = MIN_BACKOFF;
+
+        loop {
+            let polled = tokio::select! {
+                polled = self.source.poll(self.batch_size, self.batch_wait) => polled,
+                _ = cancelled.cancelled() => return,
+            };
+            let mut batch = match polled {
+                Ok(messages) => self.decode(messages),
+                Err(err) => {
+                    log::warn!("Polling transfers failed, retrying in {backoff:?}: {err:#}");
+                    tokio::select! {
+                        _ = actix_web::rt::time::sleep(backoff) => {}
+                        _ = cancelled.cancelled() => return,
+                    }
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                    continue;
+                }
+            };
+
+            while let Err(err) = shutdown.track(self.process(&mut batch)).await {
+                log::warn!("Consuming transfers failed, retrying in {backoff:?}: {err:#}");
+                tokio::select! {
+                    _ = actix_web::rt::time::sleep(backoff) => {}
+                    _ = cancelled.cancelled() => return,
+                }
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+            backoff = MIN_BACKOFF;
+        }
+    }
+
+    fn decode(&self, messages: Vec<Message>) -> Batch {
+        let mut transfers = Vec::with_capacity(messages.len());
+        let mut sources = Vec::with_capacity(messages.len());
+        let mut rejected = Vec::new();
+        for (index, message) in messages.iter().enumerate() {
+            let decoded = self.format.decode(message.payload.as_deref());
+            let validated = decoded
+                .map_err(|err| err.to_string())
+                .and_then(|transfer| match self.validator.validate(&transfer) {
+                    Ok(()) => Ok(transfer),
+                    Err(err) => Err(format!("Validation failed: {err}")),
+                });
+            match validated {
+                Ok(transfer) => {
+                    transfers.push(transfer);
+                    sources.push(index);
+                }
+                Err(reason) => rejected.push((index, reason)),
+            }
+        }
+
+        Batch {
+            messages,
+            transfers,
+            sources,
+            rejected,
+            inserted: false,
+            dead_lettered: 0,
+        }
+    }
+
+    /// Inserts, dead-letters and commits whatever of `batch` is not done yet.
+    async fn process(&self, batch: &mut Batch) -> Result<()> {
+        while !batch.inserted && !batch.transfers.is_empty() {
+            match self.transfer_repo.save_all(&batch.transfers).await {
+                Ok(_) => break,
+                // The repository validates with the same rules, so this
+                // means they changed in between; retrying would never end.
+                // The transfers it names are dead-lettered and the rest is
+                // inserted again.
+                Err(TransferRepoError::ValidationError(err)) => batch.reject(&err),
+                Err(err) => return Err(err.into()),
+            }
+        }
+        batch.inserted = true;
+
+        while let Some((index, reason)) = batch.rejected.get(batch.dead_lettered) {
+            self.dead_letters
+                .send(&batch.messages[*index], reason)
+                .await?;
+            batch.dead_lettered += 1;
+        }
+
+        self.source.commit(&batch.messages).await?;
+        log::debug!(
+            "Consumed {} transfers, dead-lettered {}",
+            batch.messages.len() - batch.rejected.len(),
+            batch.rejected.len()
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{
+        domain::{
+            entities::insert_report::InsertReport,
+            repositories::transfer_repo::MockTransferRepoAbstract, validation::errors::Violation,
+        },
+        infrastructure::kafka::memory::{MemoryBroker, MemoryConsumer, MemoryDeadLetters},
+    };
+
+    fn transfer(amount: f64) -> Vec<u8> {
+        serde_json::to_vec(&Transfer {
+            ts: 1,
+            from: "0xaaa".to_string(),
+            to: "0xbbb".to_string(),
+            amount,
+            usd_price: 1.0,
+        })
+        .unwrap()
+    }
+
+    fn consumer(
+        broker: &Arc<MemoryBroker>,
+        transfer_repo: MockTransferRepoAbstract,
+    ) -> TransferConsumer<MemoryConsumer, MemoryDeadLetters, MockTransferRepoAbstract> {
+        let settings = KafkaSettings {
+            batch_size: 10,
+            batch_wait_ms: 0,
+            ..KafkaSettings::default()
+        };
+        TransferConsumer::new(
+            broker.consumer("transfers"),
+            MemoryDeadLetters::default(),
+            Arc::new(transfer_repo),
+            TransferValidator::standard(Default::default()),
+            &settings,
+        )
+    }
+
+    #[actix_web::test]
+    async fn test_invalid_messages_are_dead_lettered_and_committed() {
+        let broker = MemoryBroker::new();
+        broker.produce("transfers", None, Some(&transfer(5.0)));
+        broker.produce("transfers", Some(b"key"), Some(b"not json"));
+        broker.produce("transfers", None, Some(&transfer(-1.0)));
+        let mut transfer_repo = MockTransferRepoAbstract::new();
+        transfer_repo
+            .expect_save_all()
+            .withf(|transfers| transfers.len() == 1 && transfers[0].amount == 5.0)
+            .times(1)
+            .returning(|_| Ok(InsertReport::default()));
+        let consumer = consumer(&broker, transfer_repo);
+
+        let messages = consumer.source.poll(10, Duration::ZERO).await.unwrap();
+        consumer
+            .process(&mut consumer.decode(messages))
+            .await
+            .unwrap();
+
+        let dead_letters = consumer.dead_letters.messages();
+        assert_eq!(dead_letters.len(), 2);
+        assert_eq!(dead_letters[0].0.key.as_deref(), Some(&b"key"[..]));
+        assert!(dead_letters[0].1.starts_with("Invalid JSON"));
+        assert!(dead_letters[1].1.starts_with("Validation failed: amount"));
+        assert_eq!(broker.committed("transfers"), 3);
+    }
+
+    #[actix_web::test]
+    async fn test_offsets_wait_for_the_insert() {
+        let broker = MemoryBroker::new();
+        broker.produce("transfers", None, Some(&transfer(5.0)));
+        broker.produce("transfers", None, Some(b"{}"));
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let counter = attempts.clone();
+        let mut transfer_repo = MockTransferRepoAbstract::new();
+        transfer_repo.expect_save_all().returning(move |_| {
+            match counter.fetch_add(1, Ordering::SeqCst) {
+                0 => Err(TransferRepoError::DatabaseConnectionError(
+                    "connection refused".to_string(),
+                )),
+                _ => Ok(InsertReport::default()),
+            }
+        });
+        let consumer = consumer(&broker, transfer_repo);
+        let messages = consumer.source.poll(10, Duration::ZERO).await.unwrap();
+        let mut batch = consumer.decode(messages);
+
+        assert!(consumer.process(&mut batch).await.is_err());
+        assert_eq!(broker.committed("transfers"), 0);
+        assert!(consumer.dead_letters.messages().is_empty());
+
+        consumer.process(&mut batch).await.unwrap();
+        assert_eq!(broker.committed("transfers"), 2);
+        assert_eq!(consumer.dead_letters.messages().len(), 1);
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[actix_web::test]
+    async fn test_repo_rejections_dead_letter_only_the_named_transfers() {
+        let broker = MemoryBroker::new();
+        broker.produce("transfers", None, Some(&transfer(5.0)));
+        broker.produce("transfers", None, Some(&transfer(6.0)));
+        broker.produce("transfers", None, Some(&transfer(7.0)));
+        let mut transfer_repo = MockTransferRepoAbstract::new();
+        let mut sequence = mockall::Sequence::new();
+        transfer_repo
+            .expect_save_all()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_| {
+                Err(TransferRepoError::ValidationError(ValidationError {
+                    violations: vec![Violation::new("transfers[1].amount", "too large")],
+                }))
+            });
+        transfer_repo
+            .expect_save_all()
+            .withf(|transfers| transfers.iter().map(|t| t.amount).collect::<Vec<_>>() == [5.0, 7.0])
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_| Ok(InsertReport::default()));
+        let consumer = consumer(&broker, transfer_repo);
+
+        let messages = consumer.source.poll(10, Duration::ZERO).await.unwrap();
+        consumer
+            .process(&mut consumer.decode(messages))
+            .await
+            .unwrap();
+
+        let dead_letters = consumer.dead_letters.messages();
+        assert_eq!(dead_letters.len(), 1);
+        assert_eq!(dead_letters[0].0.payload, Some(transfer(6.0)));
+        assert_eq!(dead_letters[0].1, "Validation failed: amount: too large");
+        assert_eq!(broker.committed("transfers"), 3);
+    }
+
+    #[actix_web::test]
+    async fn test_run_consumes_until_shutdown() {
+        let broker = MemoryBroker::new();
+        let mut transfer_repo = MockTransferRepoAbstract::new();
+        transfer_repo
+            .expect_save_all()
+            .returning(|_| Ok(InsertReport::default()));
+        let shutdown = Arc::new(Shutdown::new(Duration::from_secs(5)));
+        let running = actix_web::rt::spawn(consumer(&broker, transfer_repo).run(shutdown.clone()));
+
+        broker.produce("transfers", None, Some(&transfer(5.0)));
+        while broker.committed("transfers") < 1 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        shutdown.begin();
+
+        running.await.unwrap();
+    }
+}
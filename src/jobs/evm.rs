@@ -0,0 +1,648 @@
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result, bail};
+
+use crate::{
+    config::EvmSettings,
+    domain::{
+        entities::chain::{ChainTransfer, Checkpoint},
+        repositories::{chain_repo::ChainRepoAbstract, transfer_repo::TransferRepoAbstract},
+    },
+    infrastructure::{
+        evm::{
+            decode_transfer, parse_quantity,
+            rpc::{Block, JsonRpcClient},
+        },
+        shutdown::Shutdown,
+    },
+};
+
+const MIN_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+#[derive(Debug, PartialEq)]
+enum Progress {
+    /// Blocks up to `to` were ingested; there may be more.
+    Ingested {
+        to: u64,
+        transfers: usize,
+    },
+    /// The checkpoint block was replaced and the last blocks rolled back.
+    RolledBack {
+        to: u64,
+    },
+    CaughtUp,
+}
+
+/// Ingests a token's `Transfer` events block range by block range,
+/// following the chain head.
+pub struct EvmIngestJob<C: ChainRepoAbstract, T: TransferRepoAbstract> {
+    rpc: JsonRpcClient,
+    chain_repo: Arc<C>,
+    transfer_repo: Arc<T>,
+    settings: EvmSettings,
+}
+
+impl<C: ChainRepoAbstract, T: TransferRepoAbstract> EvmIngestJob<C, T> {
+    pub fn new(
+        rpc: JsonRpcClient,
+        chain_repo: Arc<C>,
+        transfer_repo: Arc<T>,
+        settings: EvmSettings,
+    ) -> Self {
+        Self {
+            rpc,
+            chain_repo,
+            transfer_repo,
+            settings,
+        }
+    }
+
+    /// Ingests until caught up, then polls every `poll_interval_secs`, backing
+    /// off on failures. Stops when shutdown begins, after the range in
+    /// progress.
+    pub async fn run(self, shutdown: Arc<Shutdown>) {
+        let cancelled = shutdown.token();
+        let poll_interval = Duration::from_secs(self.settings.poll_interval_secs);
+        let mut backoff = MIN_BACKOFF;
+
+        while !cancelled.is_cancelled() {
+            let wait = match shutdown.track(self.step()).await {
+                Ok(Progress::CaughtUp) => poll_interval,
+                Ok(progress) => {
+                    log::debug!("EVM ingestion: {progress:?}");
+                    backoff = MIN_BACKOFF;
+                    continue;
+                }
+                Err(err) => {
+                    log::warn!("EVM ingestion failed, retrying in {backoff:?}: {err:#}");
+                    let wait = backoff;
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                    wait
+                }
+            };
+
+            tokio::select! {
+                _ = actix_web::rt::time::sleep(wait) => {}
+                _ = cancelled.cancelled() => return,
+            }
+        }
+    }
+
+    async fn step(&self) -> Result<Progress> {
+        let head = self
+            .rpc
+            .block_number()
+            .await?
+            .saturating_sub(self.settings.confirmations);
+
+        let next = match self.chain_repo.checkpoint().await? {
+            Some(checkpoint) => {
+                let number = checkpoint.block_number;
+                let block = self.block(number).await?.with_context(|| missing(number))?;
+                if block.hash != checkpoint.block_hash {
+                    return self.roll_back(&checkpoint).await;
+                }
+                number + 1
+            }
+            None => self.settings.start_block.unwrap_or(head),
+        };
+
+        // Left by a run that stopped between inserting and checkpointing.
+        let unfinished = self.chain_repo.logs_from(next).await?;
+        if let Some(last) = unfinished.last() {
+            let to = last.block_number;
+            return self.resume(next, to, unfinished).await;
+        }
+
+        if next > head {
+            return Ok(Progress::CaughtUp);
+        }
+        let to = head.min(next + self.settings.max_block_range - 1);
+        let (transfers, last) = self.read(next, to).await?;
+        // Logs first, so whatever gets inserted can be found again.
+        self.chain_repo.save_logs(&transfers).await?;
+        self.insert(&transfers, &last).await
+    }
+
+    /// Finishes a range whose transfers were recorded but whose insert may
+    /// or may not have landed.
+    async fn resume(&self, from: u64, to: u64, logged: Vec<ChainTransfer>) -> Result<Progress> {
+        let (transfers, last) = self.read(from, to).await?;
+        if transfers != logged {
+            // Blocks past the checkpoint were replaced in the meantime.
+            self.chain_repo.rollback(from).await?;
+            self.chain_repo.save_logs(&transfers).await?;
+        }
+        // The same rows get the same deduplication token, so whatever
+        // landed before is not stored twice.
+        self.insert(&transfers, &last).await
+    }
+
+    /// The transfers of blocks `from` to `to`, and block `to`.
+    async fn read(&self, from: u64, to: u64) -> Result<(Vec<ChainTransfer>, Block)> {
+        let logs = self
+            .rpc
+            .transfer_logs(&self.settings.token_address, from, to)
+            .await?;
+
+        let mut numbers = Vec::with_capacity(logs.len() + 1);
+        for log in &logs {
+            numbers.push(parse_quantity(&log.block_number)?);
+        }
+        numbers.push(to);
+        numbers.sort_unstable();
+        numbers.dedup();
+        let mut blocks: BTreeMap<u64, Block> = numbers
+            .iter()
+            .copied()
+            .zip(self.rpc.blocks(&numbers).await?)
+            .map(|(number, block)| Ok((number, block.with_context(|| missing(number))?)))
+            .collect::<Result<_>>()?;
+
+        let mut chain_transfers = Vec::with_capacity(logs.len());
+        for log in logs.iter().filter(|log| !log.removed) {
+            let block = &blocks[&parse_quantity(&log.block_number)?];
+            if block.hash != log.block_hash {
+                bail!("Block {} changed while its logs were read", block.number);
+            }
+            let Some((from, to, amount)) = decode_transfer(log, self.settings.token_decimals)
+            else {
+                continue;
+            };
+            // Zero-value transfers are valid events but not valid rows.
+            if amount <= 0.0 {
+                continue;
+            }
+            chain_transfers.push(ChainTransfer {
+                block_number: block.number,
+                log_index: parse_quantity(&log.log_index)?,
+                block_hash: block.hash.clone(),
+                tx_hash: log.transaction_hash.clone(),
+                ts: block.timestamp,
+                from,
+                to,
+                amount,
+                usd_price: self.settings.usd_price,
+            });
+        }
+        chain_transfers.sort_by_key(|transfer| (transfer.block_number, transfer.log_index));
+
+        let last = blocks.remove(&to).with_context(|| missing(to))?;
+        Ok((chain_transfers, last))
+    }
+
+    async fn insert(&self, transfers: &[ChainTransfer], last: &Block) -> Result<Progress> {
+        if !transfers.is_empty() {
+            self.transfer_repo
+                .save_chain_transfers(transfers, &dedup_token(transfers))
+                .await?;
+        }
+        self.chain_repo.save_checkpoint(&checkpoint(last)?).await?;
+
+        Ok(Progress::Ingested {
+            to: last.number,
+            transfers: transfers.len(),
+        })
+    }
+
+    async fn roll_back(&self, stale: &Checkpoint) -> Result<Progress> {
+        let to = stale.block_number.saturating_sub(self.settings.reorg_depth);
+        log::warn!(
+            "Block {} is no longer {}, rolling back to block {to}",
+            stale.block_number,
+            stale.block_hash
+        );
+
+        let block = self.block(to).await?.with_context(|| missing(to))?;
+        self.chain_repo.rollback(to + 1).await?;
+        self.chain_repo
+            .save_checkpoint(&checkpoint(&block)?)
+            .await?;
+
+        Ok(Progress::RolledBack { to })
+    }
+
+    async fn block(&self, number: u64) -> Result<Option<Block>> {
+        Ok(self.rpc.blocks(&[number]).await?.pop().flatten())
+    }
+}
+
+fn checkpoint(block: &Block) -> Result<Checkpoint> {
+    Ok(Checkpoint {
+        updated_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64,
+        block_number: block.number,
+        block_hash: block.hash.clone(),
+    })
+}
+
+/// Derived from the blocks and logs of `transfers`: the same rows always get
+/// the same token, while rows of a fork that replaced them get another one
+/// even where their values are identical.
+fn dedup_token(transfers: &[ChainTransfer]) -> String {
+    let mut hasher = crc32fast::Hasher::new();
+    for transfer in transfers {
+        hasher.update(transfer.block_hash.as_bytes());
+        hasher.update(&transfer.log_index.to_le_bytes());
+    }
+    format!(
+        "evm-{}-{}-{}-{:08x}",
+        transfers.first().map_or(0, |t| t.block_number),
+        transfers.last().map_or(0, |t| t.block_number),
+        transfers.len(),
+        hasher.finalize()
+    )
+}
+
+fn missing(number: u64) -> String {
+    format!("The node does not have block {number}")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use actix_web::{App, HttpResponse, HttpServer, web};
+    use async_trait::async_trait;
+    use serde_json::{Value, json};
+
+    use super::*;
+    use crate::{
+        domain::{
+            entities::insert_report::InsertReport,
+            repositories::{
+                chain_repo::ChainRepoResult, errors::TransferRepoError,
+                transfer_repo::MockTransferRepoAbstract,
+            },
+        },
+        infrastructure::evm::TRANSFER_TOPIC,
+    };
+
+    const TOKEN: &str = "0x00000000000000000000000000000000000000aa";
+
+    /// Chain served by the mock node: `hashes[n]` is block `n`'s hash and
+    /// `logs` maps a block to the transfer amounts it emitted.
+    #[derive(Default)]
+    struct MockChain {
+        hashes: Vec<String>,
+        logs: BTreeMap<u64, Vec<u64>>,
+    }
+
+    impl MockChain {
+        fn extend(&mut self, fork: &str, blocks: u64) {
+            for _ in 0..blocks {
+                let number = self.hashes.len();
+                self.hashes.push(format!("0x{fork}{number}"));
+            }
+        }
+
+        fn answer(&self, request: &Value) -> Value {
+            let params = &request["params"];
+            let result = match request["method"].as_str().unwrap() {
+                "eth_blockNumber" => json!(format!("{:#x}", self.hashes.len() - 1)),
+                "eth_getBlockByNumber" => {
+                    let number = parse_quantity(params[0].as_str().unwrap()).unwrap();
+                    match self.hashes.get(number as usize) {
+                        Some(hash) => json!({
+                            "number": format!("{number:#x}"),
+                            "hash": hash,
+                            "timestamp": format!("{:#x}", 1_700_000_000 + number * 12),
+                        }),
+                        None => Value::Null,
+                    }
+                }
+                "eth_getLogs" => {
+                    let from = parse_quantity(params[0]["fromBlock"].as_str().unwrap()).unwrap();
+                    let to = parse_quantity(params[0]["toBlock"].as_str().unwrap()).unwrap();
+                    let logs: Vec<Value> = self
+                        .logs
+                        .range(from..=to)
+                        .flat_map(|(number, amounts)| {
+                            amounts.iter().enumerate().map(move |(index, amount)| {
+                                json!({
+                                    "address": TOKEN,
+                                    "topics": [
+                                        TRANSFER_TOPIC,
+                                        format!("0x{:064x}", 0xa1),
+                                        format!("0x{:064x}", 0xb2),
+                                    ],
+                                    "data": format!("0x{amount:064x}"),
+                                    "blockNumber": format!("{number:#x}"),
+                                    "blockHash": self.hashes[*number as usize],
+                                    "transactionHash": format!("0x{number}{index}"),
+                                    "logIndex": format!("{index:#x}"),
+                                })
+                            })
+                        })
+                        .collect();
+                    json!(logs)
+                }
+                method => panic!("unexpected {method}"),
+            };
+            json!({"jsonrpc": "2.0", "id": request["id"], "result": result})
+        }
+    }
+
+    async fn rpc(chain: web::Data<Mutex<MockChain>>, body: web::Json<Value>) -> HttpResponse {
+        let chain = chain.lock().unwrap();
+        let response = match body.into_inner() {
+            Value::Array(requests) => Value::Array(
+                requests
+                    .iter()
+                    .rev()
+                    .map(|request| chain.answer(request))
+                    .collect(),
+            ),
+            request => chain.answer(&request),
+        };
+        HttpResponse::Ok().json(response)
+    }
+
+    /// Serves `chain` on a local port and returns its URL.
+    fn mock_node(chain: web::Data<Mutex<MockChain>>) -> String {
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(chain.clone())
+                .route("/", web::post().to(rpc))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let url = format!("http://{}/", server.addrs()[0]);
+        actix_web::rt::spawn(server.run());
+        url
+    }
+
+    /// Keeps the checkpoint and logs in memory and records rollbacks.
+    #[derive(Default)]
+    struct MemoryChainRepo {
+        checkpoint: Mutex<Option<Checkpoint>>,
+        logs: Mutex<Vec<ChainTransfer>>,
+        rollbacks: Mutex<Vec<u64>>,
+    }
+
+    #[async_trait]
+    impl ChainRepoAbstract for MemoryChainRepo {
+        async fn checkpoint(&self) -> ChainRepoResult<Option<Checkpoint>> {
+            Ok(self.checkpoint.lock().unwrap().clone())
+        }
+
+        async fn save_checkpoint(&self, checkpoint: &Checkpoint) -> ChainRepoResult<()> {
+            *self.checkpoint.lock().unwrap() = Some(checkpoint.clone());
+            Ok(())
+        }
+
+        async fn save_logs(&self, logs: &[ChainTransfer]) -> ChainRepoResult<()> {
+            self.logs.lock().unwrap().extend_from_slice(logs);
+            Ok(())
+        }
+
+        async fn logs_from(&self, from_block: u64) -> ChainRepoResult<Vec<ChainTransfer>> {
+            let logs = self.logs.lock().unwrap();
+            Ok(logs
+                .iter()
+                .filter(|log| log.block_number >= from_block)
+                .cloned()
+                .collect())
+        }
+
+        async fn rollback(&self, from_block: u64) -> ChainRepoResult<()> {
+            self.logs
+                .lock()
+                .unwrap()
+                .retain(|log| log.block_number < from_block);
+            self.rollbacks.lock().unwrap().push(from_block);
+            Ok(())
+        }
+    }
+
+    fn job(
+        url: &str,
+        chain_repo: Arc<MemoryChainRepo>,
+        transfer_repo: MockTransferRepoAbstract,
+    ) -> EvmIngestJob<MemoryChainRepo, MockTransferRepoAbstract> {
+        let settings = EvmSettings {
+            rpc_url: Some(url.to_string()),
+            token_address: TOKEN.to_string(),
+            token_decimals: 0,
+            start_block: Some(1),
+            reorg_depth: 3,
+            max_block_range: 5,
+            ..EvmSettings::default()
+        };
+        EvmIngestJob::new(
+            JsonRpcClient::new(url, Duration::from_secs(5)).unwrap(),
+            chain_repo,
+            Arc::new(transfer_repo),
+            settings,
+        )
+    }
+
+    fn accepting_repo() -> MockTransferRepoAbstract {
+        let mut transfer_repo = MockTransferRepoAbstract::new();
+        transfer_repo
+            .expect_save_chain_transfers()
+            .returning(|_, _| Ok(InsertReport::default()));
+        transfer_repo
+    }
+
+    /// Stands in for the transfers table: inserts are stored once per
+    /// deduplication token, and the first `timeouts` of them time out after
+    /// being stored.
+    fn deduplicating_repo(
+        stored: Arc<Mutex<BTreeMap<String, Vec<ChainTransfer>>>>,
+        timeouts: usize,
+    ) -> MockTransferRepoAbstract {
+        let mut transfer_repo = MockTransferRepoAbstract::new();
+        let mut calls = 0;
+        transfer_repo
+            .expect_save_chain_transfers()
+            .returning(move |transfers, token| {
+                stored
+                    .lock()
+                    .unwrap()
+                    .entry(token.to_string())
+                    .or_insert_with(|| transfers.to_vec());
+                calls += 1;
+                if calls <= timeouts {
+                    return Err(TransferRepoError::TimedOut("insert".to_string()));
+                }
+                Ok(InsertReport::default())
+            });
+        transfer_repo
+    }
+
+    fn amounts(chain_repo: &MemoryChainRepo) -> Vec<(u64, f64)> {
+        let logs = chain_repo.logs.lock().unwrap();
+        logs.iter()
+            .map(|log| (log.block_number, log.amount))
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn test_ingests_ranges_up_to_the_head() {
+        let mut chain = MockChain::default();
+        chain.extend("a", 8);
+        chain.logs.insert(2, vec![10, 0]);
+        chain.logs.insert(6, vec![30]);
+        let url = mock_node(web::Data::new(Mutex::new(chain)));
+        let chain_repo = Arc::new(MemoryChainRepo::default());
+        let mut transfer_repo = MockTransferRepoAbstract::new();
+        transfer_repo
+            .expect_save_chain_transfers()
+            .withf(|transfers, _| {
+                transfers.len() == 1
+                    && transfers[0].ts == 1_700_000_024
+                    && transfers[0].from == format!("0x{:040x}", 0xa1)
+                    && transfers[0].amount == 10.0
+            })
+            .times(1)
+            .returning(|_, _| Ok(InsertReport::default()));
+        transfer_repo
+            .expect_save_chain_transfers()
+            .withf(|transfers, _| transfers.len() == 1 && transfers[0].amount == 30.0)
+            .times(1)
+            .returning(|_, _| Ok(InsertReport::default()));
+        let job = job(&url, chain_repo.clone(), transfer_repo);
+
+        assert_eq!(
+            job.step().await.unwrap(),
+            Progress::Ingested {
+                to: 5,
+                transfers: 1
+            }
+        );
+        assert_eq!(
+            job.step().await.unwrap(),
+            Progress::Ingested {
+                to: 7,
+                transfers: 1
+            }
+        );
+        assert_eq!(job.step().await.unwrap(), Progress::CaughtUp);
+
+        let checkpoint = chain_repo.checkpoint.lock().unwrap().clone().unwrap();
+        assert_eq!(
+            (checkpoint.block_number, checkpoint.block_hash.as_str()),
+            (7, "0xa7")
+        );
+        assert_eq!(amounts(&chain_repo), vec![(2, 10.0), (6, 30.0)]);
+    }
+
+    #[actix_web::test]
+    async fn test_reorg_rolls_back_and_ingests_the_new_fork() {
+        let mut chain = MockChain::default();
+        chain.extend("a", 6);
+        chain.logs.insert(4, vec![40]);
+        chain.logs.insert(5, vec![50]);
+        let chain = web::Data::new(Mutex::new(chain));
+        let url = mock_node(chain.clone());
+        let chain_repo = Arc::new(MemoryChainRepo::default());
+        let job = job(&url, chain_repo.clone(), accepting_repo());
+        job.step().await.unwrap();
+        assert_eq!(amounts(&chain_repo), vec![(4, 40.0), (5, 50.0)]);
+
+        // Blocks 4 and 5 are replaced by a longer fork without block 5's log.
+        {
+            let mut chain = chain.lock().unwrap();
+            chain.hashes.truncate(4);
+            chain.extend("b", 3);
+            chain.logs.remove(&5);
+        }
+
+        assert_eq!(job.step().await.unwrap(), Progress::RolledBack { to: 2 });
+        assert_eq!(*chain_repo.rollbacks.lock().unwrap(), vec![3]);
+        assert!(amounts(&chain_repo).is_empty());
+
+        assert_eq!(
+            job.step().await.unwrap(),
+            Progress::Ingested {
+                to: 6,
+                transfers: 1
+            }
+        );
+        assert_eq!(amounts(&chain_repo), vec![(4, 40.0)]);
+        let checkpoint = chain_repo.checkpoint.lock().unwrap().clone().unwrap();
+        assert_eq!(checkpoint.block_hash, "0xb6");
+    }
+
+    #[actix_web::test]
+    async fn test_retry_after_timed_out_insert_is_deduplicated() {
+        let mut chain = MockChain::default();
+        chain.extend("a", 6);
+        chain.logs.insert(2, vec![20]);
+        chain.logs.insert(3, vec![30, 31]);
+        let url = mock_node(web::Data::new(Mutex::new(chain)));
+        let chain_repo = Arc::new(MemoryChainRepo::default());
+        let stored = Arc::new(Mutex::new(BTreeMap::new()));
+        let job = job(
+            &url,
+            chain_repo.clone(),
+            deduplicating_repo(stored.clone(), 1),
+        );
+
+        // The insert was committed, but the answer never arrived.
+        assert!(job.step().await.is_err());
+        assert!(chain_repo.checkpoint.lock().unwrap().is_none());
+        assert_eq!(stored.lock().unwrap().len(), 1);
+
+        assert_eq!(
+            job.step().await.unwrap(),
+            Progress::Ingested {
+                to: 3,
+                transfers: 3
+            }
+        );
+        assert!(chain_repo.rollbacks.lock().unwrap().is_empty());
+        let stored = stored.lock().unwrap();
+        assert_eq!(stored.len(), 1);
+        let amounts: Vec<f64> = stored.values().flatten().map(|t| t.amount).collect();
+        assert_eq!(amounts, vec![20.0, 30.0, 31.0]);
+        assert_eq!(
+            chain_repo
+                .checkpoint
+                .lock()
+                .unwrap()
+                .clone()
+                .unwrap()
+                .block_number,
+            3
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_unfinished_range_of_a_replaced_fork_is_rolled_back() {
+        let mut chain = MockChain::default();
+        chain.extend("a", 4);
+        chain.logs.insert(2, vec![20]);
+        let chain = web::Data::new(Mutex::new(chain));
+        let url = mock_node(chain.clone());
+        let chain_repo = Arc::new(MemoryChainRepo::default());
+        let stored = Arc::new(Mutex::new(BTreeMap::new()));
+        let job = job(
+            &url,
+            chain_repo.clone(),
+            deduplicating_repo(stored.clone(), 1),
+        );
+
+        assert!(job.step().await.is_err());
+
+        // Block 2 is replaced by one with the same transfer.
+        {
+            let mut chain = chain.lock().unwrap();
+            chain.hashes.truncate(2);
+            chain.extend("b", 2);
+        }
+
+        job.step().await.unwrap();
+        assert_eq!(*chain_repo.rollbacks.lock().unwrap(), vec![1]);
+        assert_eq!(amounts(&chain_repo), vec![(2, 20.0)]);
+        assert_eq!(chain_repo.logs.lock().unwrap()[0].block_hash, "0xb2");
+        // The new fork's insert is not mistaken for a repeat of the old one.
+        assert_eq!(stored.lock().unwrap().len(), 2);
+    }
+}
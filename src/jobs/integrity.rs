@@ -0,0 +1,53 @@
+use crate::{
+    domain::{
+        repositories::transfer_repo::TransferRepoAbstract,
+        services::integrity_service::IntegrityService,
+    },
+    jobs::Job,
+};
+use anyhow::Result;
+use std::sync::Arc;
+
+const SAMPLE_LIMIT: usize = 5;
+
+pub struct IntegrityCheckJob<T: TransferRepoAbstract> {
+    integrity_service: Arc<IntegrityService<T>>,
+}
+
+impl<T: TransferRepoAbstract> IntegrityCheckJob<T> {
+    pub fn new(integrity_service: Arc<IntegrityService<T>>) -> Self {
+        Self { integrity_service }
+    }
+}
+
+impl<T: TransferRepoAbstract> Job for IntegrityCheckJob<T> {
+    async fn run(&self) -> Result<()> {
+        println!("Starting integrity check job...");
+
+        let report = self.integrity_service.check(SAMPLE_LIMIT).await?;
+
+        if report.total_issues() == 0 {
+            println!("Integrity check passed: no issues found");
+            return Ok(());
+        }
+
+        eprintln!(
+            "Integrity check found {} issues: {} negative balances, {} self transfers, \
+             {} invalid amounts, {} invalid prices, {} future timestamps",
+            report.total_issues(),
+            report.negative_balances.count,
+            report.self_transfers.count,
+            report.invalid_amounts.count,
+            report.invalid_prices.count,
+            report.future_timestamps.count,
+        );
+        for sample in &report.negative_balances.samples {
+            eprintln!(
+                "  negative balance: {} reaches {} (first at ts {})",
+                sample.address, sample.min_balance, sample.first_negative_ts
+            );
+        }
+
+        Ok(())
+    }
+}
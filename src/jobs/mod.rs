@@ -1,3 +1,6 @@
+pub mod consumer;
+pub mod evm;
+pub mod integrity;
 pub mod startup;
 
 use anyhow::Result;
@@ -1,36 +1,318 @@
 use crate::{
-    domain::repositories::transfer_repo::TransferRepoAbstract,
-    infrastructure::generator::{TransferGenConfig, TransferGenerator},
+    domain::{
+        entities::{generation::GenerationRun, insert_report::InsertReport},
+        repositories::{
+            errors::TransferRepoError, generation_repo::GenerationRepoAbstract,
+            transfer_repo::TransferRepoAbstract,
+        },
+        validation::rules::AddressFormat,
+    },
+    infrastructure::generator::{GenerationMode, TransferGenConfig, TransferGenerator},
     jobs::Job,
 };
 use anyhow::Result;
-use std::sync::Arc;
-pub struct DataGenerationJob<T: TransferRepoAbstract> {
+use std::{
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+pub struct DataGenerationJob<T: TransferRepoAbstract, G: GenerationRepoAbstract> {
+    source: &'static str,
     count: usize,
+    mode: GenerationMode,
+    generator: TransferGenConfig,
+    address_format: AddressFormat,
     transfer_repo: Arc<T>,
+    generation_repo: Arc<G>,
 }
 
-impl<T: TransferRepoAbstract> DataGenerationJob<T> {
-    pub fn new(count: usize, transfer_repo: Arc<T>) -> Self {
+impl<T: TransferRepoAbstract, G: GenerationRepoAbstract> DataGenerationJob<T, G> {
+    /// `source` is recorded with the run, e.g. `startup`.
+    pub fn new(
+        source: &'static str,
+        count: usize,
+        mode: GenerationMode,
+        generator: TransferGenConfig,
+        address_format: AddressFormat,
+        transfer_repo: Arc<T>,
+        generation_repo: Arc<G>,
+    ) -> Self {
         Self {
+            source,
             count,
+            mode,
+            generator,
+            address_format,
             transfer_repo,
+            generation_repo,
+        }
+    }
+
+    async fn rows_to_generate(&self) -> Result<usize> {
+        let (generated, stored) = match self.mode {
+            GenerationMode::Off | GenerationMode::Always => (0, 0),
+            GenerationMode::Once => (
+                self.generation_repo.generated_rows().await?,
+                self.transfer_repo.transfer_count().await?,
+            ),
+            GenerationMode::TopUp => (self.generation_repo.generated_rows().await?, 0),
+        };
+        let count = self.mode.rows_to_generate(self.count, generated, stored);
+
+        // Replicas starting together all find the database empty; only the
+        // one that claims the run generates.
+        if self.mode == GenerationMode::Once
+            && count > 0
+            && !self.generation_repo.claim("once").await?
+        {
+            return Ok(0);
+        }
+        Ok(count)
+    }
+
+    /// Generates and inserts `count` transfers. Rows committed before a
+    /// failure are still reported, with the failure, so `top_up` doesn't
+    /// generate them again.
+    async fn insert(&self, count: usize) -> Result<(InsertReport, Option<TransferRepoError>)> {
+        let transfers = self.generator.generate(count, self.address_format)?;
+
+        println!("Generated {} transfers", transfers.len());
+
+        match self.transfer_repo.save_all(&transfers).await {
+            Ok(report) => Ok((report, None)),
+            Err(TransferRepoError::PartialInsert { report, source }) => Ok((report, Some(*source))),
+            Err(err) => Err(err.into()),
         }
     }
 }
 
-impl<T: TransferRepoAbstract> Job for DataGenerationJob<T> {
+impl<T: TransferRepoAbstract, G: GenerationRepoAbstract> Job for DataGenerationJob<T, G> {
     async fn run(&self) -> Result<()> {
         println!("Starting data generation job...");
 
-        let generator = TransferGenConfig::default();
-        let transfers = generator.generate(self.count)?;
+        let count = self.rows_to_generate().await?;
+        if count == 0 {
+            println!("Data generation skipped in {:?} mode", self.mode);
+            return Ok(());
+        }
 
-        println!("Generated {} transfers", transfers.len());
+        let (report, failure) = match self.insert(count).await {
+            Ok(inserted) => inserted,
+            Err(err) if self.mode == GenerationMode::Once => {
+                // Nothing was stored, so the next start may claim the run.
+                if let Err(release) = self.generation_repo.release("once").await {
+                    log::warn!("Failed to release the data generation claim: {release}");
+                }
+                return Err(err);
+            }
+            Err(err) => return Err(err),
+        };
+        println!(
+            "Inserted {} transfers in {} chunks",
+            report.rows(),
+            report.chunks.len()
+        );
 
-        self.transfer_repo.save_all(&transfers).await?;
+        self.generation_repo
+            .record(&GenerationRun {
+                ts: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+                source: self.source.to_string(),
+                rows: report.rows() as u64,
+            })
+            .await?;
+        if let Some(err) = failure {
+            return Err(err.into());
+        }
 
         println!("Data generation job completed successfully");
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{
+        entities::insert_report::ChunkReport,
+        repositories::{
+            generation_repo::MockGenerationRepoAbstract, transfer_repo::MockTransferRepoAbstract,
+        },
+    };
+
+    fn job(
+        mode: GenerationMode,
+        transfer_repo: MockTransferRepoAbstract,
+        generation_repo: MockGenerationRepoAbstract,
+    ) -> DataGenerationJob<MockTransferRepoAbstract, MockGenerationRepoAbstract> {
+        DataGenerationJob::new(
+            "startup",
+            10,
+            mode,
+            TransferGenConfig::default(),
+            AddressFormat::Generic,
+            Arc::new(transfer_repo),
+            Arc::new(generation_repo),
+        )
+    }
+
+    #[actix_web::test]
+    async fn test_once_skips_a_database_with_transfers() {
+        let mut transfer_repo = MockTransferRepoAbstract::new();
+        transfer_repo.expect_transfer_count().returning(|| Ok(3));
+        transfer_repo.expect_save_all().never();
+        let mut generation_repo = MockGenerationRepoAbstract::new();
+        generation_repo.expect_generated_rows().returning(|| Ok(0));
+        generation_repo.expect_record().never();
+
+        job(GenerationMode::Once, transfer_repo, generation_repo)
+            .run()
+            .await
+            .unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_once_leaves_a_claimed_run_to_its_owner() {
+        let mut transfer_repo = MockTransferRepoAbstract::new();
+        transfer_repo.expect_transfer_count().returning(|| Ok(0));
+        transfer_repo.expect_save_all().never();
+        let mut generation_repo = MockGenerationRepoAbstract::new();
+        generation_repo.expect_generated_rows().returning(|| Ok(0));
+        generation_repo
+            .expect_claim()
+            .withf(|key| key == "once")
+            .times(1)
+            .returning(|_| Ok(false));
+        generation_repo.expect_record().never();
+
+        job(GenerationMode::Once, transfer_repo, generation_repo)
+            .run()
+            .await
+            .unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_once_releases_the_claim_when_nothing_was_inserted() {
+        let mut transfer_repo = MockTransferRepoAbstract::new();
+        transfer_repo.expect_transfer_count().returning(|| Ok(0));
+        transfer_repo.expect_save_all().times(1).returning(|_| {
+            Err(TransferRepoError::DatabaseConnectionError(
+                "connection refused".to_string(),
+            ))
+        });
+        transfer_repo
+            .expect_save_all()
+            .times(1)
+            .returning(|transfers| {
+                Ok(InsertReport {
+                    chunks: vec![ChunkReport {
+                        index: 0,
+                        rows: transfers.len(),
+                        bytes: 0,
+                    }],
+                })
+            });
+        // A claim is won by the first claimer of each round; a release
+        // starts the next round.
+        let claims = Arc::new(std::sync::Mutex::new((0, false)));
+        let mut generation_repo = MockGenerationRepoAbstract::new();
+        generation_repo.expect_generated_rows().returning(|| Ok(0));
+        let claimed = claims.clone();
+        generation_repo.expect_claim().returning(move |_| {
+            let mut claims = claimed.lock().unwrap();
+            let won = !claims.1;
+            claims.1 = true;
+            Ok(won)
+        });
+        let released = claims.clone();
+        generation_repo
+            .expect_release()
+            .withf(|key| key == "once")
+            .times(1)
+            .returning(move |_| {
+                *released.lock().unwrap() = (1, false);
+                Ok(())
+            });
+        generation_repo
+            .expect_record()
+            .withf(|run| run.rows == 10)
+            .times(1)
+            .returning(|_| Ok(()));
+        let job = job(GenerationMode::Once, transfer_repo, generation_repo);
+
+        assert!(job.run().await.is_err());
+        // The restart claims the run again instead of skipping it.
+        job.run().await.unwrap();
+        assert_eq!(*claims.lock().unwrap(), (1, true));
+    }
+
+    #[actix_web::test]
+    async fn test_partial_insert_records_the_committed_rows() {
+        let mut transfer_repo = MockTransferRepoAbstract::new();
+        transfer_repo.expect_save_all().times(1).returning(|_| {
+            Err(TransferRepoError::PartialInsert {
+                report: InsertReport {
+                    chunks: vec![ChunkReport {
+                        index: 0,
+                        rows: 3,
+                        bytes: 0,
+                    }],
+                },
+                source: Box::new(TransferRepoError::TimedOut("slow".to_string())),
+            })
+        });
+        let mut generation_repo = MockGenerationRepoAbstract::new();
+        generation_repo.expect_generated_rows().returning(|| Ok(0));
+        generation_repo
+            .expect_record()
+            .withf(|run| run.rows == 3)
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let result = job(GenerationMode::TopUp, transfer_repo, generation_repo)
+            .run()
+            .await;
+
+        assert!(result.is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_top_up_generates_the_difference_and_records_it() {
+        let mut transfer_repo = MockTransferRepoAbstract::new();
+        transfer_repo
+            .expect_save_all()
+            .withf(|transfers| transfers.len() == 4)
+            .times(1)
+            .returning(|transfers| {
+                Ok(InsertReport {
+                    chunks: vec![ChunkReport {
+                        index: 0,
+                        rows: transfers.len(),
+                        bytes: 0,
+                    }],
+                })
+            });
+        let mut generation_repo = MockGenerationRepoAbstract::new();
+        generation_repo.expect_generated_rows().returning(|| Ok(6));
+        generation_repo
+            .expect_record()
+            .withf(|run| run.rows == 4 && run.source == "startup")
+            .times(1)
+            .returning(|_| Ok(()));
+
+        job(GenerationMode::TopUp, transfer_repo, generation_repo)
+            .run()
+            .await
+            .unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_off_does_not_touch_the_database() {
+        job(
+            GenerationMode::Off,
+            MockTransferRepoAbstract::new(),
+            MockGenerationRepoAbstract::new(),
+        )
+        .run()
+        .await
+        .unwrap();
+    }
+}
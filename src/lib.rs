@@ -1,6 +1,12 @@
+use std::time::Duration;
+
 use anyhow::Result;
-use config::Config;
-use infrastructure::app_setup::{AppDependencies, server};
+use config::{Config, sources::ConfigArgs};
+use infrastructure::{
+    app_setup::{AppDependencies, server},
+    logging,
+    reload::watch_config,
+};
 
 pub mod config;
 pub mod domain;
@@ -9,9 +15,30 @@ mod jobs;
 pub mod presentation;
 
 pub async fn run(config: &Config) -> Result<()> {
+    serve(config, None).await
+}
+
+/// Like [`run`], reloading the config from the sources in `args` on
+/// `SIGHUP` and when the config file changes.
+pub async fn run_with_reload(config: &Config, args: ConfigArgs) -> Result<()> {
+    serve(config, Some(args)).await
+}
+
+async fn serve(config: &Config, reload: Option<ConfigArgs>) -> Result<()> {
+    logging::init(&config.server.log_level);
     let deps = AppDependencies::init(config).await?;
 
-    server(deps.app_state, &config.port).await?;
+    if let Some(args) = reload {
+        let watch_every = (config.server.config_watch_secs > 0)
+            .then(|| Duration::from_secs(config.server.config_watch_secs));
+        actix_web::rt::spawn(watch_config(
+            args,
+            deps.app_state.runtime.clone(),
+            watch_every,
+        ));
+    }
+
+    server(deps.app_state, &config.server).await?;
 
     Ok(())
 }
@@ -1,13 +1,10 @@
-use anyhow::Context;
 use anyhow::Result;
-use rust_challenge::{config::Config, run};
+use clap::Parser;
+use rust_challenge::presentation::cli::Cli;
 
 #[actix_web::main]
 async fn main() -> Result<()> {
-    let config = Config::from_env().context("Failed to load config from environment")?;
-
-    run(&config).await?;
-    Ok(())
+    Cli::parse().run().await
 }
 
 //check coommit
@@ -0,0 +1,315 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use clap::{Parser, Subcommand, ValueEnum};
+use futures::{Stream, StreamExt};
+
+use crate::{
+    config::{Config, sources::ConfigArgs},
+    domain::{
+        entities::{generation::GenerationRun, insert_report::InsertReport, transfer::Transfer},
+        repositories::{
+            generation_repo::GenerationRepoAbstract, transfer_repo::TransferRepoAbstract,
+        },
+    },
+    infrastructure::{app_setup::AppDependencies, generator::TransferGenerator, logging},
+    presentation::shared::streaming::{BoxError, json_array, ndjson},
+    run_with_reload,
+};
+
+#[derive(Debug, Parser)]
+#[command(about = "Token transfer analytics server")]
+pub struct Cli {
+    #[command(flatten)]
+    pub config: ConfigArgs,
+    /// Print the effective config, with secrets redacted, and exit.
+    #[arg(long, global = true)]
+    pub print_config: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the HTTP server; the default when no command is given.
+    Serve,
+    /// Create the database and tables that don't exist yet.
+    Migrate,
+    /// Generate transfers into ClickHouse, or into an NDJSON file.
+    Generate {
+        /// Defaults to `jobs.data_generation_count`.
+        #[arg(long)]
+        count: Option<usize>,
+        /// Makes the generated transfers repeatable.
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Write the transfers here instead of inserting them.
+        #[arg(long, value_name = "PATH")]
+        out: Option<PathBuf>,
+    },
+    /// Insert transfers from an NDJSON file, or `-` for stdin.
+    Import { file: PathBuf },
+    /// Write the per-user stats to stdout or a file.
+    ExportStats {
+        #[arg(long, value_enum, default_value_t)]
+        format: ExportFormat,
+        #[arg(long, value_name = "PATH")]
+        out: Option<PathBuf>,
+    },
+    /// Merge the tables so replaced labels and prices are dropped on disk.
+    Recompute,
+    /// Run the integrity checks, exiting with an error when any fail.
+    CheckIntegrity {
+        /// Offending rows to show per check.
+        #[arg(long, default_value_t = 5)]
+        samples: usize,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum ExportFormat {
+    #[default]
+    Json,
+    Ndjson,
+}
+
+impl Cli {
+    pub async fn run(self) -> Result<()> {
+        let config = Config::load(&self.config).context("Failed to load config")?;
+
+        if self.print_config {
+            print!("{}", config.redacted().to_toml()?);
+            return Ok(());
+        }
+
+        logging::init(&config.server.log_level);
+        match self.command.unwrap_or(Command::Serve) {
+            Command::Serve => run_with_reload(&config, self.config).await,
+            Command::Migrate => {
+                AppDependencies::connect(&config).await?.migrate().await?;
+                eprintln!("Tables are up to date");
+                Ok(())
+            }
+            Command::Generate { count, seed, out } => {
+                let count = count.unwrap_or(config.jobs.data_generation_count);
+                generate(&config, count, seed, out.as_deref()).await
+            }
+            Command::Import { file } => import(&config, &file).await,
+            Command::ExportStats { format, out } => {
+                export_stats(&config, format, out.as_deref()).await
+            }
+            Command::Recompute => {
+                AppDependencies::connect(&config).await?.optimize().await?;
+                eprintln!("Merged the labels and prices tables");
+                Ok(())
+            }
+            Command::CheckIntegrity { samples } => check_integrity(&config, samples).await,
+        }
+    }
+}
+
+async fn generate(
+    config: &Config,
+    count: usize,
+    seed: Option<u64>,
+    out: Option<&Path>,
+) -> Result<()> {
+    let (generator, format) = (&config.generator, config.validation.address_format);
+    let transfers = match seed {
+        Some(seed) => generator.generate_seeded(count, format, seed)?,
+        None => generator.generate(count, format)?,
+    };
+
+    let Some(path) = out else {
+        let deps = AppDependencies::connect(config).await?;
+        deps.migrate().await?;
+        let report = deps.transfer_repo.save_all(&transfers).await?;
+        deps.generation_repo
+            .record(&GenerationRun {
+                ts: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+                source: "cli".to_string(),
+                rows: report.rows() as u64,
+            })
+            .await?;
+        print_inserted(&report);
+        return Ok(());
+    };
+
+    let mut writer = BufWriter::new(create(path)?);
+    for transfer in &transfers {
+        serde_json::to_writer(&mut writer, transfer)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+    eprintln!("Wrote {} transfers to {}", transfers.len(), path.display());
+    Ok(())
+}
+
+async fn import(config: &Config, file: &Path) -> Result<()> {
+    let reader: Box<dyn BufRead> = if file == Path::new("-") {
+        Box::new(io::stdin().lock())
+    } else {
+        let opened =
+            File::open(file).with_context(|| format!("Failed to open {}", file.display()))?;
+        Box::new(BufReader::new(opened))
+    };
+
+    let deps = AppDependencies::connect(config).await?;
+    deps.migrate().await?;
+
+    // Enough rows for every parallel insert to get a full chunk.
+    let insert = &config.clickhouse.insert;
+    let batch_rows = insert.max_rows.max(1) * insert.parallelism.max(1);
+    let mut batch = Vec::with_capacity(batch_rows.min(10_000));
+    let mut total = InsertReport::default();
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let transfer: Transfer = serde_json::from_str(&line)
+            .with_context(|| format!("Invalid transfer on line {}", index + 1))?;
+        batch.push(transfer);
+
+        if batch.len() >= batch_rows {
+            save_batch(&deps, &mut batch, &mut total).await?;
+        }
+    }
+    save_batch(&deps, &mut batch, &mut total).await?;
+
+    print_inserted(&total);
+    Ok(())
+}
+
+async fn save_batch(
+    deps: &AppDependencies,
+    batch: &mut Vec<Transfer>,
+    total: &mut InsertReport,
+) -> Result<()> {
+    let report = deps
+        .transfer_repo
+        .save_all(batch)
+        .await
+        .with_context(|| format!("Failed after importing {} transfers", total.rows()))?;
+    total.chunks.extend(report.chunks);
+    batch.clear();
+    Ok(())
+}
+
+async fn export_stats(config: &Config, format: ExportFormat, out: Option<&Path>) -> Result<()> {
+    let deps = AppDependencies::connect(config).await?;
+    let rows = deps.app_state.stats_service.stream_user_stats();
+
+    let writer: Box<dyn Write> = match out {
+        Some(path) => Box::new(create(path)?),
+        None => Box::new(io::stdout().lock()),
+    };
+    match format {
+        ExportFormat::Json => {
+            let body = json_array(rows).chain(futures::stream::once(async {
+                Ok(actix_web::web::Bytes::from_static(b"\n"))
+            }));
+            write_body(body, writer).await
+        }
+        ExportFormat::Ndjson => write_body(ndjson(rows), writer).await,
+    }
+}
+
+async fn write_body(
+    body: impl Stream<Item = Result<actix_web::web::Bytes, BoxError>>,
+    writer: impl Write,
+) -> Result<()> {
+    let mut writer = BufWriter::new(writer);
+    let mut body = std::pin::pin!(body);
+    while let Some(chunk) = body.next().await {
+        let chunk = chunk.map_err(|err| anyhow!("Failed to export stats: {err}"))?;
+        writer.write_all(&chunk)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+async fn check_integrity(config: &Config, samples: usize) -> Result<()> {
+    let deps = AppDependencies::connect(config).await?;
+    let report = deps.app_state.integrity_service.check(samples).await?;
+
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    if report.total_issues() > 0 {
+        bail!("Integrity check found {} issues", report.total_issues());
+    }
+    Ok(())
+}
+
+fn create(path: &Path) -> Result<File> {
+    File::create(path).with_context(|| format!("Failed to create {}", path.display()))
+}
+
+fn print_inserted(report: &InsertReport) {
+    eprintln!(
+        "Inserted {} transfers in {} chunks",
+        report.rows(),
+        report.chunks.len()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serve_is_the_default() {
+        let cli = Cli::try_parse_from(["rust_challenge", "--port", "9000"]).unwrap();
+
+        assert!(cli.command.is_none());
+        assert_eq!(cli.config.port, Some(9000));
+    }
+
+    #[test]
+    fn test_config_flags_follow_the_command() {
+        let cli = Cli::try_parse_from([
+            "rust_challenge",
+            "generate",
+            "--count",
+            "5",
+            "--seed",
+            "1",
+            "--set",
+            "jobs.integrity_check=false",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Some(Command::Generate {
+                count, seed, out, ..
+            }) => {
+                assert_eq!(count, Some(5));
+                assert_eq!(seed, Some(1));
+                assert!(out.is_none());
+            }
+            other => panic!("expected generate, got {other:?}"),
+        }
+        assert_eq!(cli.config.overrides.len(), 1);
+    }
+
+    #[test]
+    fn test_export_format_values() {
+        let cli =
+            Cli::try_parse_from(["rust_challenge", "export-stats", "--format", "ndjson"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::ExportStats {
+                format: ExportFormat::Ndjson,
+                ..
+            })
+        ));
+        assert!(
+            Cli::try_parse_from(["rust_challenge", "export-stats", "--format", "csv"]).is_err()
+        );
+    }
+}
@@ -0,0 +1,28 @@
+use actix_web::{HttpResponse, Responder, get, web};
+
+use crate::{domain::services::errors::TransferError, presentation::shared::app_state::AppState};
+
+pub fn admin_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/v1/admin")
+            .service(get_config)
+            .service(get_buffer),
+    );
+}
+
+/// Active config revision: the reloadable settings in effect and what the
+/// last reload changed.
+#[get("/config")]
+async fn get_config(app_state: web::Data<AppState>) -> Result<impl Responder, TransferError> {
+    let revision = app_state.runtime.current();
+    Ok(HttpResponse::Ok().json(&*revision))
+}
+
+/// Write buffer depth and flusher progress; `404` when the buffer is off.
+#[get("/buffer")]
+async fn get_buffer(app_state: web::Data<AppState>) -> Result<impl Responder, TransferError> {
+    Ok(match &app_state.write_buffer {
+        Some(buffer) => HttpResponse::Ok().json(buffer.stats()),
+        None => HttpResponse::NotFound().finish(),
+    })
+}
@@ -0,0 +1,95 @@
+use actix_web::{HttpResponse, Responder, get, web};
+use serde::Deserialize;
+
+use crate::{
+    domain::{
+        analysis::wash_trading::WashTradingConfig, entities::time_range::TimeRange,
+        services::errors::TransferError,
+    },
+    presentation::shared::app_state::AppState,
+};
+
+const DEFAULT_TOP_HOLDERS: usize = 10;
+const MAX_TOP_HOLDERS: usize = 1000;
+
+pub fn analysis_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/v1/analysis")
+            .service(get_wash_trading)
+            .service(get_holders),
+    );
+}
+
+#[derive(Debug, Deserialize)]
+struct WashTradingQuery {
+    from_ts: Option<u64>,
+    to_ts: Option<u64>,
+    window_secs: Option<u64>,
+    amount_tolerance: Option<f64>,
+    min_score: Option<f64>,
+}
+
+impl WashTradingQuery {
+    fn config(&self) -> Result<WashTradingConfig, TransferError> {
+        let defaults = WashTradingConfig::default();
+        let config = WashTradingConfig {
+            window_secs: self.window_secs.unwrap_or(defaults.window_secs),
+            amount_tolerance: self.amount_tolerance.unwrap_or(defaults.amount_tolerance),
+        };
+        if config.window_secs == 0 {
+            return Err(TransferError::InvalidInput(
+                "`window_secs` must be positive".to_string(),
+            ));
+        }
+        // A NaN tolerance would silently match nothing.
+        if !(config.amount_tolerance.is_finite() && config.amount_tolerance >= 0.0) {
+            return Err(TransferError::InvalidInput(
+                "`amount_tolerance` must be a non-negative number".to_string(),
+            ));
+        }
+        Ok(config)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct HoldersQuery {
+    at: Option<u64>,
+    top: Option<usize>,
+}
+
+#[get("/wash_trading")]
+async fn get_wash_trading(
+    app_state: web::Data<AppState>,
+    query: web::Query<WashTradingQuery>,
+) -> Result<impl Responder, TransferError> {
+    let config = query.config()?;
+    let range = TimeRange::new(query.from_ts, query.to_ts);
+
+    let mut report = app_state
+        .analysis_service
+        .wash_trading_report(&range, &config)
+        .await?;
+
+    if let Some(min_score) = query.min_score {
+        report.retain(|score| score.suspicion_score >= min_score);
+    }
+
+    Ok(HttpResponse::Ok().json(report))
+}
+
+#[get("/holders")]
+async fn get_holders(
+    app_state: web::Data<AppState>,
+    query: web::Query<HoldersQuery>,
+) -> Result<impl Responder, TransferError> {
+    let top_n = query
+        .top
+        .unwrap_or(DEFAULT_TOP_HOLDERS)
+        .min(MAX_TOP_HOLDERS);
+
+    let distribution = app_state
+        .analysis_service
+        .holder_distribution(query.at, top_n)
+        .await?;
+    Ok(HttpResponse::Ok().json(distribution))
+}
@@ -0,0 +1,13 @@
+use actix_web::{HttpResponse, Responder, get, web};
+
+use crate::{domain::services::errors::TransferError, presentation::shared::app_state::AppState};
+
+pub fn cluster_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/api/v1/clusters").service(get_clusters));
+}
+
+#[get("")]
+async fn get_clusters(app_state: web::Data<AppState>) -> Result<impl Responder, TransferError> {
+    let clusters = app_state.cluster_service.clusters().await?;
+    Ok(HttpResponse::Ok().json(clusters))
+}
@@ -0,0 +1,90 @@
+use actix_web::{HttpResponse, Responder, get, web};
+use serde::Deserialize;
+
+use crate::{
+    domain::{
+        entities::{counterparty::CounterpartySort, graph::GraphFilter},
+        services::errors::TransferError,
+    },
+    presentation::shared::{app_state::AppState, graph_export::to_graphml},
+};
+
+const DEFAULT_COUNTERPARTY_LIMIT: usize = 10;
+const MAX_COUNTERPARTY_LIMIT: usize = 1000;
+/// `true` when the export left out lighter edges.
+const TRUNCATED_HEADER: &str = "X-Graph-Truncated";
+
+pub fn graph_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/v1/graph")
+            .service(get_counterparties)
+            .service(export_graph),
+    );
+}
+
+#[derive(Debug, Deserialize)]
+struct CounterpartiesQuery {
+    #[serde(default)]
+    sort: CounterpartySort,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ExportFormat {
+    #[default]
+    Json,
+    Graphml,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExportQuery {
+    #[serde(default)]
+    format: ExportFormat,
+    from_ts: Option<u64>,
+    to_ts: Option<u64>,
+    min_volume: Option<f64>,
+    limit: Option<usize>,
+}
+
+#[get("/counterparties/{address}")]
+async fn get_counterparties(
+    app_state: web::Data<AppState>,
+    address: web::Path<String>,
+    query: web::Query<CounterpartiesQuery>,
+) -> Result<impl Responder, TransferError> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_COUNTERPARTY_LIMIT)
+        .min(MAX_COUNTERPARTY_LIMIT);
+
+    let counterparties = app_state
+        .graph_service
+        .top_counterparties(&address, query.sort, limit)
+        .await?;
+    Ok(HttpResponse::Ok().json(counterparties))
+}
+
+#[get("/export")]
+async fn export_graph(
+    app_state: web::Data<AppState>,
+    query: web::Query<ExportQuery>,
+) -> Result<impl Responder, TransferError> {
+    let filter = GraphFilter {
+        from_ts: query.from_ts,
+        to_ts: query.to_ts,
+        min_volume: query.min_volume,
+        limit: query.limit,
+    };
+    let graph = app_state.graph_service.transfer_graph(&filter).await?;
+
+    let mut response = HttpResponse::Ok();
+    response.insert_header((TRUNCATED_HEADER, graph.truncated.to_string()));
+    let response = match query.format {
+        ExportFormat::Json => response.json(graph.edges),
+        ExportFormat::Graphml => response
+            .content_type("application/graphml+xml")
+            .body(to_graphml(&graph.edges)),
+    };
+    Ok(response)
+}
@@ -0,0 +1,23 @@
+use actix_web::{HttpResponse, Responder, get, web};
+use serde_json::json;
+
+use crate::presentation::shared::app_state::AppState;
+
+pub fn health_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/api/v1/health").service(live).service(ready));
+}
+
+#[get("/live")]
+async fn live() -> impl Responder {
+    HttpResponse::Ok().json(json!({ "status": "ok" }))
+}
+
+/// Fails as soon as shutdown begins, while requests are still served.
+#[get("/ready")]
+async fn ready(app_state: web::Data<AppState>) -> impl Responder {
+    if app_state.shutdown.is_draining() {
+        HttpResponse::ServiceUnavailable().json(json!({ "status": "draining" }))
+    } else {
+        HttpResponse::Ok().json(json!({ "status": "ready" }))
+    }
+}
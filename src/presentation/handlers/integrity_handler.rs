@@ -0,0 +1,26 @@
+use actix_web::{HttpResponse, Responder, get, web};
+use serde::Deserialize;
+
+use crate::{domain::services::errors::TransferError, presentation::shared::app_state::AppState};
+
+const DEFAULT_SAMPLES: usize = 10;
+const MAX_SAMPLES: usize = 1000;
+
+pub fn integrity_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/api/v1/integrity").service(get_report));
+}
+
+#[derive(Debug, Deserialize)]
+struct ReportQuery {
+    samples: Option<usize>,
+}
+
+#[get("")]
+async fn get_report(
+    app_state: web::Data<AppState>,
+    query: web::Query<ReportQuery>,
+) -> Result<impl Responder, TransferError> {
+    let samples = query.samples.unwrap_or(DEFAULT_SAMPLES).min(MAX_SAMPLES);
+    let report = app_state.integrity_service.check(samples).await?;
+    Ok(HttpResponse::Ok().json(report))
+}
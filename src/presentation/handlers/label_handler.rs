@@ -0,0 +1,93 @@
+use actix_web::{HttpResponse, Responder, delete, get, post, put, web};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::{
+        entities::label::{AddressLabel, LabelCategory},
+        services::errors::TransferError,
+    },
+    presentation::shared::app_state::AppState,
+};
+
+pub fn label_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/v1/labels")
+            .service(import_labels)
+            .service(list_labels)
+            .service(get_label)
+            .service(put_label)
+            .service(delete_label),
+    );
+}
+
+#[derive(Debug, Deserialize)]
+struct ListQuery {
+    category: Option<LabelCategory>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LabelBody {
+    name: String,
+    category: LabelCategory,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ImportResponse {
+    imported: usize,
+}
+
+#[get("")]
+async fn list_labels(
+    app_state: web::Data<AppState>,
+    query: web::Query<ListQuery>,
+) -> Result<impl Responder, TransferError> {
+    let labels = app_state.label_service.list(query.category).await?;
+    Ok(HttpResponse::Ok().json(labels))
+}
+
+#[get("/{address}")]
+async fn get_label(
+    app_state: web::Data<AppState>,
+    address: web::Path<String>,
+) -> Result<impl Responder, TransferError> {
+    let label = app_state.label_service.get(&address).await?;
+    Ok(HttpResponse::Ok().json(label))
+}
+
+#[put("/{address}")]
+async fn put_label(
+    app_state: web::Data<AppState>,
+    address: web::Path<String>,
+    body: web::Json<LabelBody>,
+) -> Result<impl Responder, TransferError> {
+    let body = body.into_inner();
+    let label = AddressLabel {
+        address: address.into_inner(),
+        name: body.name,
+        category: body.category,
+        tags: body.tags,
+    };
+
+    let label = app_state.label_service.upsert(label).await?;
+    Ok(HttpResponse::Ok().json(label))
+}
+
+#[delete("/{address}")]
+async fn delete_label(
+    app_state: web::Data<AppState>,
+    address: web::Path<String>,
+) -> Result<impl Responder, TransferError> {
+    app_state.label_service.delete(&address).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[post("/import")]
+async fn import_labels(
+    app_state: web::Data<AppState>,
+    body: String,
+) -> Result<impl Responder, TransferError> {
+    let imported = app_state.label_service.import_csv(&body).await?;
+    Ok(HttpResponse::Ok().json(ImportResponse { imported }))
+}
@@ -0,0 +1,34 @@
+use actix_web::{HttpResponse, Responder, get, web};
+use serde::Deserialize;
+
+use crate::{
+    domain::{
+        entities::{candle::CandleInterval, time_range::TimeRange},
+        services::errors::TransferError,
+    },
+    presentation::shared::app_state::AppState,
+};
+
+pub fn market_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/api/v1/market").service(get_candles));
+}
+
+#[derive(Debug, Deserialize)]
+struct CandlesQuery {
+    interval: CandleInterval,
+    from: Option<u64>,
+    to: Option<u64>,
+}
+
+#[get("/candles")]
+async fn get_candles(
+    app_state: web::Data<AppState>,
+    query: web::Query<CandlesQuery>,
+) -> Result<impl Responder, TransferError> {
+    let range = TimeRange::new(query.from, query.to);
+    let candles = app_state
+        .market_service
+        .candles(query.interval, &range)
+        .await?;
+    Ok(HttpResponse::Ok().json(candles))
+}
@@ -1 +1,12 @@
+pub mod admin_handler;
+pub mod analysis_handler;
+pub mod cluster_handler;
+pub mod graph_handler;
+pub mod health_handler;
+pub mod integrity_handler;
+pub mod label_handler;
+pub mod market_handler;
+pub mod price_handler;
 pub mod stats_handler;
+pub mod stream_handler;
+pub mod transfer_handler;
@@ -0,0 +1,85 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::{HttpResponse, Responder, get, post, web};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::{
+        entities::{price::PricePoint, time_range::TimeRange},
+        services::errors::TransferError,
+    },
+    presentation::shared::app_state::AppState,
+};
+
+pub fn price_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/v1/prices")
+            .service(get_series)
+            .service(get_price)
+            .service(upload_prices),
+    );
+}
+
+#[derive(Debug, Deserialize)]
+struct PriceSample {
+    ts: u64,
+    price: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct PriceAtQuery {
+    at: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+struct UploadResponse {
+    uploaded: usize,
+}
+
+#[post("/{token}")]
+async fn upload_prices(
+    app_state: web::Data<AppState>,
+    token: web::Path<String>,
+    body: web::Json<Vec<PriceSample>>,
+) -> Result<impl Responder, TransferError> {
+    let prices: Vec<PricePoint> = body
+        .into_inner()
+        .into_iter()
+        .map(|sample| PricePoint {
+            token: token.clone(),
+            ts: sample.ts,
+            price: sample.price,
+        })
+        .collect();
+
+    let uploaded = app_state.price_service.upload(&prices).await?;
+    Ok(HttpResponse::Ok().json(UploadResponse { uploaded }))
+}
+
+#[get("/{token}")]
+async fn get_price(
+    app_state: web::Data<AppState>,
+    token: web::Path<String>,
+    query: web::Query<PriceAtQuery>,
+) -> Result<impl Responder, TransferError> {
+    let at = match query.at {
+        Some(at) => at,
+        None => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|now| now.as_secs())
+            .unwrap_or(u64::MAX),
+    };
+
+    let price = app_state.price_service.price_at(&token, at).await?;
+    Ok(HttpResponse::Ok().json(price))
+}
+
+#[get("/{token}/series")]
+async fn get_series(
+    app_state: web::Data<AppState>,
+    token: web::Path<String>,
+    range: web::Query<TimeRange>,
+) -> Result<impl Responder, TransferError> {
+    let prices = app_state.price_service.prices(&token, &range).await?;
+    Ok(HttpResponse::Ok().json(prices))
+}
@@ -1,13 +1,182 @@
 use actix_web::{HttpResponse, Responder, get, web};
+use futures::{Stream, StreamExt, TryStreamExt, stream};
+use serde::{Deserialize, Serialize};
 
-use crate::{domain::services::errors::TransferError, presentation::shared::app_state::AppState};
+use crate::{
+    domain::{
+        analysis::wash_trading::WashTradingConfig,
+        entities::{
+            label::LabelCategory, price::PriceSource, time_range::TimeRange,
+            user_stats::EnrichedUserStats,
+        },
+        services::errors::TransferError,
+    },
+    presentation::shared::{
+        app_state::AppState,
+        streaming::{BoxError, json_array, ndjson},
+    },
+};
 
 pub fn stats_routes(cfg: &mut web::ServiceConfig) {
-    cfg.service(web::scope("/api/v1/stats").service(get_all));
+    cfg.service(
+        web::scope("/api/v1/stats")
+            .service(get_all)
+            .service(get_summary),
+    );
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum GroupBy {
+    #[default]
+    Address,
+    Cluster,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum OutputFormat {
+    #[default]
+    Json,
+    Ndjson,
+}
+
+impl OutputFormat {
+    fn respond<S, T, E>(self, rows: S) -> HttpResponse
+    where
+        S: Stream<Item = Result<T, E>> + 'static,
+        T: Serialize + 'static,
+        E: Into<BoxError> + 'static,
+    {
+        match self {
+            OutputFormat::Json => HttpResponse::Ok()
+                .content_type("application/json")
+                .streaming(json_array(rows)),
+            OutputFormat::Ndjson => HttpResponse::Ok()
+                .content_type("application/x-ndjson")
+                .streaming(ndjson(rows)),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GetAllQuery {
+    #[serde(default)]
+    format: OutputFormat,
+    #[serde(default)]
+    group_by: GroupBy,
+    #[serde(default)]
+    with_suspicion: bool,
+    #[serde(default)]
+    with_labels: bool,
+    /// Comma-separated label categories, e.g. `exchange,contract`.
+    exclude_categories: Option<String>,
+    #[serde(default)]
+    price_source: PriceSource,
+    /// Oracle token, required with `price_source=oracle`.
+    token: Option<String>,
 }
 
 #[get("/get_all")]
-async fn get_all(app_state: web::Data<AppState>) -> Result<impl Responder, TransferError> {
-    let stats = app_state.stats_service.calculate_user_stats().await?;
-    Ok(HttpResponse::Ok().json(stats))
+async fn get_all(
+    app_state: web::Data<AppState>,
+    query: web::Query<GetAllQuery>,
+) -> Result<impl Responder, TransferError> {
+    if query.group_by == GroupBy::Cluster {
+        // Cluster rows have no single address to label, score or value
+        // with an address's oracle history.
+        let unsupported = [
+            (
+                "price_source=oracle",
+                query.price_source != PriceSource::Transfer,
+            ),
+            ("with_suspicion", query.with_suspicion),
+            ("with_labels", query.with_labels),
+            ("exclude_categories", query.exclude_categories.is_some()),
+        ];
+        if let Some((option, _)) = unsupported.iter().find(|(_, set)| *set) {
+            return Err(TransferError::InvalidInput(format!(
+                "`{option}` is not supported with group_by=cluster"
+            )));
+        }
+
+        let stats = app_state.cluster_service.cluster_stats().await?;
+        return Ok(query
+            .format
+            .respond(stream::iter(stats.into_iter().map(Ok::<_, TransferError>))));
+    }
+
+    let exclude = parse_categories(query.exclude_categories.as_deref())?;
+
+    // Without enrichment rows go straight from the ClickHouse cursor to the
+    // client, which keeps memory flat for any number of addresses.
+    let plain = query.price_source == PriceSource::Transfer
+        && !query.with_suspicion
+        && !query.with_labels
+        && exclude.is_empty();
+    if plain {
+        let mut rows = app_state.stats_service.stream_user_stats();
+        // Waiting for the first row lets an unavailable database still be
+        // answered with a proper error instead of a truncated `200`.
+        let first = rows.try_next().await?;
+        let rows = stream::iter(first.map(Ok))
+            .chain(rows)
+            .inspect_err(|error| {
+                log::warn!("Streaming user stats failed: {error}");
+            });
+        return Ok(query.format.respond(rows));
+    }
+
+    let stats = match query.price_source {
+        PriceSource::Transfer => app_state.stats_service.calculate_user_stats().await?,
+        PriceSource::Oracle => {
+            let token = query.token.as_deref().ok_or_else(|| {
+                TransferError::InvalidInput("`token` is required with price_source=oracle".into())
+            })?;
+            app_state
+                .stats_service
+                .calculate_user_stats_with_prices(token)
+                .await?
+        }
+    };
+
+    let mut stats: Vec<EnrichedUserStats> =
+        stats.into_iter().map(EnrichedUserStats::from).collect();
+
+    if query.with_suspicion {
+        let scores = app_state
+            .analysis_service
+            .suspicion_scores(&WashTradingConfig::default())
+            .await?;
+        for item in &mut stats {
+            item.suspicion_score = Some(scores.get(&item.stats.address).copied().unwrap_or(0.0));
+        }
+    }
+
+    let stats = app_state
+        .label_service
+        .apply_labels(stats, query.with_labels, &exclude)
+        .await?;
+
+    Ok(query
+        .format
+        .respond(stream::iter(stats.into_iter().map(Ok::<_, TransferError>))))
+}
+
+#[get("/summary")]
+async fn get_summary(
+    app_state: web::Data<AppState>,
+    range: web::Query<TimeRange>,
+) -> Result<impl Responder, TransferError> {
+    let summary = app_state.stats_service.summary(&range).await?;
+    Ok(HttpResponse::Ok().json(summary))
+}
+
+fn parse_categories(value: Option<&str>) -> Result<Vec<LabelCategory>, TransferError> {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .filter(|category| !category.trim().is_empty())
+        .map(|category| category.parse().map_err(TransferError::InvalidInput))
+        .collect()
 }
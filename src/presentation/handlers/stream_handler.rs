@@ -0,0 +1,198 @@
+use std::time::Duration;
+
+use actix_web::{
+    HttpRequest, HttpResponse, Responder, get,
+    http::header,
+    rt::time::{Instant, interval_at},
+    web,
+};
+use actix_ws::{CloseCode, CloseReason, Message, MessageStream, Session};
+use futures::{Stream, stream};
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    domain::services::{
+        errors::TransferError,
+        stream_service::{StreamEvent, StreamFilter, Subscription},
+    },
+    presentation::shared::{
+        app_state::AppState,
+        streaming::{BoxError, sse_event},
+    },
+};
+
+pub fn stream_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/api/v1/stream").service(subscribe));
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamQuery {
+    /// Comma-separated addresses.
+    addresses: Option<String>,
+    min_amount: Option<f64>,
+}
+
+impl StreamQuery {
+    fn filter(&self) -> StreamFilter {
+        StreamFilter {
+            addresses: self
+                .addresses
+                .as_deref()
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|address| !address.is_empty())
+                .map(str::to_string)
+                .collect(),
+            min_amount: self.min_amount,
+        }
+    }
+}
+
+/// Answers to filters sent over a WebSocket.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Reply {
+    Subscribed,
+    Error { message: String },
+}
+
+/// A WebSocket when the request asks for an upgrade, Server-Sent Events
+/// otherwise. Both end when shutdown begins.
+#[get("")]
+async fn subscribe(
+    request: HttpRequest,
+    body: web::Payload,
+    app_state: web::Data<AppState>,
+    query: web::Query<StreamQuery>,
+) -> Result<impl Responder, TransferError> {
+    let subscription = app_state.stream_service.subscribe(query.filter())?;
+    let heartbeat = app_state.stream_heartbeat;
+    let cancelled = app_state.shutdown.token();
+
+    if !is_websocket(&request) {
+        return Ok(HttpResponse::Ok()
+            .content_type("text/event-stream")
+            .insert_header((header::CACHE_CONTROL, "no-cache"))
+            .streaming(server_sent_events(subscription, heartbeat, cancelled)));
+    }
+
+    let (response, session, messages) = actix_ws::handle(&request, body)
+        .map_err(|err| TransferError::InvalidInput(err.to_string()))?;
+    actix_web::rt::spawn(websocket(
+        subscription,
+        session,
+        messages,
+        heartbeat,
+        cancelled,
+    ));
+    Ok(response)
+}
+
+fn is_websocket(request: &HttpRequest) -> bool {
+    request
+        .headers()
+        .get(header::UPGRADE)
+        .and_then(|upgrade| upgrade.to_str().ok())
+        .is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket"))
+}
+
+fn event_name(event: &StreamEvent) -> &'static str {
+    match event {
+        StreamEvent::Transfers { .. } => "transfers",
+        StreamEvent::Stats { .. } => "stats",
+        StreamEvent::Lagged { .. } => "lagged",
+    }
+}
+
+/// Events, with a keepalive comment every `heartbeat` so proxies don't
+/// time out a quiet connection.
+fn server_sent_events(
+    subscription: Subscription,
+    heartbeat: Duration,
+    cancelled: CancellationToken,
+) -> impl Stream<Item = Result<web::Bytes, BoxError>> {
+    let ticks = interval_at(Instant::now() + heartbeat, heartbeat);
+    stream::unfold(
+        (subscription, ticks, cancelled),
+        |(mut subscription, mut ticks, cancelled)| async move {
+            let chunk = tokio::select! {
+                event = subscription.next() => {
+                    let event = event?;
+                    sse_event(event_name(&event), &event).map_err(BoxError::from)
+                }
+                _ = ticks.tick() => Ok(web::Bytes::from_static(b": keepalive\n\n")),
+                _ = cancelled.cancelled() => return None,
+            };
+            Some((chunk, (subscription, ticks, cancelled)))
+        },
+    )
+}
+
+/// Sends events as JSON text frames and takes a `StreamFilter` object in
+/// any text frame to replace the filter. Pings every `heartbeat` and
+/// closes the connection when the client is silent for two of them.
+async fn websocket(
+    mut subscription: Subscription,
+    mut session: Session,
+    mut messages: MessageStream,
+    heartbeat: Duration,
+    cancelled: CancellationToken,
+) {
+    let mut ticks = interval_at(Instant::now() + heartbeat, heartbeat);
+    let mut last_heard = Instant::now();
+
+    let reason: Option<CloseReason> = loop {
+        let sent = tokio::select! {
+            event = subscription.next() => match event {
+                Some(event) => send(&mut session, &event).await,
+                None => break Some(CloseCode::Away.into()),
+            },
+            message = messages.recv() => {
+                last_heard = Instant::now();
+                match message {
+                    Some(Ok(Message::Text(text))) => {
+                        let reply = match serde_json::from_str(&text)
+                            .map_err(|err| TransferError::InvalidInput(err.to_string()))
+                            .and_then(|filter| subscription.set_filter(filter))
+                        {
+                            Ok(()) => Reply::Subscribed,
+                            Err(err) => Reply::Error {
+                                message: err.to_string(),
+                            },
+                        };
+                        send(&mut session, &reply).await
+                    }
+                    Some(Ok(Message::Ping(bytes))) => session.pong(&bytes).await.is_ok(),
+                    Some(Ok(Message::Close(reason))) => break reason,
+                    Some(Ok(_)) => true,
+                    Some(Err(_)) | None => break None,
+                }
+            }
+            _ = ticks.tick() => {
+                if last_heard.elapsed() > heartbeat * 2 {
+                    break Some(CloseCode::Away.into());
+                }
+                session.ping(b"").await.is_ok()
+            }
+            _ = cancelled.cancelled() => break Some(CloseCode::Restart.into()),
+        };
+        if !sent {
+            return;
+        }
+    };
+
+    let _ = session.close(reason).await;
+}
+
+/// Whether the frame went out; `false` once the connection is closed.
+async fn send<T: Serialize>(session: &mut Session, value: &T) -> bool {
+    match serde_json::to_string(value) {
+        Ok(text) => session.text(text).await.is_ok(),
+        Err(err) => {
+            log::warn!("Encoding a stream event failed: {err}");
+            true
+        }
+    }
+}
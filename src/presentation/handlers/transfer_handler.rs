@@ -0,0 +1,31 @@
+use actix_web::{HttpResponse, Responder, post, web};
+
+use crate::{
+    domain::{
+        entities::transfer::Transfer,
+        services::{errors::TransferError, ingest_service::Ingested},
+    },
+    presentation::shared::app_state::AppState,
+};
+
+pub fn transfer_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/api/v1/transfers").service(ingest));
+}
+
+/// `202 Accepted` once the batch is in the write buffer, `200 OK` once it
+/// is inserted when there is no buffer.
+#[post("")]
+async fn ingest(
+    app_state: web::Data<AppState>,
+    transfers: web::Json<Vec<Transfer>>,
+) -> Result<impl Responder, TransferError> {
+    let ingested = app_state
+        .ingest_service
+        .ingest(transfers.into_inner())
+        .await?;
+    let mut response = match ingested {
+        Ingested::Buffered { .. } => HttpResponse::Accepted(),
+        Ingested::Inserted { .. } => HttpResponse::Ok(),
+    };
+    Ok(response.json(ingested))
+}
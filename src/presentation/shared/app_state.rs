@@ -1,16 +1,37 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use crate::{
-    domain::services::stats_service::StatsService,
-    infrastructure::repositories::transfer_repo::ClickHouseTransferRepo,
+    config::runtime::RuntimeConfig,
+    domain::services::{
+        analysis_service::AnalysisService, cluster_service::ClusterService,
+        graph_service::GraphService, ingest_service::IngestService,
+        integrity_service::IntegrityService, label_service::LabelService,
+        market_service::MarketService, price_service::PriceService, stats_service::StatsService,
+        stream_service::StreamService,
+    },
+    infrastructure::{
+        repositories::{
+            label_repo::ClickHouseLabelRepo, price_repo::ClickHousePriceRepo,
+            resilient_transfer_repo::TransferRepo,
+        },
+        shutdown::Shutdown,
+        write_buffer::WriteBuffer,
+    },
 };
 
 pub struct AppState {
-    pub stats_service: Arc<StatsService<ClickHouseTransferRepo>>,
-}
-
-impl AppState {
-    pub fn new(stats_service: Arc<StatsService<ClickHouseTransferRepo>>) -> Self {
-        Self { stats_service }
-    }
+    pub stats_service: Arc<StatsService<TransferRepo>>,
+    pub graph_service: Arc<GraphService<TransferRepo>>,
+    pub analysis_service: Arc<AnalysisService<TransferRepo>>,
+    pub cluster_service: Arc<ClusterService<TransferRepo>>,
+    pub label_service: Arc<LabelService<ClickHouseLabelRepo>>,
+    pub price_service: Arc<PriceService<ClickHousePriceRepo>>,
+    pub market_service: Arc<MarketService<TransferRepo>>,
+    pub integrity_service: Arc<IntegrityService<TransferRepo>>,
+    pub ingest_service: Arc<IngestService<TransferRepo, WriteBuffer>>,
+    pub stream_service: Arc<StreamService<TransferRepo>>,
+    pub stream_heartbeat: Duration,
+    pub write_buffer: Option<Arc<WriteBuffer>>,
+    pub runtime: Arc<RuntimeConfig>,
+    pub shutdown: Arc<Shutdown>,
 }
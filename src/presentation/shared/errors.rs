@@ -3,36 +3,182 @@ use serde::Serialize;
 
 use crate::domain::{
     repositories::errors::TransferRepoError::{
-        DatabaseConnectionError, QueryError, TransferNotFound,
+        self, BadRequest, BufferFailed, BufferFull, DatabaseConnectionError, PartialInsert,
+        QueryError, TimedOut, TransferNotFound, ValidationError,
     },
     services::errors::TransferError,
+    validation::errors::Violation,
 };
 
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// RFC 7807 problem details with `code`, `retryable` and `violations`
+/// extension members.
 #[derive(Serialize)]
-struct ApiError {
-    message: String,
+struct ProblemDetails {
+    #[serde(rename = "type")]
+    problem_type: String,
+    title: String,
     status: u16,
+    detail: String,
+    code: &'static str,
+    retryable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    violations: Option<Vec<Violation>>,
+}
+
+impl ProblemDetails {
+    fn new(status: StatusCode, code: &'static str, detail: String, retryable: bool) -> Self {
+        Self {
+            problem_type: format!("/problems/{}", code),
+            title: status
+                .canonical_reason()
+                .unwrap_or("Unknown error")
+                .to_string(),
+            status: status.as_u16(),
+            detail,
+            code,
+            retryable,
+            violations: None,
+        }
+    }
+
+    fn with_violations(mut self, violations: Vec<Violation>) -> Self {
+        self.violations = Some(violations);
+        self
+    }
 }
 
-impl ApiError {
-    fn new(message: String, status: u16) -> Self {
-        Self { message, status }
+fn repo_status_code(error: &TransferRepoError) -> StatusCode {
+    match error {
+        DatabaseConnectionError(_) | BufferFull(_) => StatusCode::SERVICE_UNAVAILABLE,
+        TimedOut(_) => StatusCode::GATEWAY_TIMEOUT,
+        BadRequest(_) => StatusCode::BAD_REQUEST,
+        TransferNotFound { id: _ } => StatusCode::NOT_FOUND,
+        QueryError(_) | BufferFailed(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        ValidationError(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        PartialInsert { report: _, source } => repo_status_code(source),
     }
 }
 
 impl ResponseError for TransferError {
     fn status_code(&self) -> actix_web::http::StatusCode {
         match self {
-            TransferError::RepositoryError(transfer_repo_error) => match transfer_repo_error {
-                DatabaseConnectionError(_) => StatusCode::INTERNAL_SERVER_ERROR,
-                TransferNotFound { id: _ } => StatusCode::NOT_FOUND,
-                QueryError(_) => StatusCode::INTERNAL_SERVER_ERROR,
-            },
+            TransferError::RepositoryError(transfer_repo_error) => {
+                repo_status_code(transfer_repo_error)
+            }
+            TransferError::InvalidInput(_) => StatusCode::BAD_REQUEST,
+            TransferError::LabelNotFound { address: _ } => StatusCode::NOT_FOUND,
+            TransferError::PriceNotFound { token: _, ts: _ } => StatusCode::NOT_FOUND,
         }
     }
 
     fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
-        let response = ApiError::new(self.to_string(), self.status_code().into());
-        HttpResponse::build(self.status_code()).json(response)
+        let status = self.status_code();
+        let mut problem =
+            ProblemDetails::new(status, self.code(), self.to_string(), self.is_retryable());
+        if let TransferError::RepositoryError(ValidationError(error)) = self {
+            problem = problem.with_violations(error.violations.clone());
+        }
+
+        let mut response = HttpResponse::build(status);
+        response.content_type(PROBLEM_JSON);
+        if status == StatusCode::SERVICE_UNAVAILABLE {
+            response.insert_header(("Retry-After", "5"));
+        }
+        response.json(problem)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{
+        repositories::errors::TransferRepoError, validation::errors::ValidationError as Invalid,
+    };
+    use actix_web::body::to_bytes;
+    use serde_json::Value;
+
+    async fn problem_body(error: TransferError) -> (StatusCode, String, Value) {
+        let response = error.error_response();
+        let status = response.status();
+        let content_type = response
+            .headers()
+            .get("content-type")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = to_bytes(response.into_body()).await.unwrap();
+        (status, content_type, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[actix_web::test]
+    async fn test_unavailable_is_retryable_problem() {
+        let error = TransferError::from(TransferRepoError::DatabaseConnectionError(
+            "refused".to_string(),
+        ));
+
+        let (status, content_type, body) = problem_body(error).await;
+
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(content_type, PROBLEM_JSON);
+        assert_eq!(body["code"], "database_unavailable");
+        assert_eq!(body["retryable"], true);
+        assert_eq!(body["type"], "/problems/database_unavailable");
+        assert_eq!(body["title"], "Service Unavailable");
+    }
+
+    #[actix_web::test]
+    async fn test_status_mapping() {
+        let cases = [
+            (
+                TransferError::from(TransferRepoError::TimedOut("slow".into())),
+                StatusCode::GATEWAY_TIMEOUT,
+            ),
+            (
+                TransferError::from(TransferRepoError::BadRequest("params".into())),
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                TransferError::LabelNotFound {
+                    address: "0x1".into(),
+                },
+                StatusCode::NOT_FOUND,
+            ),
+            (
+                TransferError::from(TransferRepoError::BufferFull("1 GiB".into())),
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+            (
+                TransferError::from(TransferRepoError::QueryError("syntax".into())),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+            (
+                TransferError::from(TransferRepoError::PartialInsert {
+                    report: Default::default(),
+                    source: Box::new(TransferRepoError::DatabaseConnectionError("reset".into())),
+                }),
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+        ];
+
+        for (error, expected) in cases {
+            assert_eq!(error.status_code(), expected);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_validation_lists_violations() {
+        let error = TransferError::from(TransferRepoError::from(Invalid {
+            violations: vec![Violation::new("transfers[0].amount", "must be positive")],
+        }));
+
+        let (status, _, body) = problem_body(error).await;
+
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["code"], "validation_failed");
+        assert_eq!(body["retryable"], false);
+        assert_eq!(body["violations"][0]["field"], "transfers[0].amount");
     }
 }
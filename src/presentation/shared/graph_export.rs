@@ -0,0 +1,116 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use crate::domain::entities::graph::GraphEdge;
+
+pub fn to_graphml(edges: &[GraphEdge]) -> String {
+    let nodes: BTreeSet<&str> = edges
+        .iter()
+        .flat_map(|edge| [edge.from.as_str(), edge.to.as_str()])
+        .collect();
+
+    let mut out = String::new();
+    out.push_str(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    out.push('\n');
+    out.push_str(r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#);
+    out.push('\n');
+    out.push_str(
+        r#"  <key id="total_volume" for="edge" attr.name="total_volume" attr.type="double"/>"#,
+    );
+    out.push('\n');
+    out.push_str(
+        r#"  <key id="transfer_count" for="edge" attr.name="transfer_count" attr.type="long"/>"#,
+    );
+    out.push('\n');
+    out.push_str(r#"  <graph id="transfers" edgedefault="directed">"#);
+    out.push('\n');
+
+    for node in nodes {
+        let _ = writeln!(out, r#"    <node id="{}"/>"#, escape_xml(node));
+    }
+
+    for (idx, edge) in edges.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            r#"    <edge id="e{}" source="{}" target="{}">"#,
+            idx,
+            escape_xml(&edge.from),
+            escape_xml(&edge.to)
+        );
+        let _ = writeln!(
+            out,
+            r#"      <data key="total_volume">{}</data>"#,
+            edge.total_volume
+        );
+        let _ = writeln!(
+            out,
+            r#"      <data key="transfer_count">{}</data>"#,
+            edge.transfer_count
+        );
+        out.push_str("    </edge>\n");
+    }
+
+    out.push_str("  </graph>\n");
+    out.push_str("</graphml>\n");
+    out
+}
+
+fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_graphml_contains_nodes_and_edges() {
+        let edges = vec![
+            GraphEdge {
+                from: "0xA".to_string(),
+                to: "0xB".to_string(),
+                total_volume: 10.5,
+                transfer_count: 2,
+            },
+            GraphEdge {
+                from: "0xB".to_string(),
+                to: "0xA".to_string(),
+                total_volume: 3.0,
+                transfer_count: 1,
+            },
+        ];
+
+        let graphml = to_graphml(&edges);
+
+        assert_eq!(graphml.matches("<node ").count(), 2);
+        assert_eq!(graphml.matches("<edge ").count(), 2);
+        assert!(graphml.contains(r#"source="0xA" target="0xB""#));
+        assert!(graphml.contains(r#"<data key="total_volume">10.5</data>"#));
+    }
+
+    #[test]
+    fn test_graphml_escapes_addresses() {
+        let edges = vec![GraphEdge {
+            from: "a&b".to_string(),
+            to: "<c>".to_string(),
+            total_volume: 1.0,
+            transfer_count: 1,
+        }];
+
+        let graphml = to_graphml(&edges);
+
+        assert!(graphml.contains(r#"<node id="a&amp;b"/>"#));
+        assert!(graphml.contains(r#"<node id="&lt;c&gt;"/>"#));
+    }
+}
@@ -1,2 +1,4 @@
 pub mod app_state;
 pub mod errors;
+pub mod graph_export;
+pub mod streaming;
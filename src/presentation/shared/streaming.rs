@@ -0,0 +1,170 @@
+use std::error::Error;
+
+use actix_web::web::Bytes;
+use futures::{Stream, StreamExt, stream};
+use serde::Serialize;
+
+pub type BoxError = Box<dyn Error>;
+
+/// Rows encoded together into one body chunk, to keep the chunked
+/// transfer framing small relative to the payload.
+const ROWS_PER_CHUNK: usize = 256;
+
+/// Encodes `rows` as a single JSON array, written out as rows arrive.
+pub fn json_array<S, T, E>(rows: S) -> impl Stream<Item = Result<Bytes, BoxError>>
+where
+    S: Stream<Item = Result<T, E>>,
+    T: Serialize,
+    E: Into<BoxError>,
+{
+    let mut first = true;
+    let items = encode(rows, move |buf, row| {
+        if !std::mem::take(&mut first) {
+            buf.push(b',');
+        }
+        serde_json::to_writer(buf, row)
+    });
+
+    stream::once(async { Ok(Bytes::from_static(b"[")) })
+        .chain(items)
+        .chain(stream::once(async { Ok(Bytes::from_static(b"]")) }))
+}
+
+/// Encodes `rows` as newline-delimited JSON.
+pub fn ndjson<S, T, E>(rows: S) -> impl Stream<Item = Result<Bytes, BoxError>>
+where
+    S: Stream<Item = Result<T, E>>,
+    T: Serialize,
+    E: Into<BoxError>,
+{
+    encode(rows, |buf, row| {
+        serde_json::to_writer(&mut *buf, row)?;
+        buf.push(b'\n');
+        Ok(())
+    })
+}
+
+/// Encodes `data` as one Server-Sent Events message named `event`.
+pub fn sse_event<T: Serialize>(event: &str, data: &T) -> serde_json::Result<Bytes> {
+    // Compact JSON has no raw newlines, so it fits a single `data:` line.
+    let mut buf = format!("event: {event}\ndata: ").into_bytes();
+    serde_json::to_writer(&mut buf, data)?;
+    buf.extend_from_slice(b"\n\n");
+    Ok(Bytes::from(buf))
+}
+
+/// Batches up to `ROWS_PER_CHUNK` ready rows per chunk. Rows encoded before
+/// an error are still sent, then the error ends the body.
+fn encode<S, T, E>(
+    rows: S,
+    mut write: impl FnMut(&mut Vec<u8>, &T) -> serde_json::Result<()>,
+) -> impl Stream<Item = Result<Bytes, BoxError>>
+where
+    S: Stream<Item = Result<T, E>>,
+    T: Serialize,
+    E: Into<BoxError>,
+{
+    rows.ready_chunks(ROWS_PER_CHUNK).flat_map(move |batch| {
+        let mut buf = Vec::new();
+        let mut error = None;
+        for row in batch {
+            match row
+                .map_err(Into::into)
+                .and_then(|row| write(&mut buf, &row).map_err(BoxError::from))
+            {
+                Ok(()) => {}
+                Err(e) => {
+                    error = Some(e);
+                    break;
+                }
+            }
+        }
+
+        let mut items = Vec::with_capacity(2);
+        if !buf.is_empty() {
+            items.push(Ok(Bytes::from(buf)));
+        }
+        if let Some(error) = error {
+            items.push(Err(error));
+        }
+        stream::iter(items)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt;
+
+    #[derive(Debug)]
+    struct TestError;
+
+    impl fmt::Display for TestError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "broken")
+        }
+    }
+
+    impl Error for TestError {}
+
+    async fn body<S>(chunks: S) -> (String, bool)
+    where
+        S: Stream<Item = Result<Bytes, BoxError>>,
+    {
+        let chunks: Vec<_> = chunks.collect().await;
+        let failed = chunks.iter().any(Result::is_err);
+        let text = chunks
+            .into_iter()
+            .filter_map(Result::ok)
+            .map(|bytes| String::from_utf8(bytes.to_vec()).unwrap())
+            .collect();
+        (text, failed)
+    }
+
+    fn rows(values: Vec<u32>) -> impl Stream<Item = Result<u32, TestError>> {
+        stream::iter(values.into_iter().map(Ok))
+    }
+
+    #[actix_web::test]
+    async fn test_json_array() {
+        let (text, failed) = body(json_array(rows(vec![1, 2, 3]))).await;
+        assert_eq!(text, "[1,2,3]");
+        assert!(!failed);
+
+        let (text, _) = body(json_array(rows(vec![]))).await;
+        assert_eq!(text, "[]");
+    }
+
+    #[actix_web::test]
+    async fn test_json_array_spanning_chunks() {
+        let values: Vec<u32> = (0..600).collect();
+
+        let (text, _) = body(json_array(rows(values.clone()))).await;
+
+        let parsed: Vec<u32> = serde_json::from_str(&text).unwrap();
+        assert_eq!(parsed, values);
+    }
+
+    #[actix_web::test]
+    async fn test_ndjson() {
+        let (text, _) = body(ndjson(rows(vec![1, 2]))).await;
+        assert_eq!(text, "1\n2\n");
+    }
+
+    #[test]
+    fn test_sse_event() {
+        let event = sse_event("stats", &serde_json::json!({"note": "a\nb"})).unwrap();
+
+        assert_eq!(event, "event: stats\ndata: {\"note\":\"a\\nb\"}\n\n");
+    }
+
+    #[actix_web::test]
+    async fn test_error_ends_body_after_encoded_rows() {
+        let rows = stream::iter(vec![Ok(1), Err(TestError), Ok(3)]);
+
+        let (text, failed) = body(ndjson(rows)).await;
+
+        assert_eq!(text, "1\n");
+        assert!(failed);
+    }
+}
@@ -1,15 +1,36 @@
-use rust_challenge::{config::Config, run};
+use rust_challenge::{
+    config::{ClickHouseSettings, Config, JobsSettings, ServerSettings},
+    domain::{
+        entities::{price::PricePoint, transfer::Transfer},
+        repositories::{price_repo::PriceRepoAbstract, transfer_repo::TransferRepoAbstract},
+    },
+    infrastructure::{
+        clickhouse::{db_connection::create_client, schema::Schema},
+        repositories::{price_repo::ClickHousePriceRepo, transfer_repo::ClickHouseTransferRepo},
+    },
+    run,
+};
 use serde_json::Value;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 fn create_test_config() -> Config {
     Config {
-        port: "8081".to_string(),
-        clickhouse_url: std::env::var("CLICKHOUSE_URL")
-            .unwrap_or_else(|_| "http://localhost:8123".to_string()),
-        clickhouse_user: "default".to_string(),
-        clickhouse_password: "123".to_string(),
-        data_generation_count: 30,
+        server: ServerSettings {
+            port: 8081,
+            ..Default::default()
+        },
+        clickhouse: ClickHouseSettings {
+            url: std::env::var("CLICKHOUSE_URL")
+                .unwrap_or_else(|_| "http://localhost:8123".to_string()),
+            user: "default".to_string(),
+            password: "123".to_string(),
+            ..Default::default()
+        },
+        jobs: JobsSettings {
+            data_generation_count: 30,
+            ..Default::default()
+        },
+        ..Default::default()
     }
 }
 
@@ -38,7 +59,7 @@ async fn make_request(
 #[ignore]
 async fn test_integration() {
     let config = create_test_config();
-    let port = config.port.clone();
+    let port = config.server.port.to_string();
 
     actix_web::rt::spawn(async move {
         if let Err(e) = run(&config).await {
@@ -100,9 +121,60 @@ async fn test_config() {
 
     let config = create_test_config();
 
-    assert_eq!(config.port, "8081");
-    assert!(!config.clickhouse_url.is_empty());
-    assert!(config.data_generation_count > 0);
+    assert_eq!(config.server.port, 8081);
+    assert!(!config.clickhouse.url.is_empty());
+    assert!(config.jobs.data_generation_count > 0);
+    assert!(config.validate().is_ok());
 
     println!("Config test passed");
 }
+
+#[actix_web::test]
+#[ignore]
+async fn test_oracle_stats_before_first_price() {
+    let config = create_test_config();
+    let client = create_client(&config.clickhouse).expect("client");
+    let run_id = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis();
+    let schema = Schema::new(
+        config.clickhouse.database.clone(),
+        Some(format!("oracle_{run_id}_")),
+        None,
+    )
+    .expect("schema");
+    let transfers = ClickHouseTransferRepo::with_schema(client.clone(), schema.clone());
+    let prices = ClickHousePriceRepo::with_schema(client, schema);
+    transfers.create_table().await.expect("transfers table");
+    prices.create_table().await.expect("prices table");
+
+    let transfer = |ts| Transfer {
+        ts,
+        from: "0xaaa".to_string(),
+        to: "0xbbb".to_string(),
+        amount: 10.0,
+        usd_price: 2.0,
+    };
+    transfers
+        .save_all(&[transfer(1_000), transfer(3_000)])
+        .await
+        .expect("insert transfers");
+    prices
+        .save_all(&[PricePoint {
+            token: "TKN".to_string(),
+            ts: 2_000,
+            price: 5.0,
+        }])
+        .await
+        .expect("insert prices");
+
+    let stats = transfers
+        .calculate_user_stats_with_prices("TKN")
+        .await
+        .expect("stats");
+
+    // The transfer before the first price keeps its own 2.0 instead of 0.
+    let buyer = stats.iter().find(|row| row.address == "0xbbb").unwrap();
+    assert!((buyer.avg_buy_price - 3.5).abs() < 1e-9);
+}